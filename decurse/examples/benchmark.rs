@@ -1,4 +1,4 @@
-use owning_ref::VecRef;
+use decurse::view::RcSlice;
 use std::time::{Duration, Instant};
 
 fn slow(v: u32) -> u32 {
@@ -17,14 +17,14 @@ fn slow(v: u32) -> u32 {
 }
 
 #[decurse::decurse_unsound]
-fn linear_search<T>(slice: VecRef<T, [T]>, find: T) -> usize
+fn linear_search<T>(slice: RcSlice<T>, find: T) -> usize
 where
 	T: Ord + 'static,
 {
 	assert!(slow(8723) < 10000);
 	if let Some(first) = slice.first() {
 		match find.cmp(first) {
-			std::cmp::Ordering::Greater => linear_search(slice.map(|s| &s[1..]), find) + 1,
+			std::cmp::Ordering::Greater => linear_search(slice.slice(1..), find) + 1,
 			_ => 0,
 		}
 	} else {
@@ -32,14 +32,14 @@ where
 	}
 }
 
-fn stack_linear_search<T>(slice: VecRef<T, [T]>, find: T) -> usize
+fn stack_linear_search<T>(slice: RcSlice<T>, find: T) -> usize
 where
 	T: Ord + 'static,
 {
 	assert!(slow(8723) < 10000);
 	if let Some(first) = slice.first() {
 		match find.cmp(first) {
-			std::cmp::Ordering::Greater => stack_linear_search(slice.map(|s| &s[1..]), find) + 1,
+			std::cmp::Ordering::Greater => stack_linear_search(slice.slice(1..), find) + 1,
 			_ => 0,
 		}
 	} else {
@@ -51,14 +51,13 @@ fn run(h: i32) -> Duration {
 	let vecs: Vec<_> = (0..1000)
 		.map(|_| {
 			let arr: Vec<i32> = (0..h).map(|x| x * 2).collect();
-			arr
+			RcSlice::from(arr)
 		})
 		.collect();
 
 	let start = Instant::now();
-	for vec in vecs.into_iter() {
-		let or = VecRef::new(vec);
-		assert_eq!(linear_search(or, h * 8 / 5), (h * 4 / 5) as usize);
+	for slice in vecs.into_iter() {
+		assert_eq!(linear_search(slice, h * 8 / 5), (h * 4 / 5) as usize);
 	}
 	start.elapsed()
 }
@@ -67,14 +66,13 @@ fn run_stack(h: i32) -> Duration {
 	let vecs: Vec<_> = (0..1000)
 		.map(|_| {
 			let arr: Vec<i32> = (0..h).map(|x| x * 2).collect();
-			arr
+			RcSlice::from(arr)
 		})
 		.collect();
 
 	let start = Instant::now();
-	for vec in vecs.into_iter() {
-		let or = VecRef::new(vec);
-		assert_eq!(stack_linear_search(or, h * 8 / 5), (h * 4 / 5) as usize);
+	for slice in vecs.into_iter() {
+		assert_eq!(stack_linear_search(slice, h * 8 / 5), (h * 4 / 5) as usize);
 	}
 	start.elapsed()
 }
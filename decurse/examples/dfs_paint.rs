@@ -1,3 +1,5 @@
+use decurse::State;
+
 #[derive(Clone, Copy, Debug)]
 enum Tile {
 	Unvisited,
@@ -8,32 +10,27 @@ enum Tile {
 type World = Vec<Vec<Tile>>;
 
 // Mark all the reachable tile as Visited.
-// Decurse doesn't support passing borrows, so you have to pass the Vec around.
+// Decurse doesn't support passing borrows, so the world is shared through `decurse::State`.
+// `state(World)` makes the function take a `&mut World` first, which the frames access with `State::with`.
 // See the dfs_paint_with_decurse_unsound example for version using borrow.
 // ↓↓ Try removing this, you will get stack overflow.
-#[decurse::decurse]
-fn dfs_paint(mut world: World, (x, y): (isize, isize)) -> World {
-	let here = &mut world[x as usize][y as usize];
-	*here = Tile::Visited;
+#[decurse::decurse(state(World))]
+fn dfs_paint((x, y): (isize, isize)) {
+	State::<World>::with(|world| world[x as usize][y as usize] = Tile::Visited);
 	const CHANGE: [isize; 3] = [1, 0, -1];
 	for di in &CHANGE {
-		let i = x + *di;
-		if (i >= world.len() as isize) || (i < 0) {
-			continue;
-		}
 		for dj in &CHANGE {
-			let j = y + *dj;
-			if (j >= world[i as usize].len() as isize) || (j < 0) {
-				continue;
-			}
-			let neighbor = &mut world[i as usize][j as usize];
-			match neighbor {
-				Tile::Unvisited => world = dfs_paint(world, (i, j)),
-				_ => {}
+			let (i, j) = (x + *di, y + *dj);
+			// Negative indices wrap around to out of bounds. The borrow ends before the recursive call.
+			let unvisited = State::<World>::with(|world| {
+				let tile = world.get(i as usize).and_then(|row| row.get(j as usize));
+				matches!(tile, Some(Tile::Unvisited))
+			});
+			if unvisited {
+				dfs_paint((i, j));
 			}
 		}
 	}
-	world
 }
 
 fn main() {
@@ -47,7 +44,7 @@ fn main() {
 	world[HALF].fill(Tile::Impassable);
 
 	// Paint from (0, 0).
-	world = dfs_paint(world, (0, 0));
+	dfs_paint(&mut world, (0, 0));
 
 	// Expect the half above the wall to be all visited.
 	let first_half_visited = world[..HALF]
@@ -0,0 +1,64 @@
+use decurse::{graph::Dfs, walk::Control};
+use std::cell::RefCell;
+
+#[derive(Clone, Copy, Debug)]
+enum Tile {
+	Unvisited,
+	Visited,
+	Impassable,
+}
+
+type World = Vec<Vec<Tile>>;
+
+// The same flood fill as the dfs_paint example, without writing the recursion.
+// The grid is an implicit graph: `Dfs` asks for the neighbours of a tile, and keeps track of the visited ones itself.
+fn dfs_paint(world: &mut World, start: (isize, isize)) {
+	let world = RefCell::new(world);
+	let mut dfs = Dfs::new(|&(x, y): &(isize, isize)| {
+		const CHANGE: [isize; 3] = [1, 0, -1];
+		let world = world.borrow();
+		let mut next = Vec::new();
+		for di in &CHANGE {
+			for dj in &CHANGE {
+				let (i, j) = (x + *di, y + *dj);
+				// Negative indices wrap around to out of bounds.
+				let tile = world.get(i as usize).and_then(|row| row.get(j as usize));
+				if matches!(tile, Some(Tile::Unvisited)) {
+					next.push((i, j));
+				}
+			}
+		}
+		next
+	})
+	.discover(|&(x, y)| {
+		world.borrow_mut()[x as usize][y as usize] = Tile::Visited;
+		Control::Continue
+	});
+	dfs.run(start);
+}
+
+fn main() {
+	const SIZE: usize = 1000;
+	const HALF: usize = SIZE / 2;
+
+	// Start off with an empty unvisited world.
+	let mut world = vec![vec![Tile::Unvisited; SIZE]; SIZE];
+
+	// Create a wall of Impassable in the middle.
+	world[HALF].fill(Tile::Impassable);
+
+	// Paint from (0, 0).
+	dfs_paint(&mut world, (0, 0));
+
+	// Expect the half above the wall to be all visited.
+	let first_half_visited = world[..HALF]
+		.iter()
+		.all(|row| row.iter().all(|v| matches!(v, Tile::Visited)));
+	assert!(first_half_visited);
+
+	// Expect the half below the wall to be all unvisited.
+	let last_half_unvisited = world[(HALF + 1)..]
+		.iter()
+		.all(|row| row.iter().all(|v| matches!(v, Tile::Unvisited)));
+	assert!(last_half_unvisited);
+}
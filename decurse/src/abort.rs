@@ -0,0 +1,62 @@
+use std::{
+	error::Error,
+	fmt::{self, Display},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Arc,
+	},
+};
+
+/// Handle for cancelling a running decursed computation, possibly from another thread.
+///
+/// Create one before calling the `_abortable` variant of a decursed function
+/// (generated by `#[decurse(abortable)]`), keep a clone, and call [`AbortHandle::abort`] on it.
+/// The executor checks the handle between frame polls.
+/// Once it sees the abort, it drops all the frames stored on the heap and returns [`Cancelled`].
+///
+/// ```
+/// #[decurse::decurse(abortable)]
+/// fn count_down(x: u64) -> u64 {
+///     if x == 0 {
+///         0
+///     } else {
+///         count_down(x - 1) + 1
+///     }
+/// }
+///
+/// let handle = decurse::AbortHandle::new();
+/// assert_eq!(count_down_abortable(10, handle.clone()), Ok(10));
+/// handle.abort();
+/// assert_eq!(count_down_abortable(10, handle), Err(decurse::Cancelled));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AbortHandle {
+	aborted: Arc<AtomicBool>,
+}
+
+impl AbortHandle {
+	/// Create a new handle that is not aborted.
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/// Request cancellation of every computation using this handle (or a clone of it).
+	pub fn abort(&self) {
+		self.aborted.store(true, Ordering::Relaxed);
+	}
+	/// Whether [`AbortHandle::abort`] has been called on this handle (or a clone of it).
+	pub fn is_aborted(&self) -> bool {
+		self.aborted.load(Ordering::Relaxed)
+	}
+}
+
+/// Error returned by the `_abortable` variant of a decursed function when its [`AbortHandle`] was aborted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cancelled;
+
+impl Display for Cancelled {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("decursed computation was cancelled")
+	}
+}
+
+impl Error for Cancelled {}
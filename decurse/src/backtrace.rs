@@ -0,0 +1,189 @@
+use crate::{execute, introspect, StackFrame};
+use std::{
+	any::Any,
+	cell::RefCell,
+	error::Error,
+	fmt,
+	future::Future,
+	panic::{self, AssertUnwindSafe},
+	sync::{
+		atomic::{AtomicBool, Ordering},
+		Once,
+	},
+};
+
+// Runs of the same function longer than this many frames at each end are collapsed.
+const SHOWN_AT_ENDS: usize = 2;
+
+static HOOK: Once = Once::new();
+static PRINT: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+	// The logical stack at the last panic on this thread.
+	static CAPTURED: RefCell<Option<Backtrace>> = const { RefCell::new(None) };
+}
+
+/// The live frames of decursed functions, like [`stack`](crate::stack), formatted like a backtrace.
+///
+/// The innermost frame is shown first, with its depth.
+/// Long runs of frames of the same function are collapsed.
+#[derive(Clone, Debug)]
+pub struct Backtrace {
+	frames: Vec<StackFrame>,
+}
+
+impl Backtrace {
+	/// The logical stack of the current thread.
+	pub fn capture() -> Self {
+		Self {
+			frames: introspect::stack(),
+		}
+	}
+	/// The frames, outermost first.
+	pub fn frames(&self) -> &[StackFrame] {
+		&self.frames
+	}
+}
+
+impl fmt::Display for Backtrace {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(f, "decurse logical stack ({} frames):", self.frames.len())?;
+		let mut depth = self.frames.len();
+		let mut innermost = self.frames.iter().rev().peekable();
+		while let Some(frame) = innermost.next() {
+			// Take the whole run of frames of the same function.
+			let mut run = vec![frame];
+			while let Some(next) = innermost.next_if(|next| next.name() == frame.name()) {
+				run.push(next);
+			}
+			let collapsed = run.len().saturating_sub(2 * SHOWN_AT_ENDS);
+			for (i, frame) in run.iter().enumerate() {
+				if collapsed > 0 && i >= SHOWN_AT_ENDS && i < run.len() - SHOWN_AT_ENDS {
+					if i == SHOWN_AT_ENDS {
+						writeln!(
+							f,
+							"{:>8}  ... {} more frames of {}",
+							"",
+							collapsed,
+							frame.name()
+						)?;
+					}
+				} else {
+					writeln!(f, "{:>8}: {}", depth, frame)?;
+				}
+				depth -= 1;
+			}
+		}
+		Ok(())
+	}
+}
+
+// Install our hook on top of the current one, once.
+fn ensure_hook() {
+	HOOK.call_once(|| {
+		let previous = panic::take_hook();
+		panic::set_hook(Box::new(move |info| {
+			previous(info);
+			let backtrace = CAPTURED.try_with(|captured| {
+				let backtrace = Backtrace::capture();
+				if let Ok(mut captured) = captured.try_borrow_mut() {
+					*captured = Some(backtrace.clone());
+				}
+				backtrace
+			});
+			if let Ok(backtrace) = backtrace {
+				if PRINT.load(Ordering::Relaxed) && !backtrace.frames.is_empty() {
+					eprint!("{}", backtrace);
+				}
+			}
+		}));
+	});
+}
+
+/// Install a panic hook that prints the logical stack (see [`Backtrace`]) after the panic message,
+/// when a panic happens inside decursed functions.
+///
+/// The hook calls the hook that was set before it first.
+pub fn install_panic_hook() {
+	ensure_hook();
+	PRINT.store(true, Ordering::Relaxed);
+}
+
+/// A panic caught by [`try_execute`].
+pub struct Panicked {
+	payload: Box<dyn Any + Send>,
+	backtrace: Backtrace,
+}
+
+impl Panicked {
+	/// The panic message, if the payload is a string (as it is for `panic!`).
+	pub fn message(&self) -> Option<&str> {
+		match self.payload.downcast_ref::<&'static str>() {
+			Some(message) => Some(message),
+			None => self.payload.downcast_ref::<String>().map(String::as_str),
+		}
+	}
+	/// The logical stack at the time of the panic.
+	pub fn backtrace(&self) -> &Backtrace {
+		&self.backtrace
+	}
+	/// The panic payload, e.g. to resume the panic with [`std::panic::resume_unwind`].
+	pub fn into_payload(self) -> Box<dyn Any + Send> {
+		self.payload
+	}
+}
+
+impl fmt::Debug for Panicked {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Panicked")
+			.field("message", &self.message())
+			.field("backtrace", &self.backtrace)
+			.finish()
+	}
+}
+
+impl fmt::Display for Panicked {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"decursed computation panicked: {}",
+			self.message().unwrap_or("Box<dyn Any>")
+		)?;
+		write!(f, "{}", self.backtrace)
+	}
+}
+
+impl Error for Panicked {}
+
+/// Like [`execute`], but catches panics, returning the payload along with the logical stack at the panic.
+///
+/// This installs a panic hook (on top of the existing one) to capture the stack,
+/// but doesn't make it print anything. See [`install_panic_hook`] for that.
+///
+/// ```
+/// #[decurse::decurse]
+/// fn count_down(x: u32) -> u32 {
+///     if x == 0 {
+///         panic!("reached zero")
+///     } else {
+///         count_down(x - 1)
+///     }
+/// }
+///
+/// let panicked = decurse::try_execute(async { count_down(100000) }).unwrap_err();
+/// assert_eq!(panicked.message(), Some("reached zero"));
+/// assert_eq!(panicked.backtrace().frames().len(), 100001);
+/// ```
+pub fn try_execute<F>(fut: F) -> Result<F::Output, Panicked>
+where
+	F: Future + 'static,
+{
+	ensure_hook();
+	CAPTURED.with(|captured| captured.borrow_mut().take());
+	panic::catch_unwind(AssertUnwindSafe(|| execute(fut))).map_err(|payload| Panicked {
+		payload,
+		backtrace: CAPTURED
+			.with(|captured| captured.borrow_mut().take())
+			.unwrap_or(Backtrace { frames: Vec::new() }),
+	})
+}
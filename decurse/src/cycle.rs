@@ -0,0 +1,102 @@
+use std::{
+	any::{Any, TypeId},
+	cell::RefCell,
+	collections::{HashMap, HashSet},
+	fmt::{Debug, Write},
+	hash::Hash,
+	rc::Rc,
+};
+
+// Type-erased cycle detection used by `Recursion`.
+pub(crate) trait Cycles<A> {
+	// Record that a frame with these arguments is live, returning its position.
+	// Panics if an ancestor frame has the same arguments.
+	fn enter(&self, args: &A) -> usize;
+	// Record that the frame at this position (and everything above it) is gone.
+	fn exit(&self, position: usize);
+}
+
+// The arguments of the live frames of one recursion.
+pub(crate) struct Tracker<A> {
+	live: RefCell<HashSet<A>>,
+	chain: RefCell<Vec<A>>,
+}
+
+impl<A> Tracker<A> {
+	pub(crate) fn new() -> Self {
+		Self {
+			live: RefCell::new(HashSet::new()),
+			chain: RefCell::new(Vec::new()),
+		}
+	}
+}
+
+impl<A: Hash + Eq + Clone + Debug> Cycles<A> for Tracker<A> {
+	fn enter(&self, args: &A) -> usize {
+		let mut chain = self.chain.borrow_mut();
+		if self.live.borrow().contains(args) {
+			// UNWRAP Safety: Everything in `live` is in `chain`.
+			let start = chain.iter().position(|a| a == args).unwrap();
+			let mut message =
+				String::from("Decurse: infinite recursion detected, arguments repeat: ");
+			for a in &chain[start..] {
+				let _ = write!(message, "{:?} -> ", a);
+			}
+			let _ = write!(message, "{:?}", args);
+			drop(chain);
+			panic!("{}", message);
+		}
+		self.live.borrow_mut().insert(args.clone());
+		chain.push(args.clone());
+		chain.len() - 1
+	}
+	fn exit(&self, position: usize) {
+		let mut chain = self.chain.borrow_mut();
+		let mut live = self.live.borrow_mut();
+		for a in chain.drain(position..) {
+			live.remove(&a);
+		}
+	}
+}
+
+thread_local! {
+	// The trackers shared by body type, see `shared`.
+	static SHARED: RefCell<HashMap<TypeId, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+// The tracker of the recursions with this body type on this thread,
+// so that a function is tracked across the recursions made for calls into it from other functions.
+pub(crate) fn shared<A: Hash + Eq + Clone + Debug + 'static>(
+	body_type: TypeId,
+) -> Rc<dyn Cycles<A>> {
+	let tracker = SHARED.with(|shared| {
+		shared
+			.borrow_mut()
+			.entry(body_type)
+			.or_insert_with(|| Rc::new(Tracker::<A>::new()))
+			.clone()
+	});
+	// UNWRAP Safety: The body type determines the argument type.
+	tracker.downcast::<Tracker<A>>().ok().unwrap()
+}
+
+// Keeps a frame's arguments recorded as live until the frame completes or is dropped.
+pub(crate) struct LiveGuard<A> {
+	cycles: Rc<dyn Cycles<A>>,
+	position: usize,
+}
+
+impl<A> LiveGuard<A> {
+	pub(crate) fn enter(cycles: &Rc<dyn Cycles<A>>, args: &A) -> Self {
+		Self {
+			position: cycles.enter(args),
+			cycles: cycles.clone(),
+		}
+	}
+}
+
+impl<A> Drop for LiveGuard<A> {
+	fn drop(&mut self) {
+		self.cycles.exit(self.position);
+	}
+}
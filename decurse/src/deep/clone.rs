@@ -0,0 +1,25 @@
+use super::{Scope, ScopedCall};
+
+/// Cloning without recursion, implemented by `#[derive(DeepClone)]`.
+///
+/// The derive also implements `Clone` for the type, which runs [`DeepClone::deep_clone`] on a new [`Scope`].
+/// Children are cloned as frames of the scope, and other fields with their own `Clone`.
+/// `Rc` and `Arc` children are shared rather than cloned, as with `Clone`.
+///
+/// ```
+/// #[derive(decurse::DeepClone, decurse::DeepDrop)]
+/// enum Json {
+///     Null,
+///     Array(Vec<Json>),
+/// }
+///
+/// let mut json = Json::Null;
+/// for _ in 0..100000 {
+///     json = Json::Array(vec![json]);
+/// }
+/// let cloned = json.clone();
+/// ```
+pub trait DeepClone: Sized {
+	/// A frame of the scope that clones `self`.
+	fn deep_clone<'a>(&'a self, scope: &Scope<'a>) -> ScopedCall<'a, Self>;
+}
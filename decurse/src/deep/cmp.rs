@@ -0,0 +1,49 @@
+use super::{Scope, ScopedCall};
+use std::cmp::Ordering;
+
+/// Equality without recursion, implemented by `#[derive(DeepPartialEq)]`.
+///
+/// The derive also implements `PartialEq` for the type, which runs [`DeepPartialEq::deep_eq`] on a new [`Scope`].
+/// Fields are compared in order and the comparison stops at the first difference, as with `#[derive(PartialEq)]`.
+/// Children are compared as frames of the scope, and other fields with their own `PartialEq`.
+/// `#[derive(DeepEq)]` implements `Eq`, which has nothing to recurse into.
+///
+/// ```
+/// #[derive(decurse::DeepPartialEq, decurse::DeepEq, decurse::DeepDrop)]
+/// enum Json {
+///     Null,
+///     Array(Vec<Json>),
+/// }
+///
+/// let (mut a, mut b) = (Json::Null, Json::Null);
+/// for _ in 0..100000 {
+///     a = Json::Array(vec![a]);
+///     b = Json::Array(vec![b]);
+/// }
+/// assert!(a == b);
+/// ```
+pub trait DeepPartialEq {
+	/// A frame of the scope that checks whether `self` and `other` are equal.
+	fn deep_eq<'a>(&'a self, other: &'a Self, scope: &Scope<'a>) -> ScopedCall<'a, bool>;
+}
+
+/// Partial ordering without recursion, implemented by `#[derive(DeepPartialOrd)]`.
+///
+/// The derive also implements `PartialOrd`, ordering like `#[derive(PartialOrd)]`:
+/// variants by their discriminants, then fields lexicographically in order.
+pub trait DeepPartialOrd {
+	/// A frame of the scope that compares `self` and `other`.
+	fn deep_partial_cmp<'a>(
+		&'a self,
+		other: &'a Self,
+		scope: &Scope<'a>,
+	) -> ScopedCall<'a, Option<Ordering>>;
+}
+
+/// Total ordering without recursion, implemented by `#[derive(DeepOrd)]`.
+///
+/// The derive also implements `Ord`, ordering like `#[derive(Ord)]`.
+pub trait DeepOrd {
+	/// A frame of the scope that compares `self` and `other`.
+	fn deep_cmp<'a>(&'a self, other: &'a Self, scope: &Scope<'a>) -> ScopedCall<'a, Ordering>;
+}
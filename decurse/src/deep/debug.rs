@@ -0,0 +1,204 @@
+use super::{Scope, ScopedCall};
+use std::{
+	cell::RefCell,
+	fmt::{self, Debug, Formatter, Write},
+};
+
+/// Debug formatting without recursion, implemented by `#[derive(DeepDebug)]`.
+///
+/// The derive also implements `Debug` for the type, which runs [`DeepDebug::deep_fmt`] on a new [`Scope`].
+/// The output is the same as with `#[derive(Debug)]`, pretty printing with `{:#?}` included.
+/// Children are formatted as frames of the scope, and other fields with their own `Debug`.
+/// Indented fields are formatted with `{:#?}`, any other options of the formatter only apply to the fields that aren't indented.
+///
+/// ```
+/// #[derive(decurse::DeepDebug)]
+/// struct Node {
+///     value: u32,
+///     next: Option<Box<Node>>,
+/// }
+///
+/// let list = Node { value: 1, next: Some(Box::new(Node { value: 2, next: None })) };
+/// assert_eq!(
+///     format!("{:?}", list),
+///     "Node { value: 1, next: Some(Node { value: 2, next: None }) }",
+/// );
+/// ```
+pub trait DeepDebug {
+	/// A frame of the scope that writes `self` to the output.
+	fn deep_fmt<'a>(
+		&'a self,
+		out: &'a DebugOutput<'a>,
+		scope: &Scope<'a>,
+	) -> ScopedCall<'a, fmt::Result>;
+}
+
+// A `Formatter`, without its lifetime.
+trait Sink {
+	fn write_str(&mut self, s: &str) -> fmt::Result;
+	fn debug(&mut self, value: &dyn Debug) -> fmt::Result;
+}
+
+impl Sink for Formatter<'_> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		Formatter::write_str(self, s)
+	}
+	fn debug(&mut self, value: &dyn Debug) -> fmt::Result {
+		value.fmt(self)
+	}
+}
+
+struct Output<'a> {
+	f: &'a mut (dyn Sink + 'a),
+	alternate: bool,
+	// How many levels the lines are indented by, when pretty printing.
+	depth: usize,
+	on_newline: bool,
+}
+
+// Indents the lines like the std builders do when pretty printing.
+impl Write for Output<'_> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		for line in s.split_inclusive('\n') {
+			if self.on_newline {
+				for _ in 0..self.depth {
+					self.f.write_str("    ")?;
+				}
+			}
+			self.on_newline = line.ends_with('\n');
+			self.f.write_str(line)?;
+		}
+		Ok(())
+	}
+}
+
+/// The formatter that [`DeepDebug`] frames write to.
+///
+/// Write structs, tuples and collections with [`Entries`], which keeps track of the indentation.
+pub struct DebugOutput<'a> {
+	output: RefCell<Output<'a>>,
+}
+
+impl<'a> DebugOutput<'a> {
+	/// Write to the formatter, with its `#` option.
+	pub fn new(f: &'a mut Formatter<'_>) -> Self {
+		let alternate = f.alternate();
+		Self {
+			output: RefCell::new(Output {
+				f,
+				alternate,
+				depth: 0,
+				on_newline: false,
+			}),
+		}
+	}
+	/// Write a string, like [`Formatter::write_str`].
+	pub fn write_str(&self, s: &str) -> fmt::Result {
+		self.output.borrow_mut().write_str(s)
+	}
+	/// Write a value with its own `Debug`.
+	pub fn value(&self, value: &dyn Debug) -> fmt::Result {
+		let mut output = self.output.borrow_mut();
+		if output.depth == 0 {
+			output.f.debug(value)
+		} else {
+			write!(output, "{:#?}", value)
+		}
+	}
+}
+
+/// The fields of a struct or tuple, or the entries of a collection, being written to a [`DebugOutput`].
+///
+/// Call [`Entries::entry`] before writing each one, then [`Entries::finish`].
+pub struct Entries {
+	open: &'static str,
+	close: &'static str,
+	// What's written if there are no entries.
+	empty: &'static str,
+	// Whether there's a space inside the brackets.
+	spaced: bool,
+	// Whether a single entry is followed by a comma, as in `(1,)`.
+	comma: bool,
+	count: usize,
+}
+
+impl Entries {
+	fn new(open: &'static str, close: &'static str, empty: &'static str) -> Self {
+		Self {
+			open,
+			close,
+			empty,
+			spaced: false,
+			comma: false,
+			count: 0,
+		}
+	}
+	/// Named fields, written after the name like [`Formatter::debug_struct`].
+	pub fn fields() -> Self {
+		Self {
+			spaced: true,
+			..Self::new(" {", "}", "")
+		}
+	}
+	/// Unnamed fields, written after the name like [`Formatter::debug_tuple`].
+	pub fn tuple() -> Self {
+		Self::new("(", ")", "")
+	}
+	/// The elements of a tuple.
+	pub fn anonymous_tuple() -> Self {
+		Self {
+			comma: true,
+			..Self::new("(", ")", "()")
+		}
+	}
+	/// Like [`Formatter::debug_list`].
+	pub fn list() -> Self {
+		Self::new("[", "]", "[]")
+	}
+	/// Like [`Formatter::debug_set`] and [`Formatter::debug_map`].
+	/// Write the key, `": "` and the value as an entry of a map.
+	pub fn set() -> Self {
+		Self::new("{", "}", "{}")
+	}
+	/// Start an entry, or a field after writing `"name: "`.
+	pub fn entry(&mut self, out: &DebugOutput<'_>) -> fmt::Result {
+		let mut output = out.output.borrow_mut();
+		if output.alternate {
+			if self.count == 0 {
+				output.write_str(self.open)?;
+				output.write_str("\n")?;
+				output.depth += 1;
+			} else {
+				output.write_str(",\n")?;
+			}
+		} else if self.count == 0 {
+			output.write_str(self.open)?;
+			if self.spaced {
+				output.write_str(" ")?;
+			}
+		} else {
+			output.write_str(", ")?;
+		}
+		self.count += 1;
+		Ok(())
+	}
+	/// Close the brackets.
+	pub fn finish(self, out: &DebugOutput<'_>) -> fmt::Result {
+		let mut output = out.output.borrow_mut();
+		if self.count == 0 {
+			output.write_str(self.empty)
+		} else if output.alternate {
+			output.write_str(",\n")?;
+			output.depth -= 1;
+			output.write_str(self.close)
+		} else {
+			if self.comma && self.count == 1 {
+				output.write_str(",")?;
+			}
+			if self.spaced {
+				output.write_str(" ")?;
+			}
+			output.write_str(self.close)
+		}
+	}
+}
@@ -0,0 +1,74 @@
+use std::mem::ManuallyDrop;
+
+/// Dropping without recursion, implemented by `#[derive(DeepDrop)]`.
+///
+/// The derive also implements `Drop` for the type, which takes the children out (see [`DeepDrop::take_children`])
+/// and drops them one by one from a list on the heap, taking out their children first.
+/// The children in the list are owned, so they are taken apart field by field (see [`DeepDrop::take_children_owned`]).
+///
+/// The value being dropped is only borrowed, so its children are taken out in place:
+/// * Children in an `Option`, a collection, a `Cell` or a `RefCell` are moved out, leaving it empty.
+/// * A child of the type itself held directly in a `Box`, `Rc` or `Arc` takes the place of the value,
+///   whose other fields are taken apart. This repeats until the value has no such child.
+/// * An `Rc` or `Arc` child that is shared is left in place, as dropping it doesn't drop its contents.
+/// * Other children held directly, of another type, are left to be dropped with the value.
+///   A long chain of them alternating between types still recurses, so hold one of the links in an `Option`.
+pub trait DeepDrop {
+	/// Move the children out of `self` into `children`, so dropping `self` doesn't drop them.
+	///
+	/// As `self` might be replaced by one of its children, this is only meant for dropping.
+	fn take_children<'a>(&mut self, children: &mut Children<'a>)
+	where
+		Self: 'a;
+	/// Move the children out of `this` into `children`, and drop the rest of it.
+	///
+	/// By default, the children are taken out with [`DeepDrop::take_children`], and the rest is dropped normally,
+	/// running its `Drop`. The derive instead takes the fields apart, so its `Drop` doesn't run.
+	fn take_children_owned<'a>(this: ManuallyDrop<Self>, children: &mut Children<'a>)
+	where
+		Self: Sized + 'a,
+	{
+		let mut this = ManuallyDrop::into_inner(this);
+		this.take_children(children);
+	}
+}
+
+/// The children taken out of values being dropped by [`DeepDrop`].
+pub struct Children<'a> {
+	list: Vec<Box<dyn Owned<'a> + 'a>>,
+}
+
+// A child in the list, taken apart when its turn comes.
+trait Owned<'a> {
+	fn take_children(self: Box<Self>, children: &mut Children<'a>);
+}
+
+impl<'a, T: DeepDrop + 'a> Owned<'a> for T {
+	fn take_children(self: Box<Self>, children: &mut Children<'a>) {
+		T::take_children_owned(ManuallyDrop::new(*self), children);
+	}
+}
+
+impl<'a> Children<'a> {
+	fn new() -> Self {
+		Self { list: Vec::new() }
+	}
+	/// Add a child to be dropped.
+	pub fn push<T: DeepDrop + 'a>(&mut self, child: T) {
+		self.list.push(Box::new(child));
+	}
+	/// Add a boxed child to be dropped, without moving it out of its box.
+	pub fn push_box<T: DeepDrop + 'a>(&mut self, child: Box<T>) {
+		self.list.push(child);
+	}
+}
+
+/// Drop the children of `value` without recursion. This is what the `Drop` implemented by `#[derive(DeepDrop)]` calls.
+pub fn drop_deep<'a, T: DeepDrop + 'a>(value: &mut T) {
+	let mut children = Children::new();
+	value.take_children(&mut children);
+	while let Some(child) = children.list.pop() {
+		// The child is taken apart without its `Drop`, so this doesn't recurse.
+		child.take_children(&mut children);
+	}
+}
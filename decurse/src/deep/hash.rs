@@ -0,0 +1,33 @@
+use super::{Scope, ScopedCall};
+use std::{cell::RefCell, hash::Hasher};
+
+/// Hashing without recursion, implemented by `#[derive(DeepHash)]`.
+///
+/// The derive also implements `Hash` for the type, which runs [`DeepHash::deep_hash`] on a new [`Scope`].
+/// It feeds the hasher exactly what `#[derive(Hash)]` would, so the two can be swapped without changing any hashes.
+/// Children are hashed as frames of the scope, and other fields with their own `Hash`.
+///
+/// ```
+/// use std::collections::HashSet;
+///
+/// #[derive(decurse::DeepHash, decurse::DeepPartialEq, decurse::DeepEq, decurse::DeepDrop)]
+/// enum Json {
+///     Null,
+///     Array(Vec<Json>),
+/// }
+///
+/// let mut json = Json::Null;
+/// for _ in 0..100000 {
+///     json = Json::Array(vec![json]);
+/// }
+/// let mut set = HashSet::new();
+/// set.insert(json);
+/// ```
+pub trait DeepHash {
+	/// A frame of the scope that hashes `self` into the state.
+	fn deep_hash<'a>(
+		&'a self,
+		state: &'a RefCell<&'a mut dyn Hasher>,
+		scope: &Scope<'a>,
+	) -> ScopedCall<'a, ()>;
+}
@@ -0,0 +1,49 @@
+//! Stack-safe versions of traits that the standard derives implement recursively.
+//!
+//! Dropping (or cloning, comparing, ...) a deeply nested value with the code generated by the standard derives
+//! recurses once per level, so a long enough `Box`-linked list overflows the stack
+//! even if every function that built it was decursed.
+//! The derives of this crate do the same work on the heap instead.
+//!
+//! The derives find the children of a type by looking at the types of its fields:
+//! a field whose type mentions the type itself (e.g. `Box<Expr>`, `Vec<Tree>` or `Option<Rc<List<T>>>`) holds children,
+//! and so does a field marked with `#[deep]` (for types that recurse through another type).
+//! Children can be held in `Box`, `Rc`, `Arc`, `Option`, tuples, `Vec` and the other std collections,
+//! nested in any way. Other fields are left to their own implementations,
+//! and so are fields marked with `#[deep(skip)]` and `Weak` pointers, which don't own what they point to.
+//! Only [`DeepDrop`] looks for children in a `Cell` or `RefCell`:
+//! the other derives use the std implementations for them, and [`Recursive`] doesn't support them.
+//!
+//! ```
+//! #[derive(decurse::DeepDrop)]
+//! struct Node {
+//!     value: u32,
+//!     next: Option<Box<Node>>,
+//! }
+//!
+//! let mut list = None;
+//! for value in 0..1000000 {
+//!     list = Some(Box::new(Node { value, next: list }));
+//! }
+//! // With the drop generated by the compiler, this would overflow the stack.
+//! drop(list);
+//! ```
+//!
+//! [`Recursive`] goes the other way: instead of a stack-safe std trait,
+//! it gives a stack-safe [`fold`](Recursive::fold) for writing your own recursive functions one level at a time.
+
+mod clone;
+mod cmp;
+mod debug;
+mod drop;
+mod hash;
+mod recursive;
+mod scope;
+
+pub use clone::DeepClone;
+pub use cmp::{DeepOrd, DeepPartialEq, DeepPartialOrd};
+pub use debug::{DebugOutput, DeepDebug, Entries};
+pub use drop::{drop_deep, Children, DeepDrop};
+pub use hash::DeepHash;
+pub use recursive::Recursive;
+pub use scope::{Scope, ScopedCall};
@@ -0,0 +1,80 @@
+use super::{Scope, ScopedCall};
+use std::cell::RefCell;
+
+/// Folding and mapping a recursive type without writing the recursion, implemented by `#[derive(Recursive)]`.
+///
+/// The derive generates a layer type, named after the type with `Layer` appended,
+/// which has the same variants and fields except that every child is replaced with a type parameter `R`,
+/// e.g. `Box<Expr>` becomes `R`, and `Vec<Expr>` becomes `Vec<R>`.
+/// [`Recursive::fold`] hands the function a layer holding the results for the children, so it only handles one level.
+///
+/// ```
+/// use decurse::deep::Recursive;
+///
+/// #[derive(decurse::Recursive, decurse::DeepDrop, Default)]
+/// enum Expr {
+///     #[default]
+///     Zero,
+///     Num(i64),
+///     Add(Box<Expr>, Box<Expr>),
+///     Sum(Vec<Expr>),
+/// }
+///
+/// let mut expr = Expr::Num(1);
+/// for _ in 0..100000 {
+///     expr = Expr::Add(Box::new(expr), Box::new(Expr::Sum(vec![Expr::Num(1), Expr::Zero])));
+/// }
+/// let value = expr.fold(|layer| match layer {
+///     ExprLayer::Zero => 0,
+///     ExprLayer::Num(n) => n,
+///     ExprLayer::Add(a, b) => a + b,
+///     ExprLayer::Sum(items) => items.into_iter().sum(),
+/// });
+/// assert_eq!(value, 100001);
+/// ```
+///
+/// Children are found as with the other derives of this module (see the [module docs](crate::deep)),
+/// but they must be of the type itself, and not in an `Rc`, an `Arc` or a set.
+/// Taking a value apart with [`Recursive::into_layer`] doesn't run its `Drop`, so the type can derive `DeepDrop`.
+pub trait Recursive: Sized {
+	/// The generated layer type, with the children replaced by `R`.
+	type Layer<R>;
+	/// Take the value apart, leaving the children in its layer.
+	fn into_layer(self) -> Self::Layer<Self>;
+	/// Put a value back together.
+	fn from_layer(layer: Self::Layer<Self>) -> Self;
+	/// Replace every child in the layer with what `f` returns for it, in the order of the fields.
+	fn map_layer<R, S>(layer: Self::Layer<R>, f: impl FnMut(R) -> S) -> Self::Layer<S>;
+	/// Replace every child (not recursively) with what `f` returns for it.
+	fn map_children(self, f: impl FnMut(Self) -> Self) -> Self {
+		Self::from_layer(Self::map_layer(self.into_layer(), f))
+	}
+	/// Fold the value bottom up: `f` gets each layer with the results for its children, and returns the result for it.
+	///
+	/// The levels run as frames of a [`Scope`], so this doesn't overflow the stack however deep the value is.
+	fn fold<T>(self, f: impl FnMut(Self::Layer<T>) -> T) -> T {
+		let f = RefCell::new(f);
+		Scope::run(|scope| fold_in(self, &f, scope))
+	}
+}
+
+fn fold_in<'a, N, T, F>(node: N, f: &'a RefCell<F>, scope: &Scope<'a>) -> ScopedCall<'a, T>
+where
+	N: Recursive + 'a,
+	T: 'a,
+	F: FnMut(N::Layer<T>) -> T,
+{
+	let inner = scope.clone();
+	scope.call(async move {
+		let mut children = Vec::new();
+		let layer = N::map_layer(node.into_layer(), |child| children.push(child));
+		let mut results = Vec::with_capacity(children.len());
+		for child in children {
+			results.push(fold_in(child, f, &inner).await);
+		}
+		let mut results = results.into_iter();
+		// UNWRAP Safety: The layer has as many children as it had the first time it was mapped.
+		let layer = N::map_layer(layer, |()| results.next().unwrap());
+		(f.borrow_mut())(layer)
+	})
+}
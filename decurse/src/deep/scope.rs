@@ -0,0 +1,150 @@
+use std::{
+	cell::{Cell, RefCell},
+	future::Future,
+	pin::Pin,
+	rc::Rc,
+	task::{Context, Poll},
+};
+
+type Frame<'a> = Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+struct Inner<'a> {
+	frames: RefCell<Vec<Frame<'a>>>,
+	next: Cell<Option<Frame<'a>>>,
+}
+
+/// A heap stack for futures that borrow data of lifetime `'a`, used by the derives of this module.
+///
+/// This works like [`execute`](crate::execute) and [`call`](crate::call),
+/// but the frames don't have to be `'static`, so they can borrow the value being traversed.
+/// They can't borrow from each other, since every frame lives for all of `'a`.
+/// Frames call each other through the `Scope` handle rather than a thread local.
+///
+/// ```
+/// use decurse::deep::Scope;
+///
+/// fn sum<'a>(scope: &Scope<'a>, values: &'a [u64]) -> decurse::deep::ScopedCall<'a, u64> {
+///     let inner = scope.clone();
+///     scope.call(async move {
+///         match values.split_first() {
+///             Some((first, rest)) => first + sum(&inner, rest).await,
+///             None => 0,
+///         }
+///     })
+/// }
+///
+/// let values: Vec<u64> = (0..100000).collect();
+/// assert_eq!(Scope::run(|scope| sum(scope, &values)), 4999950000);
+/// ```
+pub struct Scope<'a> {
+	inner: Rc<Inner<'a>>,
+}
+
+impl Clone for Scope<'_> {
+	fn clone(&self) -> Self {
+		Self {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a> Scope<'a> {
+	/// Run the call returned by `start` on a new heap stack, returning its output.
+	pub fn run<T>(start: impl FnOnce(&Scope<'a>) -> ScopedCall<'a, T>) -> T {
+		let scope = Scope {
+			inner: Rc::new(Inner {
+				frames: RefCell::new(Vec::new()),
+				next: Cell::new(None),
+			}),
+		};
+		let call = start(&scope);
+		// Drops the frames (innermost first) if we panic, and breaks the cycles between them and the scope.
+		struct Clear<'s, 'a>(&'s Inner<'a>);
+		impl Drop for Clear<'_, '_> {
+			fn drop(&mut self) {
+				self.0.next.take();
+				loop {
+					let frame = self.0.frames.borrow_mut().pop();
+					match frame {
+						Some(frame) => drop(frame),
+						None => break,
+					}
+				}
+			}
+		}
+		let _clear = Clear(&scope.inner);
+		let dummy_waker = waker_fn::waker_fn(|| {});
+		let mut dummy_async_cx = Context::from_waker(&dummy_waker);
+		let slot = call.slot.clone();
+		if let Some(frame) = call.frame {
+			scope.inner.frames.borrow_mut().push(frame);
+		}
+		loop {
+			let frame = scope.inner.frames.borrow_mut().pop();
+			let mut frame = match frame {
+				Some(frame) => frame,
+				None => break,
+			};
+			if frame.as_mut().poll(&mut dummy_async_cx).is_pending() {
+				let next = scope.inner.next.take().expect(
+					"Decurse: a scoped frame awaited something other than a call of its scope.",
+				);
+				let mut frames = scope.inner.frames.borrow_mut();
+				frames.push(frame);
+				frames.push(next);
+			}
+		}
+		// UNWRAP Safety: The frames only run out once the first one completed, which fills the slot.
+		slot.take().unwrap()
+	}
+	/// Make the future a new frame on this heap stack. It runs when the returned call is awaited.
+	pub fn call<F>(&self, fut: F) -> ScopedCall<'a, F::Output>
+	where
+		F: Future + 'a,
+		F::Output: 'a,
+	{
+		let slot = Rc::new(Cell::new(None));
+		let slot_clone = slot.clone();
+		let frame = Box::pin(async move {
+			slot_clone.set(Some(fut.await));
+		});
+		ScopedCall {
+			frame: Some(frame),
+			slot,
+			scope: self.clone(),
+		}
+	}
+	/// A call that is already done, for results that don't need a frame.
+	pub(crate) fn ready<T>(&self, value: T) -> ScopedCall<'a, T> {
+		ScopedCall {
+			frame: None,
+			slot: Rc::new(Cell::new(Some(value))),
+			scope: self.clone(),
+		}
+	}
+}
+
+/// Future returned by [`Scope::call`].
+pub struct ScopedCall<'a, T> {
+	frame: Option<Frame<'a>>,
+	slot: Rc<Cell<Option<T>>>,
+	scope: Scope<'a>,
+}
+
+// The output is never pinned, it's only moved out.
+impl<T> Unpin for ScopedCall<'_, T> {}
+
+impl<T> Future for ScopedCall<'_, T> {
+	type Output = T;
+
+	fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+		match self.frame.take() {
+			Some(frame) => {
+				self.scope.inner.next.set(Some(frame));
+				Poll::Pending
+			}
+			// UNWRAP Safety: The scope only polls us again once the frame we pushed completed.
+			None => Poll::Ready(self.slot.take().unwrap()),
+		}
+	}
+}
@@ -1,5 +1,7 @@
 mod pend_once;
 
+pub use crate::generator::Yield;
+
 /// Private for use by decurse macro only.
 pub mod sound;
 
@@ -1,94 +1,80 @@
-pub use super::pend_once::PendOnce;
-pub use decurse_macro::decurse_sound;
-use pfn::PFnOnce;
-use pinned_vec::PinnedVec;
-use scoped_tls::scoped_thread_local;
-use std::{any::Any, cell::RefCell, future::Future, task::Poll};
+pub use crate::runtime::Call;
+use crate::{introspect::Entered, runtime, AbortHandle, Cancelled};
+pub use decurse_macro::{decurse_generator, decurse_sound};
+use std::future::Future;
 
-pub struct Context<F: Future> {
-	next: RefCell<Option<F>>,
-	result: RefCell<Option<F::Output>>,
+// The frames of functions without options only need counting, so they skip `Recursion`.
+// Each frame is the future of the function itself, so frames of the same function share a segment (see `runtime::new_segment`),
+// and counts itself with `enter` when it starts.
+
+pub struct Counted {
+	_entered: Entered,
 }
 
-impl<F: Future + 'static> Context<F> {
-	pub fn new() -> Self {
-		Self {
-			next: RefCell::new(None),
-			result: RefCell::new(None),
-		}
-	}
-	pub fn set_next(self_ptr: &Box<dyn Any>, fut: F) {
-		let this: &Self = self_ptr.downcast_ref().unwrap();
-		*this.next.borrow_mut() = Some(fut);
-	}
-	pub fn get_result(self_ptr: &Box<dyn Any>) -> F::Output {
-		let this: &Self = self_ptr.downcast_ref().unwrap();
-		this.result.borrow_mut().take().unwrap()
+// Keep the frame on the logical stack until it completes or is dropped.
+pub fn enter(name: &'static str) -> Counted {
+	Counted {
+		_entered: Entered::push(name, None),
 	}
 }
 
-scoped_thread_local! (static CONTEXT: Box<dyn Any>);
-
-pub fn set_next<F: Future + 'static>(fut: F) {
-	CONTEXT.with(|c| Context::set_next(c, fut))
+pub fn execute<F>(name: &'static str, fut: F) -> F::Output
+where
+	F: Future + 'static,
+{
+	// UNWRAP Safety: Without an abort handle the computation is never cancelled.
+	execute_with(name, fut, None).unwrap()
 }
 
-pub fn get_result<A, R, F>(_phantom: R) -> F::Output
+pub fn execute_abortable<F>(
+	name: &'static str,
+	fut: F,
+	abort_handle: AbortHandle,
+) -> Result<F::Output, Cancelled>
 where
-	R: PFnOnce<A, PFnOutput = F>,
 	F: Future + 'static,
 {
-	CONTEXT.with(|c| Context::<F>::get_result(c))
+	execute_with(name, fut, Some(&abort_handle))
 }
 
-pub fn execute<F>(fut: F) -> F::Output
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+fn execute_with<F>(
+	name: &'static str,
+	fut: F,
+	abort_handle: Option<&AbortHandle>,
+) -> Result<F::Output, Cancelled>
 where
 	F: Future + 'static,
 {
-	let dummy_waker = waker_fn::waker_fn(|| {});
-	let mut dummy_async_cx: std::task::Context = std::task::Context::from_waker(&dummy_waker);
-	let ctx: Context<F> = Context::new();
-	let any_ctx: Box<dyn Any> = Box::new(ctx);
-	let ctx: &Context<F> = any_ctx.downcast_ref().unwrap();
+	#[cfg(feature = "tracing")]
+	if let Some(span) = crate::trace::span(name, None, crate::depth() + 1) {
+		let (frame, slot) = runtime::new_frame(fut);
+		let frame = crate::trace::instrument(frame, span);
+		runtime::with_runtime(|runtime| runtime.drive(frame, abort_handle))?;
+		// UNWRAP Safety: `drive` only returns Ok once the frame completed, which fills the slot.
+		return Ok(slot.take().unwrap());
+	}
+	runtime::execute_with(fut, abort_handle)
+}
 
-	let output = CONTEXT.set(&any_ctx, || {
-		let mut heap_stack: PinnedVec<F> = PinnedVec::new();
-		heap_stack.push(fut);
-		loop {
-			let len = heap_stack.len();
-			// UNWRAP Safety: The only way len could go down is through the pop in the Poll::Ready case,
-			// in which we return if len is 1. So len never gets to 0.
-			let fut = heap_stack.get_mut(len - 1).unwrap();
-			let polled = fut.poll(&mut dummy_async_cx);
-			match polled {
-				Poll::Ready(r) => {
-					if len == 1 {
-						break r;
-					} else {
-						let mut bm = ctx.result.borrow_mut();
-						*bm = Some(r);
-						heap_stack.pop();
-					}
-				}
-				Poll::Pending => {
-					// UNWRAP Safety: The decurse macro only yields when recursing,
-					// in which case `next` would be filled before Pending is returned (see ctx.set_next).
-					heap_stack.push(ctx.next.borrow_mut().take().unwrap());
-				}
-			}
-		}
-	});
-	output
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub fn recurse<F>(name: &'static str, fut: F) -> Call<F::Output>
+where
+	F: Future + 'static,
+{
+	// A traced frame needs a span of its own.
+	#[cfg(feature = "tracing")]
+	if let Some(span) = crate::trace::span(name, None, crate::depth() + 1) {
+		let (frame, slot) = runtime::new_frame(fut);
+		return Call::from_frame(crate::trace::instrument(frame, span), slot);
+	}
+	Call::new(fut)
 }
 
 #[macro_export]
 macro_rules! for_macro_only_recurse_sound {
-    ($func:path, ($($args:expr),*)) => {
-        ({
-            $crate::for_macro_only::sound::set_next($func ($($args),*));
-            $crate::for_macro_only::sound::PendOnce::new().await;
-            $crate::for_macro_only::sound::get_result($func)
-        })
+    ($name:expr, $func:path, ($($args:expr),*)) => {
+        $crate::for_macro_only::sound::recurse($name, $func ($($args),*)).await
     };
 }
 
@@ -124,10 +110,10 @@ mod tests {
 			if x == 0 {
 				1
 			} else {
-				for_macro_only_recurse_sound!(factorial, (x - 1)) * x
+				for_macro_only_recurse_sound!("factorial", factorial, (x - 1)) * x
 			}
 		}
-		assert_eq!(execute(factorial(6)), 720);
+		assert_eq!(execute("factorial", factorial(6)), 720);
 	}
 
 	#[test]
@@ -136,11 +122,11 @@ mod tests {
 			if x == 0 || x == 1 {
 				1
 			} else {
-				for_macro_only_recurse_sound!(fibonacci, (x - 1))
-					+ for_macro_only_recurse_sound!(fibonacci, (x - 2))
+				for_macro_only_recurse_sound!("fibonacci", fibonacci, (x - 1))
+					+ for_macro_only_recurse_sound!("fibonacci", fibonacci, (x - 2))
 			}
 		}
-		assert_eq!(execute(fibonacci(10)), 89);
+		assert_eq!(execute("fibonacci", fibonacci(10)), 89);
 	}
 
 	// This test cause stack overflow.
@@ -163,10 +149,10 @@ mod tests {
 				if x == 0 {
 					0
 				} else {
-					for_macro_only_recurse_sound!(decurse_triangular, (x - 1)) + x
+					for_macro_only_recurse_sound!("triangular", decurse_triangular, (x - 1)) + x
 				}
 			}
-			execute(decurse_triangular(x))
+			execute("triangular", decurse_triangular(x))
 		}
 		assert_eq!(20000100000, triangular(200000));
 	}
@@ -1,4 +1,5 @@
 pub use super::pend_once::PendOnce;
+use crate::{AbortHandle, Cancelled};
 pub use decurse_macro::decurse_unsound;
 use pfn::PFnOnce;
 use pinned_vec::PinnedVec;
@@ -45,6 +46,21 @@ where
 }
 
 pub fn execute<F>(fut: F) -> F::Output
+where
+	F: Future,
+{
+	// UNWRAP Safety: Without an abort handle the computation is never cancelled.
+	drive(fut, None).unwrap()
+}
+
+pub fn execute_abortable<F>(fut: F, abort_handle: AbortHandle) -> Result<F::Output, Cancelled>
+where
+	F: Future,
+{
+	drive(fut, Some(&abort_handle))
+}
+
+fn drive<F>(fut: F, abort_handle: Option<&AbortHandle>) -> Result<F::Output, Cancelled>
 where
 	F: Future,
 {
@@ -56,6 +72,13 @@ where
 		let mut heap_stack: PinnedVec<F> = PinnedVec::new();
 		heap_stack.push(fut);
 		loop {
+			if matches!(abort_handle, Some(h) if h.is_aborted()) {
+				// Drop the innermost frames first, as if the calls were unwinding.
+				while !heap_stack.is_empty() {
+					heap_stack.pop();
+				}
+				break Err(Cancelled);
+			}
 			let len = heap_stack.len();
 			// UNWRAP Safety: The only way len could go down is through the pop in the Poll::Ready case,
 			// in which we return if len is 1. So len never gets to 0.
@@ -64,7 +87,7 @@ where
 			match polled {
 				Poll::Ready(r) => {
 					if len == 1 {
-						break r;
+						break Ok(r);
 					} else {
 						let mut bm = ctx.result.borrow_mut();
 						*bm = Some(r);
@@ -0,0 +1,154 @@
+use crate::{
+	introspect::Detached,
+	runtime::{self, Runtime},
+};
+use std::{
+	cell::Cell,
+	fmt,
+	future::Future,
+	iter::FusedIterator,
+	pin::Pin,
+	rc::Rc,
+	task::{Context, Poll},
+};
+
+/// An iterator whose items are yielded by recursive code running on the heap stack.
+///
+/// This is what `#[decurse::generator]` functions return.
+/// The frames run on a heap stack of their own, and only run when [`Iterator::next`] is called,
+/// up to the next [`yield_item!`](crate::yield_item).
+///
+/// A generator can also be made from any future with [`Generator::new`].
+/// Every `.await` in it (and in everything it calls) must be on a [`call`](crate::call) or a `yield_item!`.
+///
+/// ```
+/// use decurse::{call, yield_item, Generator};
+///
+/// async fn count_down(x: u32) {
+///     yield_item!(x);
+///     if x > 0 {
+///         call(count_down(x - 1)).await;
+///     }
+/// }
+///
+/// let mut generator: Generator<u32> = Generator::new(count_down(100000));
+/// assert_eq!(generator.next(), Some(100000));
+/// assert_eq!(generator.count(), 100000);
+/// ```
+pub struct Generator<T> {
+	runtime: Rc<Runtime>,
+	items: Rc<Cell<Option<T>>>,
+	detached: Detached,
+	done: bool,
+}
+
+impl<T: 'static> Generator<T> {
+	/// A generator that runs the future, yielding the items it (and everything it calls) passes to `yield_item!`.
+	///
+	/// Nothing runs until the first call to [`Iterator::next`].
+	pub fn new<F>(fut: F) -> Self
+	where
+		F: Future<Output = ()> + 'static,
+	{
+		let items = Rc::new(Cell::new(None));
+		let (frame, _) = runtime::new_frame(fut);
+		Self {
+			runtime: Runtime::generator(frame, items.clone()),
+			items,
+			detached: Detached::default(),
+			done: false,
+		}
+	}
+}
+
+impl<T: 'static> Iterator for Generator<T> {
+	type Item = T;
+
+	/// Run the frames until one of them yields an item, or they all complete.
+	///
+	/// ### Panics
+	/// If a frame panics, the panic is propagated, and the generator is done.
+	fn next(&mut self) -> Option<T> {
+		if self.done {
+			return None;
+		}
+		// Drops the frames if we panic.
+		struct Done<'a>(&'a Runtime, &'a mut bool);
+		impl Drop for Done<'_> {
+			fn drop(&mut self) {
+				if std::thread::panicking() {
+					*self.1 = true;
+					self.0.clear();
+				}
+			}
+		}
+		let _attached = self.detached.attach();
+		let _done = Done(&self.runtime, &mut self.done);
+		if self.runtime.resume() {
+			*_done.1 = true;
+			None
+		} else {
+			self.items.take()
+		}
+	}
+}
+
+impl<T: 'static> FusedIterator for Generator<T> {}
+
+impl<T> Drop for Generator<T> {
+	fn drop(&mut self) {
+		if !self.runtime.is_empty() {
+			// Drop the frames where they would be if they were running.
+			let _attached = self.detached.attach();
+			self.runtime.clear();
+		}
+	}
+}
+
+impl<T> fmt::Debug for Generator<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Generator")
+			.field("done", &self.done)
+			.finish()
+	}
+}
+
+// Future of `yield_item!`. It hands the item to the generator, then pends once.
+#[doc(hidden)]
+pub struct Yield<T> {
+	item: Option<T>,
+}
+
+impl<T> Yield<T> {
+	pub fn new(item: T) -> Self {
+		Self { item: Some(item) }
+	}
+}
+
+// The item is never pinned, it's only moved out.
+impl<T> Unpin for Yield<T> {}
+
+impl<T: 'static> Future for Yield<T> {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+		match self.item.take() {
+			Some(item) => {
+				runtime::yield_item(item);
+				Poll::Pending
+			}
+			None => Poll::Ready(()),
+		}
+	}
+}
+
+/// Yield an item from a `#[decurse::generator]` function (or a future run by a [`Generator`]).
+///
+/// The function continues from here on the next call to [`Iterator::next`].
+/// This can only be used directly in the body, since it `.await`s.
+#[macro_export]
+macro_rules! yield_item {
+	($item:expr) => {
+		$crate::for_macro_only::Yield::new($item).await
+	};
+}
@@ -0,0 +1,408 @@
+//! Depth first search and the algorithms built on it, for graphs of any node type, on the heap stack.
+//!
+//! A graph is given as a function returning the neighbours of a node,
+//! so it can be implicit (e.g. the cells of a grid) instead of stored.
+//! Nodes are kept in hash maps, so they must be `Hash + Eq + Clone`, and are usually indices, coordinates or references.
+//! Each algorithm is written as the usual recursive function, but every level is a frame on a heap stack,
+//! so paths of any length are fine.
+//!
+//! ```
+//! use decurse::graph;
+//!
+//! // 0 -> 1 -> ... -> 99999 -> 0, and 99999 -> 100000.
+//! let len = 100000;
+//! let neighbors = |&node: &usize| match node {
+//!     n if n == len - 1 => vec![0, len],
+//!     n if n == len => vec![],
+//!     n => vec![n + 1],
+//! };
+//! let components = graph::strongly_connected_components(vec![0], neighbors);
+//! assert_eq!(components.len(), 2);
+//! assert_eq!(components[0], [len]);
+//! assert_eq!(components[1].len(), len);
+//! ```
+
+use crate::{
+	deep::{Scope, ScopedCall},
+	walk::{Control, Walk},
+};
+use std::{
+	cell::RefCell,
+	collections::{HashMap, HashSet},
+	error::Error,
+	fmt::{self, Debug, Display},
+	hash::Hash,
+};
+
+type Neighbors<'a, N> = Box<dyn FnMut(&N) -> Box<dyn Iterator<Item = N> + 'a> + 'a>;
+type Callback<'a, N> = Option<Box<dyn FnMut(&N) -> Control + 'a>>;
+
+/// A depth first search, calling back when it discovers and finishes a node.
+///
+/// The callbacks return a [`Control`]: [`Control::Prune`] from `discover` doesn't follow the edges of the node,
+/// and [`Control::Stop`] ends the search.
+/// The visited nodes are remembered across calls to [`Dfs::run`], so running it from every node gives a DFS forest.
+///
+/// ```
+/// use decurse::{graph::Dfs, walk::Control};
+///
+/// // Flood fill a grid, except for a wall in the middle.
+/// const SIZE: i32 = 500;
+/// let mut painted = 0;
+/// let mut dfs = Dfs::new(|&(x, y): &(i32, i32)| {
+///     let next = vec![(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)];
+///     next.into_iter()
+///         .filter(|&(x, y)| (0..SIZE).contains(&x) && (0..SIZE).contains(&y) && x != SIZE / 2)
+/// })
+/// .discover(|_| {
+///     painted += 1;
+///     Control::Continue
+/// });
+/// dfs.run((0, 0));
+/// assert!(dfs.is_visited(&(SIZE / 2 - 1, SIZE - 1)));
+/// assert!(!dfs.is_visited(&(SIZE / 2 + 1, 0)));
+/// drop(dfs);
+/// assert_eq!(painted, SIZE / 2 * SIZE);
+/// ```
+pub struct Dfs<'a, N> {
+	neighbors: Neighbors<'a, N>,
+	discover: Callback<'a, N>,
+	finish: Callback<'a, N>,
+	visited: HashSet<N>,
+}
+
+impl<'a, N: Hash + Eq + Clone + 'a> Dfs<'a, N> {
+	/// A search of the graph given by `neighbors`, without any callbacks yet.
+	pub fn new<I>(mut neighbors: impl FnMut(&N) -> I + 'a) -> Self
+	where
+		I: IntoIterator<Item = N>,
+		I::IntoIter: 'a,
+	{
+		Self {
+			neighbors: Box::new(move |node| Box::new(neighbors(node).into_iter())),
+			discover: None,
+			finish: None,
+			visited: HashSet::new(),
+		}
+	}
+	/// Call `f` on each node when it is first reached, before its edges are followed.
+	pub fn discover(mut self, f: impl FnMut(&N) -> Control + 'a) -> Self {
+		self.discover = Some(Box::new(f));
+		self
+	}
+	/// Call `f` on each node once everything reachable from it was visited.
+	pub fn finish(mut self, f: impl FnMut(&N) -> Control + 'a) -> Self {
+		self.finish = Some(Box::new(f));
+		self
+	}
+	/// Search from `start`, unless it was already visited. Returns `false` if a callback stopped the search.
+	pub fn run(&mut self, start: N) -> bool {
+		if self.visited.contains(&start) {
+			return true;
+		}
+		let Self {
+			neighbors,
+			discover,
+			finish,
+			visited,
+		} = self;
+		let visited = RefCell::new(visited);
+		// The neighbors are filtered when the search gets to them, so that each node is discovered once.
+		let mut walk =
+			Walk::new(|node: &N| neighbors(node).filter(|next| !visited.borrow().contains(next)))
+				.enter(|node, _| {
+					visited.borrow_mut().insert(node.clone());
+					call(discover, node)
+				})
+				.exit(|node, _| call(finish, node));
+		walk.run(start)
+	}
+	/// Whether a search reached the node.
+	pub fn is_visited(&self, node: &N) -> bool {
+		self.visited.contains(node)
+	}
+}
+
+// Call the callback, if there is one.
+fn call<N>(callback: &mut Callback<'_, N>, node: &N) -> Control {
+	match callback {
+		Some(callback) => callback(node),
+		None => Control::Continue,
+	}
+}
+
+struct Tarjan<N, F> {
+	neighbors: F,
+	// The order in which the nodes were discovered.
+	index: HashMap<N, usize>,
+	// The nodes whose component isn't known yet, and the same as a set.
+	stack: Vec<N>,
+	on_stack: HashSet<N>,
+	components: Vec<Vec<N>>,
+}
+
+/// The strongly connected components of the nodes reachable from `starts`, by Tarjan's algorithm.
+///
+/// Two nodes are in the same component if each is reachable from the other.
+/// The components are in reverse topological order: the edges leaving a component go to earlier components.
+pub fn strongly_connected_components<N, I>(
+	starts: impl IntoIterator<Item = N>,
+	neighbors: impl FnMut(&N) -> I,
+) -> Vec<Vec<N>>
+where
+	N: Hash + Eq + Clone,
+	I: IntoIterator<Item = N>,
+{
+	let state = RefCell::new(Tarjan {
+		neighbors,
+		index: HashMap::new(),
+		stack: Vec::new(),
+		on_stack: HashSet::new(),
+		components: Vec::new(),
+	});
+	for start in starts {
+		if !state.borrow().index.contains_key(&start) {
+			Scope::run(|scope| strong_connect(start, &state, scope));
+		}
+	}
+	state.into_inner().components
+}
+
+// Returns the lowest index reachable from the node through nodes still on the stack.
+fn strong_connect<'b, N, F, I>(
+	node: N,
+	state: &'b RefCell<Tarjan<N, F>>,
+	scope: &Scope<'b>,
+) -> ScopedCall<'b, usize>
+where
+	N: Hash + Eq + Clone + 'b,
+	F: FnMut(&N) -> I + 'b,
+	I: IntoIterator<Item = N>,
+	I::IntoIter: 'b,
+{
+	let inner = scope.clone();
+	scope.call(async move {
+		let index = {
+			let mut state = state.borrow_mut();
+			let index = state.index.len();
+			state.index.insert(node.clone(), index);
+			state.stack.push(node.clone());
+			state.on_stack.insert(node.clone());
+			index
+		};
+		let mut low = index;
+		let neighbors = (state.borrow_mut().neighbors)(&node);
+		for next in neighbors {
+			let seen = state.borrow().index.get(&next).copied();
+			match seen {
+				None => low = low.min(strong_connect(next, state, &inner).await),
+				Some(next_index) if state.borrow().on_stack.contains(&next) => {
+					low = low.min(next_index)
+				}
+				// Already in a component.
+				Some(_) => {}
+			}
+		}
+		if low == index {
+			let mut state = state.borrow_mut();
+			let mut component = Vec::new();
+			loop {
+				// UNWRAP Safety: The node itself is still on the stack.
+				let member = state.stack.pop().unwrap();
+				state.on_stack.remove(&member);
+				let done = member == node;
+				component.push(member);
+				if done {
+					break;
+				}
+			}
+			state.components.push(component);
+		}
+		low
+	})
+}
+
+/// A cycle found by [`topological_sort`]: each node has an edge to the next, and the last to the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cycle<N>(pub Vec<N>);
+
+impl<N: Debug> Display for Cycle<N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str("graph has a cycle: ")?;
+		for node in &self.0 {
+			write!(f, "{:?} -> ", node)?;
+		}
+		match self.0.first() {
+			Some(first) => write!(f, "{:?}", first),
+			None => Ok(()),
+		}
+	}
+}
+
+impl<N: Debug> Error for Cycle<N> {}
+
+struct Topo<N, F> {
+	neighbors: F,
+	// `false` while the node is on the path, `true` once it's finished.
+	finished: HashMap<N, bool>,
+	path: Vec<N>,
+	order: Vec<N>,
+}
+
+/// The nodes reachable from `starts`, each before the nodes it has edges to.
+///
+/// Fails with one of the cycles if there is any.
+pub fn topological_sort<N, I>(
+	starts: impl IntoIterator<Item = N>,
+	neighbors: impl FnMut(&N) -> I,
+) -> Result<Vec<N>, Cycle<N>>
+where
+	N: Hash + Eq + Clone,
+	I: IntoIterator<Item = N>,
+{
+	let state = RefCell::new(Topo {
+		neighbors,
+		finished: HashMap::new(),
+		path: Vec::new(),
+		order: Vec::new(),
+	});
+	for start in starts {
+		if !state.borrow().finished.contains_key(&start) {
+			Scope::run(|scope| topo_visit(start, &state, scope))?;
+		}
+	}
+	let mut order = state.into_inner().order;
+	order.reverse();
+	Ok(order)
+}
+
+fn topo_visit<'b, N, F, I>(
+	node: N,
+	state: &'b RefCell<Topo<N, F>>,
+	scope: &Scope<'b>,
+) -> ScopedCall<'b, Result<(), Cycle<N>>>
+where
+	N: Hash + Eq + Clone + 'b,
+	F: FnMut(&N) -> I + 'b,
+	I: IntoIterator<Item = N>,
+	I::IntoIter: 'b,
+{
+	let inner = scope.clone();
+	scope.call(async move {
+		{
+			let mut state = state.borrow_mut();
+			state.finished.insert(node.clone(), false);
+			state.path.push(node.clone());
+		}
+		let neighbors = (state.borrow_mut().neighbors)(&node);
+		for next in neighbors {
+			let finished = state.borrow().finished.get(&next).copied();
+			match finished {
+				None => topo_visit(next, state, &inner).await?,
+				Some(false) => {
+					let state = state.borrow();
+					// UNWRAP Safety: The nodes that aren't finished are the ones on the path.
+					let start = state.path.iter().position(|n| *n == next).unwrap();
+					return Err(Cycle(state.path[start..].to_vec()));
+				}
+				Some(true) => {}
+			}
+		}
+		let mut state = state.borrow_mut();
+		state.finished.insert(node.clone(), true);
+		state.path.pop();
+		state.order.push(node);
+		Ok(())
+	})
+}
+
+struct Articulation<N, F> {
+	neighbors: F,
+	index: HashMap<N, usize>,
+	points: Vec<N>,
+}
+
+/// The articulation points of the nodes reachable from `starts`:
+/// the nodes whose removal disconnects some of the other nodes from each other.
+///
+/// The graph must be undirected, i.e. every edge must be returned by `neighbors` for both its ends.
+/// Parallel edges count, so two nodes joined twice stay connected when a third is removed.
+/// The points are in the order their searches finished.
+pub fn articulation_points<N, I>(
+	starts: impl IntoIterator<Item = N>,
+	neighbors: impl FnMut(&N) -> I,
+) -> Vec<N>
+where
+	N: Hash + Eq + Clone,
+	I: IntoIterator<Item = N>,
+{
+	let state = RefCell::new(Articulation {
+		neighbors,
+		index: HashMap::new(),
+		points: Vec::new(),
+	});
+	for start in starts {
+		if !state.borrow().index.contains_key(&start) {
+			Scope::run(|scope| articulation_visit(start, None, &state, scope));
+		}
+	}
+	state.into_inner().points
+}
+
+// Returns the lowest index reachable from the subtree of the node through one edge that isn't in the tree.
+fn articulation_visit<'b, N, F, I>(
+	node: N,
+	parent: Option<N>,
+	state: &'b RefCell<Articulation<N, F>>,
+	scope: &Scope<'b>,
+) -> ScopedCall<'b, usize>
+where
+	N: Hash + Eq + Clone + 'b,
+	F: FnMut(&N) -> I + 'b,
+	I: IntoIterator<Item = N>,
+	I::IntoIter: 'b,
+{
+	let inner = scope.clone();
+	scope.call(async move {
+		let index = {
+			let mut state = state.borrow_mut();
+			let index = state.index.len();
+			state.index.insert(node.clone(), index);
+			index
+		};
+		let mut low = index;
+		let mut children = 0;
+		let mut is_point = false;
+		// Only the edge we came through is skipped, not the parallel ones.
+		let mut came_through = parent.as_ref();
+		let neighbors = (state.borrow_mut().neighbors)(&node);
+		for next in neighbors {
+			if came_through == Some(&next) {
+				came_through = None;
+				continue;
+			}
+			let seen = state.borrow().index.get(&next).copied();
+			match seen {
+				None => {
+					children += 1;
+					let next_low =
+						articulation_visit(next, Some(node.clone()), state, &inner).await;
+					low = low.min(next_low);
+					// The child's subtree can't get above this node without it.
+					if parent.is_some() && next_low >= index {
+						is_point = true;
+					}
+				}
+				Some(next_index) => low = low.min(next_index),
+			}
+		}
+		// The root is a point if the search had to start over from it.
+		if parent.is_none() && children > 1 {
+			is_point = true;
+		}
+		if is_point {
+			state.borrow_mut().points.push(node);
+		}
+		low
+	})
+}
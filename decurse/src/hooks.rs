@@ -0,0 +1,233 @@
+//! Hooks into the executor.
+//!
+//! A [`Hook`] is told whenever a frame of a `#[decurse]` function (or [`Recursion`](crate::Recursion))
+//! is pushed onto or popped off the heap stack, when the stack gets as deep as some thresholds,
+//! and when the computation completes.
+//!
+//! Hooks can be added for all the computations on a thread with [`add_thread_hook`],
+//! or for a single computation with [`execute_with_hook`].
+//! [`Hook::on_depth_crossed`] is called once per threshold and computation, even if the depth goes up and down around it.
+//!
+//! ```
+//! use decurse::hooks::{execute_with_hook, Hook};
+//! use std::cell::Cell;
+//!
+//! #[derive(Default)]
+//! struct DeepWarning {
+//!     warned: Cell<usize>,
+//! }
+//! impl Hook for DeepWarning {
+//!     fn depth_thresholds(&self) -> &[usize] {
+//!         &[1000, 100000]
+//!     }
+//!     fn on_depth_crossed(&self, depth: usize) {
+//!         eprintln!("recursion is {} deep", depth);
+//!         self.warned.set(self.warned.get() + 1);
+//!     }
+//! }
+//!
+//! #[decurse::decurse]
+//! fn count_down(x: u32) -> u32 {
+//!     if x == 0 {
+//!         0
+//!     } else {
+//!         count_down(x - 1) + 1
+//!     }
+//! }
+//!
+//! let hook = std::rc::Rc::new(DeepWarning::default());
+//! assert_eq!(execute_with_hook(hook.clone(), async { count_down(5000) }), 5000);
+//! assert_eq!(hook.warned.get(), 1);
+//! ```
+
+use crate::{execute, introspect, introspect::Watcher, runtime, StackFrame};
+use std::{
+	cell::{Cell, RefCell},
+	future::Future,
+	rc::Rc,
+};
+
+/// Callbacks from the executor. Every method does nothing by default.
+///
+/// The methods take `&self`, so use `Cell`s or `RefCell`s for state.
+pub trait Hook {
+	/// A frame was pushed. `depth` is its depth, as in [`depth`](crate::depth).
+	fn on_push(&self, frame: &StackFrame, depth: usize) {
+		let _ = (frame, depth);
+	}
+	/// A frame was popped, because it completed or was dropped.
+	fn on_pop(&self, frame: &StackFrame, depth: usize) {
+		let _ = (frame, depth);
+	}
+	/// The depths that trigger [`Hook::on_depth_crossed`].
+	fn depth_thresholds(&self) -> &[usize] {
+		&[]
+	}
+	/// A frame was pushed at one of the [`Hook::depth_thresholds`] (or deeper) for the first time in the computation.
+	fn on_depth_crossed(&self, depth: usize) {
+		let _ = depth;
+	}
+	/// The computation completed.
+	///
+	/// For a hook added with [`add_thread_hook`], this is whenever a computation that isn't nested in another one completes.
+	fn on_complete(&self) {}
+}
+
+impl<H: Hook + ?Sized> Hook for Rc<H> {
+	fn on_push(&self, frame: &StackFrame, depth: usize) {
+		(**self).on_push(frame, depth)
+	}
+	fn on_pop(&self, frame: &StackFrame, depth: usize) {
+		(**self).on_pop(frame, depth)
+	}
+	fn depth_thresholds(&self) -> &[usize] {
+		(**self).depth_thresholds()
+	}
+	fn on_depth_crossed(&self, depth: usize) {
+		(**self).on_depth_crossed(depth)
+	}
+	fn on_complete(&self) {
+		(**self).on_complete()
+	}
+}
+
+struct Entry {
+	id: HookId,
+	hook: Rc<dyn Hook>,
+	// The runtime of the computation for a hook added with `execute_with_hook`, or None for the whole thread.
+	runtime: Option<usize>,
+	// The thresholds crossed in the current computation.
+	crossed: RefCell<Vec<usize>>,
+}
+
+impl Entry {
+	fn sees(&self, runtime: Option<usize>) -> bool {
+		self.runtime.is_none() || self.runtime == runtime
+	}
+}
+
+type Hooks = Rc<Vec<Rc<Entry>>>;
+
+thread_local! {
+	// Replaced rather than mutated, so it can be iterated while a hook adds or removes hooks.
+	static HOOKS: RefCell<Hooks> = RefCell::new(Rc::new(Vec::new()));
+	static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Identifies a hook added with [`add_thread_hook`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HookId(u64);
+
+fn hooks() -> Option<Hooks> {
+	if introspect::is_watched(Watcher::Hooks) {
+		Some(HOOKS.with(|h| h.borrow().clone()))
+	} else {
+		None
+	}
+}
+
+fn update(f: impl FnOnce(&mut Vec<Rc<Entry>>)) {
+	HOOKS.with(|h| {
+		let mut hooks = h.borrow_mut();
+		let mut updated = (**hooks).clone();
+		f(&mut updated);
+		introspect::watch(Watcher::Hooks, !updated.is_empty());
+		*hooks = Rc::new(updated);
+	});
+}
+
+fn add(hook: Rc<dyn Hook>, runtime: Option<usize>) -> HookId {
+	let id = HookId(NEXT_ID.with(|n| n.replace(n.get() + 1)));
+	let entry = Entry {
+		id,
+		hook,
+		runtime,
+		crossed: RefCell::new(Vec::new()),
+	};
+	update(|hooks| hooks.push(Rc::new(entry)));
+	id
+}
+
+/// Add a hook for every computation on this thread.
+pub fn add_thread_hook(hook: impl Hook + 'static) -> HookId {
+	add(Rc::new(hook), None)
+}
+
+/// Remove a hook added with [`add_thread_hook`]. Returns whether it was there.
+pub fn remove_thread_hook(id: HookId) -> bool {
+	let mut found = false;
+	update(|hooks| {
+		let len = hooks.len();
+		hooks.retain(|entry| entry.id != id);
+		found = hooks.len() != len;
+	});
+	found
+}
+
+/// Run a future on the heap stack, like [`execute`](crate::execute), with a hook for this computation only.
+///
+/// The hook is only told about the frames this call runs,
+/// not about those of [`Generator`](crate::Generator)s it resumes, which run on their own.
+pub fn execute_with_hook<H, F>(hook: H, fut: F) -> F::Output
+where
+	H: Hook + 'static,
+	F: Future + 'static,
+{
+	// Removes the hook even if we panic.
+	struct Remove(HookId);
+	impl Drop for Remove {
+		fn drop(&mut self) {
+			remove_thread_hook(self.0);
+		}
+	}
+	runtime::with_runtime(|runtime| {
+		let hook: Rc<dyn Hook> = Rc::new(hook);
+		let remove = Remove(add(hook.clone(), Some(runtime::id(runtime))));
+		let output = execute(fut);
+		drop(remove);
+		hook.on_complete();
+		output
+	})
+}
+
+pub(crate) fn push(frame: &StackFrame, depth: usize) {
+	if let Some(hooks) = hooks() {
+		let runtime = runtime::current_id();
+		for entry in hooks.iter().filter(|entry| entry.sees(runtime)) {
+			entry.hook.on_push(frame, depth);
+			for &threshold in entry.hook.depth_thresholds() {
+				// Not borrowed during the call, which might push frames.
+				let crossed = {
+					let mut crossed = entry.crossed.borrow_mut();
+					let new = threshold <= depth && !crossed.contains(&threshold);
+					if new {
+						crossed.push(threshold);
+					}
+					new
+				};
+				if crossed {
+					entry.hook.on_depth_crossed(threshold);
+				}
+			}
+		}
+	}
+}
+
+pub(crate) fn pop(frame: &StackFrame, depth: usize) {
+	if let Some(hooks) = hooks() {
+		let runtime = runtime::current_id();
+		for entry in hooks.iter().filter(|entry| entry.sees(runtime)) {
+			entry.hook.on_pop(frame, depth);
+		}
+	}
+}
+
+// A computation that isn't nested in another one completed.
+pub(crate) fn complete() {
+	if let Some(hooks) = hooks() {
+		for entry in hooks.iter().filter(|entry| entry.runtime.is_none()) {
+			entry.crossed.borrow_mut().clear();
+			entry.hook.on_complete();
+		}
+	}
+}
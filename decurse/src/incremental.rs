@@ -0,0 +1,287 @@
+//! Incremental recomputation of memoized recursions.
+//!
+//! While a memoized frame runs, decurse records what it reads:
+//! the outputs of other memoized calls, and values from [`Input`]s.
+//! Changing an input with [`Input::set`] or [`Input::invalidate`] removes every cached output
+//! that depends on it, directly or through other memoized calls.
+//! The next call then re-executes only those frames and reuses the cache for the rest.
+//!
+//! ```
+//! use decurse::incremental::Input;
+//!
+//! thread_local! {
+//!     static WEIGHTS: Input<usize, u64> = Input::new();
+//! }
+//!
+//! // The total weight of nodes 0 to `node`.
+//! #[decurse::decurse(memoize)]
+//! fn total(node: usize) -> u64 {
+//!     let own = WEIGHTS.with(|w| w.get(&node)).unwrap_or(0);
+//!     if node == 0 {
+//!         own
+//!     } else {
+//!         total(node - 1) + own
+//!     }
+//! }
+//!
+//! WEIGHTS.with(|w| (0..1000).for_each(|i| w.set(i, 1)));
+//! assert_eq!(total(999), 1000);
+//! // Only `total(500)` to `total(999)` are recomputed.
+//! WEIGHTS.with(|w| w.set(500, 11));
+//! assert_eq!(total(999), 1010);
+//! ```
+//!
+//! Removing an entry from a memoization cache (including by eviction) doesn't remove the outputs computed from it.
+//! They still get invalidated when an input the removed entry read changes.
+
+use std::{
+	cell::{Cell, RefCell},
+	collections::{HashMap, HashSet},
+	hash::Hash,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+// Identifies a cached output or an input value in the dependency graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct NodeId(u64);
+
+impl NodeId {
+	pub(crate) fn new() -> Self {
+		// Global, so that ids stay unique even if a cache is moved to another thread.
+		static NEXT: AtomicU64 = AtomicU64::new(0);
+		Self(NEXT.fetch_add(1, Ordering::Relaxed))
+	}
+}
+
+#[derive(Default)]
+struct Node {
+	// The nodes this one read.
+	dependencies: HashSet<NodeId>,
+	// The nodes that read this one.
+	dependents: HashSet<NodeId>,
+	// Removes the cached output this node stands for.
+	evict: Option<Box<dyn FnOnce()>>,
+}
+
+thread_local! {
+	static GRAPH: RefCell<HashMap<NodeId, Node>> = RefCell::new(HashMap::new());
+	// The memoized frame being polled, if any.
+	static CURRENT: Cell<Option<NodeId>> = const { Cell::new(None) };
+	// Nodes waiting to be invalidated.
+	static PENDING: RefCell<Vec<NodeId>> = const { RefCell::new(Vec::new()) };
+	static INVALIDATING: Cell<bool> = const { Cell::new(false) };
+}
+
+pub(crate) fn current() -> Option<NodeId> {
+	CURRENT.with(Cell::get)
+}
+
+pub(crate) fn set_current(node: Option<NodeId>) {
+	CURRENT.with(|c| c.set(node));
+}
+
+// Record that the frame being polled read the input value `node`.
+pub(crate) fn read(node: NodeId) {
+	if let Some(current) = current() {
+		GRAPH.with(|g| link(&mut g.borrow_mut(), node, current));
+	}
+}
+
+// Record that the frame being polled read the cached output `node`.
+// An output that didn't read anything can't be invalidated, so it isn't in the graph and isn't recorded.
+pub(crate) fn read_output(node: NodeId) {
+	if let Some(current) = current() {
+		GRAPH.with(|g| {
+			let mut graph = g.borrow_mut();
+			if graph.contains_key(&node) {
+				link(&mut graph, node, current);
+			}
+		});
+	}
+}
+
+fn link(graph: &mut HashMap<NodeId, Node>, node: NodeId, current: NodeId) {
+	if current != node {
+		graph.entry(node).or_default().dependents.insert(current);
+		graph.entry(current).or_default().dependencies.insert(node);
+	}
+}
+
+// Once the output `node` is computed for `caller`, set how to remove it when it is invalidated,
+// unless it didn't read anything that can change.
+pub(crate) fn complete(node: NodeId, caller: Option<NodeId>, evict: impl FnOnce() + 'static) {
+	GRAPH.with(|g| {
+		let mut graph = g.borrow_mut();
+		if let Some(entry) = graph.get_mut(&node) {
+			entry.evict = Some(Box::new(evict));
+			if let Some(caller) = caller {
+				link(&mut graph, node, caller);
+			}
+		}
+	});
+}
+
+// Remove `node` and everything that depends on it from the graph, evicting their cached outputs.
+pub(crate) fn invalidate(node: NodeId) {
+	PENDING.with(|p| p.borrow_mut().push(node));
+	// If evicting ends up invalidating more nodes, they just join the queue.
+	if INVALIDATING.with(|i| i.replace(true)) {
+		return;
+	}
+	while let Some(node) = PENDING.with(|p| p.borrow_mut().pop()) {
+		let evict = GRAPH.with(|g| {
+			let mut graph = g.borrow_mut();
+			let Node {
+				dependencies,
+				dependents,
+				evict,
+			} = graph.remove(&node)?;
+			for dependency in &dependencies {
+				if let Some(dependency) = graph.get_mut(dependency) {
+					dependency.dependents.remove(&node);
+				}
+			}
+			PENDING.with(|p| p.borrow_mut().extend(dependents));
+			evict
+		});
+		if let Some(evict) = evict {
+			evict();
+		}
+	}
+	INVALIDATING.with(|i| i.set(false));
+}
+
+// Remove `node` from the graph without invalidating anything, when its cached output is removed.
+// What it read is now read directly by what read it.
+pub(crate) fn forget(node: NodeId) {
+	// The graph might already be gone if a thread-local cache is dropped when the thread exits.
+	let removed = GRAPH.try_with(|g| {
+		let mut graph = g.borrow_mut();
+		let removed = graph.remove(&node)?;
+		for dependency in &removed.dependencies {
+			if let Some(dependency) = graph.get_mut(dependency) {
+				dependency.dependents.remove(&node);
+				dependency.dependents.extend(&removed.dependents);
+			}
+		}
+		for dependent in &removed.dependents {
+			if let Some(dependent) = graph.get_mut(dependent) {
+				dependent.dependencies.remove(&node);
+				dependent.dependencies.extend(&removed.dependencies);
+			}
+		}
+		Some(removed)
+	});
+	// The evict closure holds a cache, so drop it outside the borrow.
+	drop(removed);
+}
+
+// Forget the cached outputs, when every cache on the thread is cleared.
+// The inputs stay, with what the frames still running read from them.
+pub(crate) fn clear() {
+	let removed: Vec<Node> = GRAPH.with(|g| {
+		let mut graph = g.borrow_mut();
+		let outputs: HashSet<NodeId> = graph
+			.iter()
+			.filter(|(_, node)| node.evict.is_some())
+			.map(|(id, _)| *id)
+			.collect();
+		let removed = outputs.iter().filter_map(|id| graph.remove(id)).collect();
+		for node in graph.values_mut() {
+			node.dependencies.retain(|id| !outputs.contains(id));
+			node.dependents.retain(|id| !outputs.contains(id));
+		}
+		graph.retain(|_, node| !node.dependencies.is_empty() || !node.dependents.is_empty());
+		removed
+	});
+	// The evict closures hold caches, so drop them outside the borrow.
+	drop(removed);
+}
+
+/// Values that memoized recursions read, and that can change between runs.
+///
+/// Reading a value from inside a memoized frame records the frame as depending on it.
+/// Changing the value removes the cached outputs of the frames that depend on it.
+///
+/// Declare it in a `thread_local!`, like a [`Cache`](crate::memo::Cache).
+pub struct Input<K, V> {
+	values: RefCell<HashMap<K, (V, NodeId)>>,
+	// Keys that were read without having a value, so that setting them invalidates the readers.
+	missing: RefCell<HashMap<K, NodeId>>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Input<K, V> {
+	/// Create an input without any value.
+	pub fn new() -> Self {
+		Self {
+			values: RefCell::new(HashMap::new()),
+			missing: RefCell::new(HashMap::new()),
+		}
+	}
+	/// Get the value for the key, recording the read.
+	pub fn get(&self, key: &K) -> Option<V> {
+		let (value, node) = match self.values.borrow().get(key) {
+			Some((value, node)) => (Some(value.clone()), *node),
+			None => (
+				None,
+				*self
+					.missing
+					.borrow_mut()
+					.entry(key.clone())
+					.or_insert_with(NodeId::new),
+			),
+		};
+		read(node);
+		value
+	}
+	/// Set the value for the key.
+	/// If it is different from the old value, the outputs that read the old value are invalidated.
+	pub fn set(&self, key: K, value: V)
+	where
+		V: PartialEq,
+	{
+		let old = self.values.borrow().get(&key).map(|(old, _)| *old == value);
+		match old {
+			Some(true) => (),
+			Some(false) => {
+				let node = NodeId::new();
+				let old = self.values.borrow_mut().insert(key, (value, node));
+				// UNWRAP Safety: We just checked the key is there.
+				invalidate(old.unwrap().1);
+			}
+			None => {
+				let missing = self.missing.borrow_mut().remove(&key);
+				self.values.borrow_mut().insert(key, (value, NodeId::new()));
+				if let Some(node) = missing {
+					invalidate(node);
+				}
+			}
+		}
+	}
+	/// Mark the value for the key as changed, invalidating the outputs that read it.
+	///
+	/// Use this if the value was mutated in place, or if it depends on something decurse doesn't know about.
+	pub fn invalidate(&self, key: &K) {
+		let node = match self.values.borrow_mut().get_mut(key) {
+			Some((_, node)) => Some(std::mem::replace(node, NodeId::new())),
+			None => self.missing.borrow_mut().remove(key),
+		};
+		if let Some(node) = node {
+			invalidate(node);
+		}
+	}
+	/// Remove the value for the key, invalidating the outputs that read it.
+	pub fn remove(&self, key: &K) -> Option<V> {
+		let removed = self.values.borrow_mut().remove(key);
+		removed.map(|(value, node)| {
+			invalidate(node);
+			value
+		})
+	}
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for Input<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
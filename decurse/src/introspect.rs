@@ -0,0 +1,260 @@
+use crate::{hooks, record};
+use std::{
+	cell::{Cell, RefCell},
+	fmt, mem,
+	rc::Rc,
+};
+
+thread_local! {
+	// The frames of `Recursion`s (including `#[decurse]` functions) live on this thread, outermost first.
+	// While a generator runs, this only has its frames, and the rest are in `OUTER`.
+	static STACK: RefCell<Vec<Run>> = const { RefCell::new(Vec::new()) };
+	// The number of frames in `STACK`.
+	static LEN: Cell<usize> = const { Cell::new(0) };
+	// The frames below each running generator, outermost first, with their number.
+	static OUTER: RefCell<Vec<(Vec<Run>, usize)>> = const { RefCell::new(Vec::new()) };
+	// The number of frames in `OUTER`.
+	static BELOW: Cell<usize> = const { Cell::new(0) };
+	// The `Watcher`s that are on, as bits.
+	static WATCHED: Cell<u8> = const { Cell::new(0) };
+}
+
+// What wants to be told about every frame pushed and popped.
+// Without any of them, pushing a frame only counts it.
+#[derive(Clone, Copy)]
+pub(crate) enum Watcher {
+	Record = 1,
+	Hooks = 2,
+}
+
+pub(crate) fn watch(watcher: Watcher, on: bool) {
+	WATCHED.with(|w| {
+		if on {
+			w.set(w.get() | watcher as u8)
+		} else {
+			w.set(w.get() & !(watcher as u8))
+		}
+	});
+}
+
+pub(crate) fn is_watched(watcher: Watcher) -> bool {
+	WATCHED.with(|w| w.get() & watcher as u8 != 0)
+}
+
+// Consecutive frames of the same function, without arguments, are kept as a single run.
+struct Run {
+	frame: StackFrame,
+	len: usize,
+}
+
+fn push_run(stack: &mut Vec<Run>, name: &'static str, args: Option<Rc<str>>) {
+	match stack.last_mut() {
+		Some(run) if args.is_none() && run.frame.args.is_none() && run.frame.name == name => {
+			run.len += 1
+		}
+		_ => stack.push(Run {
+			frame: StackFrame::new(name, args),
+			len: 1,
+		}),
+	}
+}
+
+// Pop the innermost frame, returning it if `want` is set.
+fn pop_run(stack: &mut Vec<Run>, want: bool) -> Option<StackFrame> {
+	// UNWRAP Safety: `LEN` counts the frames, and callers only pop when it's positive.
+	let run = stack.last_mut().unwrap();
+	run.len -= 1;
+	if run.len == 0 {
+		// UNWRAP Safety: We just looked at it.
+		Some(stack.pop().unwrap().frame).filter(|_| want)
+	} else if want {
+		Some(run.frame.clone())
+	} else {
+		None
+	}
+}
+
+fn expand(runs: &[Run], stack: &mut Vec<StackFrame>) {
+	for run in runs {
+		for _ in 0..run.len {
+			stack.push(run.frame.clone());
+		}
+	}
+}
+
+/// A live frame of a decursed function, as returned by [`stack`].
+#[derive(Clone, Debug)]
+pub struct StackFrame {
+	name: &'static str,
+	args: Option<Rc<str>>,
+}
+
+impl StackFrame {
+	pub(crate) fn new(name: &'static str, args: Option<Rc<str>>) -> Self {
+		Self { name, args }
+	}
+	/// The name of the function, with its module path.
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+	/// The `Debug` representation of the arguments,
+	/// if the function opted in with `#[decurse(debug_args)]` or [`Recursion::debug_args`](crate::Recursion::debug_args).
+	pub fn args(&self) -> Option<&str> {
+		self.args.as_deref()
+	}
+}
+
+impl fmt::Display for StackFrame {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.args {
+			Some(args) => write!(f, "{}({})", self.name, args),
+			None => write!(f, "{}", self.name),
+		}
+	}
+}
+
+/// The number of live frames of decursed functions on this thread, counting the one calling this.
+///
+/// This is 0 outside of decursed functions.
+/// Only functions made with `#[decurse]` or [`Recursion`](crate::Recursion) are counted.
+///
+/// ```
+/// #[decurse::decurse]
+/// fn count_down(x: u32) -> usize {
+///     if x == 0 {
+///         decurse::depth()
+///     } else {
+///         count_down(x - 1)
+///     }
+/// }
+/// assert_eq!(count_down(100000), 100001);
+/// assert_eq!(decurse::depth(), 0);
+/// ```
+pub fn depth() -> usize {
+	BELOW.with(Cell::get) + LEN.with(Cell::get)
+}
+
+/// The live frames of decursed functions on this thread, outermost first.
+/// The last one is the frame calling this.
+///
+/// Each frame has the function name.
+/// Functions that opt in with `#[decurse(debug_args)]` also record their arguments.
+///
+/// ```
+/// #[decurse::decurse(debug_args)]
+/// fn find(path: String, x: u32) -> Vec<String> {
+///     if x == 0 {
+///         decurse::stack()
+///             .iter()
+///             .map(|f| f.args().unwrap().to_string())
+///             .collect()
+///     } else {
+///         find(format!("{}/{}", path, x), x - 1)
+///     }
+/// }
+/// assert_eq!(
+///     find(String::new(), 2),
+///     ["\"\", 2", "\"/2\", 1", "\"/2/1\", 0"]
+/// );
+/// ```
+pub fn stack() -> Vec<StackFrame> {
+	let mut stack = Vec::with_capacity(depth());
+	OUTER.with(|o| {
+		o.borrow()
+			.iter()
+			.for_each(|(runs, _)| expand(runs, &mut stack))
+	});
+	STACK.with(|s| expand(&s.borrow(), &mut stack));
+	stack
+}
+
+// The frames of a suspended generator, kept off the logical stack.
+#[derive(Default)]
+pub(crate) struct Detached {
+	frames: Vec<Run>,
+	len: usize,
+	recorded: record::Detached,
+}
+
+// The frames of a generator, put on top of the logical stack while it runs.
+pub(crate) struct Attached<'a> {
+	detached: &'a mut Detached,
+}
+
+impl Detached {
+	pub(crate) fn attach(&mut self) -> Attached<'_> {
+		let frames = mem::take(&mut self.frames);
+		let outer = STACK.with(|s| mem::replace(&mut *s.borrow_mut(), frames));
+		let outer_len = LEN.with(|l| l.replace(self.len));
+		BELOW.with(|b| b.set(b.get() + outer_len));
+		OUTER.with(|o| o.borrow_mut().push((outer, outer_len)));
+		record::attach(mem::take(&mut self.recorded));
+		Attached { detached: self }
+	}
+}
+
+impl Drop for Attached<'_> {
+	fn drop(&mut self) {
+		self.detached.recorded = record::detach();
+		// `try_with`, since generators might be dropped while the thread locals are being destroyed.
+		let _ = OUTER.try_with(|o| {
+			// UNWRAP Safety: `attach` pushed it.
+			let (outer, outer_len) = o.borrow_mut().pop().unwrap();
+			BELOW.with(|b| b.set(b.get() - outer_len));
+			self.detached.len = LEN.with(|l| l.replace(outer_len));
+			self.detached.frames = STACK.with(|s| mem::replace(&mut *s.borrow_mut(), outer));
+		});
+	}
+}
+
+// Keeps a frame on the logical stack until the frame completes or is dropped.
+pub(crate) struct Entered {
+	// In `STACK`, so it doesn't change when a generator is resumed at another depth.
+	position: usize,
+	// Whether the record and hooks were told about the frame, so they are told when it's popped too.
+	watched: (bool, bool),
+}
+
+impl Entered {
+	pub(crate) fn push(name: &'static str, args: Option<Rc<str>>) -> Self {
+		let position = LEN.with(Cell::get);
+		let watched = WATCHED.with(Cell::get);
+		let watched = (
+			watched & Watcher::Record as u8 != 0,
+			watched & Watcher::Hooks as u8 != 0,
+		);
+		if watched != (false, false) {
+			let frame = StackFrame::new(name, args.clone());
+			let depth = BELOW.with(Cell::get) + position + 1;
+			if watched.0 {
+				record::push(&frame, depth);
+			}
+			if watched.1 {
+				hooks::push(&frame, depth);
+			}
+		}
+		STACK.with(|s| push_run(&mut s.borrow_mut(), name, args));
+		LEN.with(|l| l.set(position + 1));
+		Self { position, watched }
+	}
+}
+
+impl Drop for Entered {
+	fn drop(&mut self) {
+		if self.watched.0 {
+			record::pop();
+		}
+		let below = BELOW.try_with(Cell::get).unwrap_or(0);
+		// `try_with`, since frames might be dropped while the thread locals are being destroyed.
+		let _ = LEN.try_with(|l| {
+			while l.get() > self.position {
+				let popped = STACK.with(|s| pop_run(&mut s.borrow_mut(), self.watched.1));
+				l.set(l.get() - 1);
+				// Outside the borrow, since hooks can look at the stack.
+				if let Some(frame) = popped {
+					hooks::pop(&frame, below + l.get() + 1);
+				}
+			}
+		});
+	}
+}
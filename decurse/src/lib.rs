@@ -1,6 +1,6 @@
 //! See the [README](https://github.com/wishawa/decurse) for an overview of this crate and how to use.
 //!
-//! The only things here are the two macros.
+//! The main things here are the two macros.
 //! To use, put them above your recursive function.
 //!
 //! ```text
@@ -13,10 +13,43 @@
 //! fn some_function(...) -> ...
 //! ```
 //! Also make sure to read [the Limitations section in the README](https://github.com/wishawa/decurse#limitations).
+//!
+//! The executor the macros use is also available directly, through [`execute`] and [`call`].
 
 /// Private for use by the macro only.
 pub mod for_macro_only;
 
+mod abort;
+mod backtrace;
+mod cycle;
+pub mod deep;
+mod generator;
+pub mod graph;
+pub mod hooks;
+pub mod incremental;
+mod introspect;
+pub mod memo;
+pub mod parse;
+pub mod record;
+mod recurse;
+mod runtime;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod state;
+#[cfg(feature = "tracing")]
+pub mod trace;
+mod trampoline;
+pub mod view;
+pub mod walk;
+pub use abort::{AbortHandle, Cancelled};
+pub use backtrace::{install_panic_hook, try_execute, Backtrace, Panicked};
+pub use generator::Generator;
+pub use introspect::{depth, stack, StackFrame};
+pub use recurse::{Recurse, Recursion};
+pub use runtime::{call, execute, Call};
+pub use state::State;
+pub use trampoline::Trampoline;
+
 /// Macro to make recursive functions run on the heap.
 ///
 /// This is the version you should prefer.
@@ -34,3 +67,66 @@ pub use for_macro_only::sound::decurse_sound as decurse;
 /// so there is a small chance that this might actually be sound,
 /// so for brave souls, *try it out*!
 pub use for_macro_only::unsound::decurse_unsound;
+
+/// Macro to make a recursive function return a [`Generator`] of the items it yields with [`yield_item!`].
+///
+/// Write the function as returning `decurse::Generator<Item>`, with no output of its own.
+/// Recursive calls are statements, and the items of the callee are yielded from the same generator.
+///
+/// ```
+/// use decurse::{yield_item, Generator};
+///
+/// #[decurse::generator]
+/// fn count_down(x: u32) -> Generator<u32> {
+///     yield_item!(x);
+///     if x > 0 {
+///         count_down(x - 1);
+///     }
+/// }
+///
+/// assert_eq!(count_down(100000).count(), 100001);
+/// ```
+pub use for_macro_only::sound::decurse_generator as generator;
+
+/// Derive [`deep::DeepDrop`], and a `Drop` that doesn't recurse into the children.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepDrop;
+
+/// Derive [`deep::DeepClone`], and a `Clone` that clones the children on the heap.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepClone;
+
+/// Derive [`deep::DeepPartialEq`], and a `PartialEq` that compares the children on the heap.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepPartialEq;
+
+/// Derive `Eq`, for types deriving [`DeepPartialEq`].
+pub use decurse_macro::DeepEq;
+
+/// Derive [`deep::DeepPartialOrd`], and a `PartialOrd` that compares the children on the heap.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepPartialOrd;
+
+/// Derive [`deep::DeepOrd`], and an `Ord` that compares the children on the heap.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepOrd;
+
+/// Derive [`deep::DeepHash`], and a `Hash` that hashes the children on the heap.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepHash;
+
+/// Derive [`deep::DeepDebug`], and a `Debug` that formats the children on the heap.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::DeepDebug;
+
+/// Derive [`deep::Recursive`], generating the layer type for folding.
+///
+/// See the [`deep`] module for how the children are found.
+pub use decurse_macro::Recursive;
@@ -0,0 +1,287 @@
+//! Caches for memoized recursions.
+//!
+//! `#[decurse(memoize)]` (or [`Recursion::memoize`](crate::Recursion::memoize)) makes a recursion look up
+//! its arguments in a [`Cache`] before putting a new frame on the heap stack,
+//! and store the output once the frame finishes.
+//!
+//! ```
+//! #[decurse::decurse(memoize)]
+//! fn fibonacci(x: u64) -> u64 {
+//!     if x == 0 || x == 1 {
+//!         1
+//!     } else {
+//!         fibonacci(x - 1) + fibonacci(x - 2)
+//!     }
+//! }
+//! assert_eq!(fibonacci(90), 4660046610375530309);
+//! ```
+//!
+//! By default each memoized function gets its own thread-local cache.
+//! You can supply your own cache instead, to limit its size or clear it.
+//!
+//! ```
+//! use decurse::memo::Cache;
+//!
+//! thread_local! {
+//!     static FIB_CACHE: Cache<(u64,), u64> = Cache::with_limit(1000);
+//! }
+//!
+//! #[decurse::decurse(memoize(cache = FIB_CACHE))]
+//! fn fibonacci(x: u64) -> u64 {
+//!     if x == 0 || x == 1 {
+//!         1
+//!     } else {
+//!         fibonacci(x - 1) + fibonacci(x - 2)
+//!     }
+//! }
+//! assert_eq!(fibonacci(90), 4660046610375530309);
+//! assert_eq!(FIB_CACHE.with(Cache::len), 91);
+//! FIB_CACHE.with(Cache::clear);
+//! ```
+
+use crate::{
+	incremental::{self, NodeId},
+	runtime::{Frame, Slot},
+};
+use std::{
+	cell::{Cell, RefCell},
+	collections::{HashMap, VecDeque},
+	hash::Hash,
+	rc::{Rc, Weak},
+	thread::LocalKey,
+};
+
+thread_local! {
+	// Bumped by `clear_thread_caches`. A cache that sees a new epoch clears itself.
+	static EPOCH: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Clear every [`Cache`] used on this thread, including the default caches of `#[decurse(memoize)]` functions.
+pub fn clear_thread_caches() {
+	EPOCH.with(|e| e.set(e.get() + 1));
+	incremental::clear();
+}
+
+/// Memoization cache, mapping arguments to outputs.
+///
+/// For a function generated by `#[decurse(memoize)]`, the key is the tuple of all the arguments.
+///
+/// A cache created with [`Cache::with_limit`] holds at most that many entries.
+/// When it is full, the oldest entry is evicted.
+///
+/// Entries computed from [`Input`](crate::incremental::Input)s are removed when the inputs change.
+/// See the [`incremental`](crate::incremental) module.
+pub struct Cache<K, V> {
+	map: RefCell<HashMap<K, (V, NodeId)>>,
+	// Insertion order, only kept if there is a limit.
+	order: RefCell<VecDeque<K>>,
+	limit: Option<usize>,
+	epoch: Cell<u64>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> {
+	/// Create an empty cache without size limit.
+	pub fn new() -> Self {
+		Self {
+			map: RefCell::new(HashMap::new()),
+			order: RefCell::new(VecDeque::new()),
+			limit: None,
+			epoch: Cell::new(EPOCH.with(Cell::get)),
+		}
+	}
+	/// Create an empty cache that holds at most `limit` entries.
+	pub fn with_limit(limit: usize) -> Self {
+		let mut cache = Self::new();
+		cache.limit = Some(limit);
+		cache
+	}
+	/// Get the cached output for the given arguments.
+	pub fn get(&self, key: &K) -> Option<V> {
+		self.get_node(key).map(|(value, _)| value)
+	}
+	/// Store the output for the given arguments.
+	pub fn insert(&self, key: K, value: V) {
+		self.insert_node(key, value, NodeId::new());
+	}
+	/// Remove the cached output for the given arguments.
+	pub fn remove(&self, key: &K) -> Option<V> {
+		self.sync_epoch();
+		let removed = self.map.borrow_mut().remove(key);
+		removed.map(|(value, node)| {
+			self.forget_order(key);
+			incremental::forget(node);
+			value
+		})
+	}
+	/// Remove all the entries.
+	pub fn clear(&self) {
+		let map = self.map.take();
+		self.order.borrow_mut().clear();
+		for (_, (_, node)) in map {
+			incremental::forget(node);
+		}
+	}
+	pub(crate) fn get_node(&self, key: &K) -> Option<(V, NodeId)> {
+		self.sync_epoch();
+		self.map.borrow().get(key).cloned()
+	}
+	pub(crate) fn insert_node(&self, key: K, value: V, node: NodeId) {
+		self.sync_epoch();
+		if self.limit == Some(0) {
+			incremental::forget(node);
+			return;
+		}
+		let mut evicted = Vec::new();
+		{
+			let mut map = self.map.borrow_mut();
+			let mut order = self.order.borrow_mut();
+			match map.insert(key.clone(), (value, node)) {
+				Some((_, old)) => evicted.push(old),
+				None if self.limit.is_some() => order.push_back(key),
+				None => (),
+			}
+			if let Some(limit) = self.limit {
+				while map.len() > limit {
+					// UNWRAP Safety: Every key in the map is in the order queue.
+					let oldest = order.pop_front().unwrap();
+					// UNWRAP Safety: Every key in the order queue is in the map.
+					evicted.push(map.remove(&oldest).unwrap().1);
+				}
+			}
+		}
+		for node in evicted {
+			incremental::forget(node);
+		}
+	}
+	fn forget_order(&self, key: &K) {
+		if self.limit.is_some() {
+			self.order.borrow_mut().retain(|k| k != key);
+		}
+	}
+	// Remove the entry for the key if it still belongs to `node`, without touching the graph.
+	fn discard(&self, key: &K, node: NodeId) {
+		let mut map = self.map.borrow_mut();
+		if matches!(map.get(key), Some((_, n)) if *n == node) {
+			map.remove(key);
+			self.forget_order(key);
+		}
+	}
+	/// The number of entries in the cache.
+	pub fn len(&self) -> usize {
+		self.sync_epoch();
+		self.map.borrow().len()
+	}
+	/// Whether the cache has no entry.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+	fn sync_epoch(&self) {
+		let epoch = EPOCH.with(Cell::get);
+		if self.epoch.get() != epoch {
+			self.epoch.set(epoch);
+			self.clear();
+		}
+	}
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Default for Cache<K, V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K, V> Drop for Cache<K, V> {
+	fn drop(&mut self) {
+		for (_, (_, node)) in self.map.get_mut().drain() {
+			incremental::forget(node);
+		}
+	}
+}
+
+/// Something that gives access to a [`Cache`]:
+/// either a thread-local (`&'static LocalKey<Cache<K, V>>`) or a shared `Rc<Cache<K, V>>`.
+pub trait CacheRef<K, V>: Clone + 'static {
+	/// A reference that doesn't keep the cache alive.
+	type Weak: 'static;
+	/// Call `f` with the cache.
+	fn with_cache<R>(&self, f: impl FnOnce(&Cache<K, V>) -> R) -> R;
+	/// Get a reference that doesn't keep the cache alive.
+	fn downgrade(&self) -> Self::Weak;
+	/// Call `f` with the cache, if it is still alive.
+	fn with_weak<R>(weak: &Self::Weak, f: impl FnOnce(&Cache<K, V>) -> R) -> Option<R>;
+}
+
+impl<K: 'static, V: 'static> CacheRef<K, V> for &'static LocalKey<Cache<K, V>> {
+	type Weak = Self;
+	fn with_cache<R>(&self, f: impl FnOnce(&Cache<K, V>) -> R) -> R {
+		self.with(f)
+	}
+	fn downgrade(&self) -> Self {
+		self
+	}
+	fn with_weak<R>(weak: &Self, f: impl FnOnce(&Cache<K, V>) -> R) -> Option<R> {
+		weak.try_with(f).ok()
+	}
+}
+
+impl<K: 'static, V: 'static> CacheRef<K, V> for Rc<Cache<K, V>> {
+	type Weak = Weak<Cache<K, V>>;
+	fn with_cache<R>(&self, f: impl FnOnce(&Cache<K, V>) -> R) -> R {
+		f(self)
+	}
+	fn downgrade(&self) -> Self::Weak {
+		Rc::downgrade(self)
+	}
+	fn with_weak<R>(weak: &Self::Weak, f: impl FnOnce(&Cache<K, V>) -> R) -> Option<R> {
+		weak.upgrade().map(|cache| f(&cache))
+	}
+}
+
+// Type-erased memoization used by `Recursion`.
+pub(crate) trait Memo<A, O> {
+	fn get(&self, args: &A) -> Option<O>;
+	// Create the frame computing the output for `args` using `body`,
+	// wrapped so that the output is stored once it's done.
+	fn remember(&self, args: A, body: &dyn Fn(A) -> (Frame, Slot<O>)) -> (Frame, Slot<O>);
+}
+
+pub(crate) struct MemoIn<C>(pub(crate) C);
+
+impl<A, O, C> Memo<A, O> for MemoIn<C>
+where
+	A: Hash + Eq + Clone + 'static,
+	O: Clone + 'static,
+	C: CacheRef<A, O>,
+{
+	fn get(&self, args: &A) -> Option<O> {
+		let (output, node) = self.0.with_cache(|cache| cache.get_node(args))?;
+		incremental::read_output(node);
+		Some(output)
+	}
+	fn remember(&self, args: A, body: &dyn Fn(A) -> (Frame, Slot<O>)) -> (Frame, Slot<O>) {
+		let key = args.clone();
+		let cache = self.0.clone();
+		let node = NodeId::new();
+		let (frame, slot) = body(args);
+		let slot_clone = slot.clone();
+		let frame = Box::pin(async move {
+			// The caller depends on the new output.
+			let caller = incremental::current();
+			// The executor keeps this as the current node whenever it polls the frame,
+			// or anything it calls that isn't memoized itself.
+			incremental::set_current(Some(node));
+			frame.await;
+			// UNWRAP Safety: The frame fills the slot when it completes.
+			let output = slot_clone.take().unwrap();
+			// The graph doesn't keep the cache alive.
+			let weak = cache.downgrade();
+			let evict_key = key.clone();
+			incremental::complete(node, caller, move || {
+				C::with_weak(&weak, |c| c.discard(&evict_key, node));
+			});
+			cache.with_cache(|c| c.insert_node(key, output.clone(), node));
+			slot_clone.set(Some(output));
+		});
+		(frame, slot)
+	}
+}
@@ -0,0 +1,539 @@
+//! Parser combinators whose recursion runs on the heap stack.
+//!
+//! A recursive descent parser for a nested format recurses once per level of nesting,
+//! so an input like 100000 `[` overflows the stack.
+//! The parsers of this module run every step as a frame of a heap stack instead,
+//! and can limit the nesting with [`Parser::parse_with_max_depth`] so that hostile input fails early.
+//!
+//! Parsers are built from primitives like [`tag`] and [`satisfy`] with [`seq`], [`alt`], [`many`] and the others,
+//! and refer to themselves through [`recursive`].
+//! [`alt`] backtracks: it tries each alternative from the same position.
+//! When parsing fails, the error has the furthest position reached and what was expected there.
+//!
+//! ```
+//! use decurse::parse::{alt, recursive, sep_by, seq, tag, take_while1, Parser};
+//!
+//! #[derive(Debug, PartialEq, decurse::DeepDrop)]
+//! enum Expr {
+//!     Num(u32),
+//!     List(Vec<Expr>),
+//! }
+//!
+//! fn expr<'i>() -> Parser<'i, Expr> {
+//!     recursive(|expr| {
+//!         let num = take_while1("a digit", |c| c.is_ascii_digit()).map(|n| Expr::Num(n.parse().unwrap()));
+//!         let list = seq((tag("("), sep_by(expr, tag(" ")), tag(")"))).map(|(_, items, _)| Expr::List(items));
+//!         alt((num, list))
+//!     })
+//! }
+//!
+//! assert_eq!(
+//!     expr().parse("(1 (2))"),
+//!     Ok(Expr::List(vec![Expr::Num(1), Expr::List(vec![Expr::Num(2)])]))
+//! );
+//! let error = expr().parse("(1 (2").unwrap_err();
+//! assert_eq!(error.to_string(), r#"expected " " or ")" at line 1, column 6"#);
+//!
+//! let deep = "(".repeat(100000) + &")".repeat(100000);
+//! assert!(expr().parse(&deep).is_ok());
+//! assert!(expr().parse_with_max_depth(&deep, 1000).is_err());
+//! ```
+
+use crate::deep::{Scope, ScopedCall};
+use std::{
+	cell::{Cell, RefCell},
+	error, fmt,
+	future::Future,
+	rc::Rc,
+};
+
+// How a parser failed.
+enum Fail {
+	// Try something else, the failure was recorded in the state.
+	Backtrack,
+	// Give up on the whole parse.
+	Fatal,
+}
+
+type Step<'i, T> = ScopedCall<'i, Result<(T, usize), Fail>>;
+type Run<'i, T> = dyn Fn(&Ctx<'i>, usize) -> Step<'i, T> + 'i;
+
+// Shared by all the frames of a parse.
+struct State {
+	depth: Cell<usize>,
+	max_depth: Option<usize>,
+	// The furthest position where a parser failed, and what was expected there.
+	furthest: Cell<usize>,
+	expected: RefCell<Vec<String>>,
+	too_deep: Cell<Option<usize>>,
+}
+
+#[derive(Clone)]
+struct Ctx<'i> {
+	input: &'i str,
+	scope: Scope<'i>,
+	state: Rc<State>,
+}
+
+impl<'i> Ctx<'i> {
+	fn frame<T: 'i>(
+		&self,
+		fut: impl Future<Output = Result<(T, usize), Fail>> + 'i,
+	) -> Step<'i, T> {
+		self.scope.call(fut)
+	}
+	fn ready<T: 'i>(&self, result: Result<(T, usize), Fail>) -> Step<'i, T> {
+		self.scope.ready(result)
+	}
+	// Record that `expected` was expected at `pos`, unless something went further.
+	fn fail<T>(&self, pos: usize, expected: impl FnOnce() -> String) -> Result<T, Fail> {
+		let state = &self.state;
+		if pos < state.furthest.get() {
+			return Err(Fail::Backtrack);
+		}
+		let expected = expected();
+		if pos > state.furthest.get() {
+			state.furthest.set(pos);
+			state.expected.borrow_mut().clear();
+		}
+		let mut list = state.expected.borrow_mut();
+		if !list.contains(&expected) {
+			list.push(expected);
+		}
+		Err(Fail::Backtrack)
+	}
+}
+
+/// A parser of a `&'i str`, producing a `T`.
+///
+/// Cloning a parser is cheap, and the clone parses the same way.
+pub struct Parser<'i, T> {
+	run: Rc<Run<'i, T>>,
+}
+
+impl<T> Clone for Parser<'_, T> {
+	fn clone(&self) -> Self {
+		Self {
+			run: self.run.clone(),
+		}
+	}
+}
+
+impl<'i, T: 'i> Parser<'i, T> {
+	fn new(run: impl Fn(&Ctx<'i>, usize) -> Step<'i, T> + 'i) -> Self {
+		Self { run: Rc::new(run) }
+	}
+	/// Parse the whole input.
+	pub fn parse(&self, input: &'i str) -> Result<T, Error> {
+		self.parse_in(input, None)
+	}
+	/// Parse the whole input, failing if [`recursive`] parsers are nested more than `max_depth` levels deep.
+	pub fn parse_with_max_depth(&self, input: &'i str, max_depth: usize) -> Result<T, Error> {
+		self.parse_in(input, Some(max_depth))
+	}
+	fn parse_in(&self, input: &'i str, max_depth: Option<usize>) -> Result<T, Error> {
+		let state = Rc::new(State {
+			depth: Cell::new(0),
+			max_depth,
+			furthest: Cell::new(0),
+			expected: RefCell::new(Vec::new()),
+			too_deep: Cell::new(None),
+		});
+		let parser = seq((self.clone(), end())).map(|(value, ())| value);
+		let result = Scope::run(|scope| {
+			let ctx = Ctx {
+				input,
+				scope: scope.clone(),
+				state: state.clone(),
+			};
+			(parser.run)(&ctx, 0)
+		});
+		match result {
+			Ok((value, _)) => Ok(value),
+			Err(_) => {
+				let (position, kind) = match (state.too_deep.get(), max_depth) {
+					(Some(position), Some(max_depth)) => (position, ErrorKind::TooDeep(max_depth)),
+					_ => (
+						state.furthest.get(),
+						ErrorKind::Expected(state.expected.take()),
+					),
+				};
+				Err(Error::new(input, position, kind))
+			}
+		}
+	}
+	/// Transform the output.
+	pub fn map<U: 'i>(self, f: impl Fn(T) -> U + 'i) -> Parser<'i, U> {
+		let f = Rc::new(f);
+		Parser::new(move |ctx, pos| {
+			let (step, f) = ((self.run)(ctx, pos), f.clone());
+			ctx.frame(async move { step.await.map(|(value, pos)| (f(value), pos)) })
+		})
+	}
+	/// Report a failure of this parser at its start as expecting `name`, instead of what its parts expected.
+	pub fn label(self, name: &'i str) -> Self {
+		Parser::new(move |ctx, pos| {
+			let state = ctx.state.clone();
+			let before = (state.furthest.get() == pos).then(|| state.expected.borrow().clone());
+			let step = (self.run)(ctx, pos);
+			ctx.frame(async move {
+				let result = step.await;
+				if result.is_err() && state.furthest.get() == pos {
+					let mut expected = before.unwrap_or_default();
+					expected.push(name.to_string());
+					*state.expected.borrow_mut() = expected;
+				}
+				result
+			})
+		})
+	}
+}
+
+/// What went wrong in [`Parser::parse`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+	/// None of the listed things were found.
+	Expected(Vec<String>),
+	/// The parsers were nested deeper than this maximum.
+	TooDeep(usize),
+}
+
+/// A failure of [`Parser::parse`], at the furthest position the parsers reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error {
+	position: usize,
+	line: usize,
+	column: usize,
+	kind: ErrorKind,
+}
+
+impl Error {
+	fn new(input: &str, position: usize, kind: ErrorKind) -> Self {
+		let before = &input[..position];
+		let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+		Self {
+			position,
+			line: before.matches('\n').count() + 1,
+			column: before[line_start..].chars().count() + 1,
+			kind,
+		}
+	}
+	/// The byte offset in the input.
+	pub fn position(&self) -> usize {
+		self.position
+	}
+	/// The line, starting at 1.
+	pub fn line(&self) -> usize {
+		self.line
+	}
+	/// The column in characters, starting at 1.
+	pub fn column(&self) -> usize {
+		self.column
+	}
+	/// What went wrong.
+	pub fn kind(&self) -> &ErrorKind {
+		&self.kind
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.kind {
+			ErrorKind::Expected(expected) => {
+				f.write_str("expected ")?;
+				for (i, item) in expected.iter().enumerate() {
+					match i {
+						0 => {}
+						i if i == expected.len() - 1 => f.write_str(" or ")?,
+						_ => f.write_str(", ")?,
+					}
+					f.write_str(item)?;
+				}
+			}
+			ErrorKind::TooDeep(max_depth) => {
+				write!(f, "nested deeper than the maximum depth of {}", max_depth)?
+			}
+		}
+		write!(f, " at line {}, column {}", self.line, self.column)
+	}
+}
+
+impl error::Error for Error {}
+
+/// Match the string exactly.
+pub fn tag<'i>(tag: &'i str) -> Parser<'i, &'i str> {
+	Parser::new(move |ctx, pos| {
+		let result = match ctx.input[pos..].starts_with(tag) {
+			true => Ok((&ctx.input[pos..pos + tag.len()], pos + tag.len())),
+			false => ctx.fail(pos, || format!("{:?}", tag)),
+		};
+		ctx.ready(result)
+	})
+}
+
+/// Match one character for which `f` returns `true`. `name` says what was expected if it fails.
+pub fn satisfy<'i>(name: &'i str, f: impl Fn(char) -> bool + 'i) -> Parser<'i, char> {
+	Parser::new(move |ctx, pos| {
+		let result = match ctx.input[pos..].chars().next() {
+			Some(c) if f(c) => Ok((c, pos + c.len_utf8())),
+			_ => ctx.fail(pos, || name.to_string()),
+		};
+		ctx.ready(result)
+	})
+}
+
+/// Match the characters for which `f` returns `true`, as many as there are, maybe none.
+pub fn take_while<'i>(f: impl Fn(char) -> bool + 'i) -> Parser<'i, &'i str> {
+	Parser::new(move |ctx, pos| {
+		let rest = &ctx.input[pos..];
+		let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+		ctx.ready(Ok((&rest[..len], pos + len)))
+	})
+}
+
+/// Match the characters for which `f` returns `true`, at least one. `name` says what was expected if there's none.
+pub fn take_while1<'i>(name: &'i str, f: impl Fn(char) -> bool + 'i) -> Parser<'i, &'i str> {
+	let take = take_while(f);
+	Parser::new(move |ctx, pos| {
+		let step = (take.run)(ctx, pos);
+		let ctx_inner = ctx.clone();
+		ctx.frame(async move {
+			match step.await? {
+				("", _) => ctx_inner.fail(pos, || name.to_string()),
+				result => Ok(result),
+			}
+		})
+	})
+}
+
+/// Skip whitespace, if there is any.
+pub fn whitespace<'i>() -> Parser<'i, ()> {
+	Parser::new(|ctx, pos| {
+		let rest = &ctx.input[pos..];
+		let len = rest.len() - rest.trim_start().len();
+		ctx.ready(Ok(((), pos + len)))
+	})
+}
+
+/// Match the end of the input.
+pub fn end<'i>() -> Parser<'i, ()> {
+	Parser::new(|ctx, pos| {
+		let result = match pos == ctx.input.len() {
+			true => Ok(((), pos)),
+			false => ctx.fail(pos, || "end of input".to_string()),
+		};
+		ctx.ready(result)
+	})
+}
+
+/// A tuple of parsers, for [`seq`].
+pub trait Seq<'i> {
+	/// The tuple of their outputs.
+	type Output;
+	/// The parser running them one after the other.
+	fn into_parser(self) -> Parser<'i, Self::Output>;
+}
+
+macro_rules! impl_seq {
+	($($name:ident: $t:ident),*) => {
+		impl<'i, $($t: 'i),*> Seq<'i> for ($(Parser<'i, $t>,)*) {
+			type Output = ($($t,)*);
+			fn into_parser(self) -> Parser<'i, Self::Output> {
+				let parsers = Rc::new(self);
+				Parser::new(move |ctx, pos| {
+					let (parsers, ctx_inner) = (parsers.clone(), ctx.clone());
+					ctx.frame(async move {
+						let ($($name,)*) = &*parsers;
+						let pos = pos;
+						$(let ($name, pos) = ($name.run)(&ctx_inner, pos).await?;)*
+						Ok((($($name,)*), pos))
+					})
+				})
+			}
+		}
+	};
+}
+
+impl_seq!(a: A);
+impl_seq!(a: A, b: B);
+impl_seq!(a: A, b: B, c: C);
+impl_seq!(a: A, b: B, c: C, d: D);
+impl_seq!(a: A, b: B, c: C, d: D, e: E);
+impl_seq!(a: A, b: B, c: C, d: D, e: E, f: F);
+impl_seq!(a: A, b: B, c: C, d: D, e: E, f: F, g: G);
+impl_seq!(a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H);
+
+/// Run the parsers of the tuple one after the other, producing the tuple of their outputs.
+pub fn seq<'i, S: Seq<'i>>(parsers: S) -> Parser<'i, S::Output> {
+	parsers.into_parser()
+}
+
+/// A tuple or `Vec` of parsers with the same output, for [`alt`].
+pub trait Alt<'i> {
+	/// Their output.
+	type Output;
+	/// The parsers, in order.
+	fn into_parsers(self) -> Vec<Parser<'i, Self::Output>>;
+}
+
+impl<'i, T> Alt<'i> for Vec<Parser<'i, T>> {
+	type Output = T;
+	fn into_parsers(self) -> Vec<Parser<'i, T>> {
+		self
+	}
+}
+
+macro_rules! impl_alt {
+	($($name:ident),*) => {
+		impl<'i, T> Alt<'i> for ($(impl_alt!(@parser $name),)*) {
+			type Output = T;
+			fn into_parsers(self) -> Vec<Parser<'i, T>> {
+				let ($($name,)*) = self;
+				vec![$($name),*]
+			}
+		}
+	};
+	(@parser $name:ident) => { Parser<'i, T> };
+}
+
+impl_alt!(a, b);
+impl_alt!(a, b, c);
+impl_alt!(a, b, c, d);
+impl_alt!(a, b, c, d, e);
+impl_alt!(a, b, c, d, e, f);
+impl_alt!(a, b, c, d, e, f, g);
+impl_alt!(a, b, c, d, e, f, g, h);
+
+/// Try the parsers in order from the same position, producing the output of the first that succeeds.
+pub fn alt<'i, A: Alt<'i>>(parsers: A) -> Parser<'i, A::Output>
+where
+	A::Output: 'i,
+{
+	let parsers = Rc::new(parsers.into_parsers());
+	Parser::new(move |ctx, pos| {
+		let (parsers, ctx_inner) = (parsers.clone(), ctx.clone());
+		ctx.frame(async move {
+			for parser in parsers.iter() {
+				match (parser.run)(&ctx_inner, pos).await {
+					Err(Fail::Backtrack) => {}
+					result => return result,
+				}
+			}
+			Err(Fail::Backtrack)
+		})
+	})
+}
+
+/// Run the parser, producing `None` if it fails.
+pub fn opt<'i, T: 'i>(parser: Parser<'i, T>) -> Parser<'i, Option<T>> {
+	Parser::new(move |ctx, pos| {
+		let step = (parser.run)(ctx, pos);
+		ctx.frame(async move {
+			match step.await {
+				Ok((value, pos)) => Ok((Some(value), pos)),
+				Err(Fail::Backtrack) => Ok((None, pos)),
+				Err(Fail::Fatal) => Err(Fail::Fatal),
+			}
+		})
+	})
+}
+
+// Run `item` as many times as it succeeds (preceded by `sep` after the first), at least `min` times.
+fn repeat<'i, T: 'i, S: 'i>(
+	item: Parser<'i, T>,
+	sep: Option<Parser<'i, S>>,
+	min: usize,
+) -> Parser<'i, Vec<T>> {
+	let parsers = Rc::new((item, sep));
+	Parser::new(move |ctx, pos| {
+		let (parsers, ctx_inner) = (parsers.clone(), ctx.clone());
+		ctx.frame(async move {
+			let (item, sep) = &*parsers;
+			let mut items = Vec::new();
+			let mut pos = pos;
+			loop {
+				let mut next = pos;
+				if let (Some(sep), false) = (sep, items.is_empty()) {
+					match (sep.run)(&ctx_inner, next).await {
+						Ok((_, after)) => next = after,
+						Err(Fail::Backtrack) => break,
+						Err(Fail::Fatal) => return Err(Fail::Fatal),
+					}
+				}
+				match (item.run)(&ctx_inner, next).await {
+					// Stop if nothing was consumed, it would match forever.
+					Ok((value, after)) if after > pos => {
+						items.push(value);
+						pos = after;
+					}
+					Ok((value, _)) => {
+						items.push(value);
+						break;
+					}
+					Err(Fail::Backtrack) => break,
+					Err(Fail::Fatal) => return Err(Fail::Fatal),
+				}
+			}
+			match items.len() >= min {
+				true => Ok((items, pos)),
+				false => Err(Fail::Backtrack),
+			}
+		})
+	})
+}
+
+/// Run the parser as many times as it succeeds, maybe none.
+pub fn many<'i, T: 'i>(parser: Parser<'i, T>) -> Parser<'i, Vec<T>> {
+	repeat::<_, ()>(parser, None, 0)
+}
+
+/// Run the parser as many times as it succeeds, at least once.
+pub fn many1<'i, T: 'i>(parser: Parser<'i, T>) -> Parser<'i, Vec<T>> {
+	repeat::<_, ()>(parser, None, 1)
+}
+
+/// Run `item` as many times as it succeeds, maybe none, with `sep` between them.
+pub fn sep_by<'i, T: 'i, S: 'i>(item: Parser<'i, T>, sep: Parser<'i, S>) -> Parser<'i, Vec<T>> {
+	repeat(item, Some(sep), 0)
+}
+
+/// A parser that refers to itself: `define` gets a parser standing for the result, and builds the result with it.
+///
+/// Every time the result is entered counts as one level towards [`Parser::parse_with_max_depth`].
+pub fn recursive<'i, T: 'i>(define: impl FnOnce(Parser<'i, T>) -> Parser<'i, T>) -> Parser<'i, T> {
+	let definition = Rc::new(RefCell::new(None));
+	// A weak reference, so that the parser doesn't keep itself alive.
+	let weak = Rc::downgrade(&definition);
+	let reference = Parser::new(move |ctx, pos| {
+		let definition = weak
+			.upgrade()
+			.expect("Decurse: a recursive parser was used after it was dropped.");
+		let parser = definition.borrow().clone();
+		enter(ctx, pos, parser)
+	});
+	let parser = define(reference);
+	*definition.borrow_mut() = Some(parser.clone());
+	Parser::new(move |ctx, pos| {
+		// Keep the definition alive for the references.
+		let _ = &definition;
+		enter(ctx, pos, Some(parser.clone()))
+	})
+}
+
+// Run the definition of a recursive parser one level deeper.
+fn enter<'i, T: 'i>(ctx: &Ctx<'i>, pos: usize, parser: Option<Parser<'i, T>>) -> Step<'i, T> {
+	let parser = parser.expect("Decurse: a recursive parser was used while it was being defined.");
+	let state = ctx.state.clone();
+	let depth = state.depth.get() + 1;
+	if matches!(state.max_depth, Some(max) if depth > max) {
+		state.too_deep.set(Some(pos));
+		return ctx.ready(Err(Fail::Fatal));
+	}
+	state.depth.set(depth);
+	let step = (parser.run)(ctx, pos);
+	ctx.frame(async move {
+		let result = step.await;
+		state.depth.set(depth - 1);
+		result
+	})
+}
@@ -0,0 +1,439 @@
+//! Recording the logical call tree.
+//!
+//! [`Recorder::execute`] runs a future like [`execute`](crate::execute) does,
+//! and records every frame of the `#[decurse]` functions (and [`Recursion`](crate::Recursion)s) it runs.
+//! The [`Recording`] can be written out as Graphviz DOT, Chrome `trace_event` JSON (for `chrome://tracing` or Perfetto),
+//! or folded stacks (for `inferno` or `flamegraph.pl`).
+//!
+//! Arguments are recorded for functions with `#[decurse(debug_args)]`, and results for functions with `#[decurse(debug_result)]`.
+//!
+//! ```
+//! use decurse::record::Recorder;
+//!
+//! #[decurse::decurse(debug_args, debug_result)]
+//! fn fibonacci(x: u32) -> u32 {
+//!     if x < 2 {
+//!         1
+//!     } else {
+//!         fibonacci(x - 1) + fibonacci(x - 2)
+//!     }
+//! }
+//!
+//! let (output, recording) = Recorder::new().execute(async { fibonacci(3) });
+//! assert_eq!(output, 3);
+//! // The top-level call and everything under it.
+//! assert_eq!(recording.frames().len(), 5);
+//! let frame = &recording.frames()[1];
+//! // The name has the module path in front.
+//! assert!(frame.frame().name().ends_with("::fibonacci"));
+//! assert_eq!(frame.frame().args(), Some("2"));
+//! assert_eq!(frame.result(), Some("2"));
+//!
+//! let mut dot = Vec::new();
+//! recording.write_dot(&mut dot).unwrap();
+//! ```
+
+use crate::{
+	execute,
+	introspect::{self, Watcher},
+	StackFrame,
+};
+use std::{
+	cell::{Cell, RefCell},
+	collections::BTreeMap,
+	fmt,
+	future::Future,
+	io::{self, Write},
+	mem,
+	rc::Rc,
+	time::{Duration, Instant},
+};
+
+thread_local! {
+	static ACTIVE: Cell<bool> = const { Cell::new(false) };
+	static STATE: RefCell<Option<State>> = const { RefCell::new(None) };
+	static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+struct State {
+	id: u64,
+	frames: Vec<RecordedFrame>,
+	// The index of each open frame, or None if it was over the limit.
+	// While a generator runs, this only has its frames, and the rest are in `outer`.
+	open: Vec<Option<usize>>,
+	// The open frames below each running generator, with the parent of its outermost frame.
+	outer: Vec<(Vec<Option<usize>>, Option<usize>)>,
+	// The parent of the outermost frame in `open`.
+	parent: Option<usize>,
+	node_limit: usize,
+	dropped: usize,
+	start: Option<Instant>,
+	tick: u64,
+}
+
+impl State {
+	fn innermost(&self) -> Option<usize> {
+		match self.open.last() {
+			Some(index) => *index,
+			None => self.parent,
+		}
+	}
+	fn now(&mut self) -> u64 {
+		match self.start {
+			Some(start) => start.elapsed().as_nanos() as u64,
+			None => {
+				self.tick += 1;
+				self.tick
+			}
+		}
+	}
+}
+
+pub(crate) fn is_recording() -> bool {
+	ACTIVE.with(Cell::get)
+}
+
+// Returns whether it was active.
+fn set_active(active: bool) -> bool {
+	introspect::watch(Watcher::Record, active);
+	ACTIVE.with(|a| a.replace(active))
+}
+
+fn with_state(f: impl FnOnce(&mut State)) {
+	if is_recording() {
+		STATE.with(|s| {
+			if let Some(state) = s.borrow_mut().as_mut() {
+				f(state);
+			}
+		});
+	}
+}
+
+pub(crate) fn push(frame: &StackFrame, depth: usize) {
+	with_state(|state| {
+		if state.frames.len() >= state.node_limit {
+			state.dropped += 1;
+			state.open.push(None);
+			return;
+		}
+		let start = state.now();
+		state.frames.push(RecordedFrame {
+			frame: frame.clone(),
+			result: None,
+			parent: state.innermost(),
+			depth,
+			start,
+			end: None,
+		});
+		state.open.push(Some(state.frames.len() - 1));
+	});
+}
+
+// Record the result of the innermost open frame.
+pub(crate) fn result(result: String) {
+	with_state(|state| {
+		if let Some(Some(index)) = state.open.last() {
+			state.frames[*index].result = Some(result.into());
+		}
+	});
+}
+
+pub(crate) fn pop() {
+	with_state(|state| {
+		if let Some(Some(index)) = state.open.pop() {
+			state.frames[index].end = Some(state.now());
+		}
+	});
+}
+
+// Open frames of a suspended generator, and the recording they belong to.
+#[derive(Default)]
+pub(crate) struct Detached {
+	open: Vec<Option<usize>>,
+	recording: Option<u64>,
+}
+
+// Put the open frames of a generator on top, while it runs.
+pub(crate) fn attach(detached: Detached) {
+	with_state(|state| {
+		// Frames from another recording are not in this one.
+		let open = if detached.recording == Some(state.id) {
+			detached.open
+		} else {
+			Vec::new()
+		};
+		let parent = state.innermost();
+		let outer = mem::replace(&mut state.open, open);
+		state
+			.outer
+			.push((outer, mem::replace(&mut state.parent, parent)));
+	});
+}
+
+// Take the open frames of a generator off, when it suspends.
+pub(crate) fn detach() -> Detached {
+	let mut detached = Detached::default();
+	with_state(|state| {
+		if let Some((outer, parent)) = state.outer.pop() {
+			detached.open = mem::replace(&mut state.open, outer);
+			detached.recording = Some(state.id);
+			state.parent = parent;
+		}
+	});
+	detached
+}
+
+/// Settings for recording the call tree.
+#[derive(Clone, Debug)]
+pub struct Recorder {
+	node_limit: usize,
+	timestamps: bool,
+}
+
+impl Default for Recorder {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Recorder {
+	/// Record up to 100000 frames, with timestamps.
+	pub fn new() -> Self {
+		Self {
+			node_limit: 100000,
+			timestamps: true,
+		}
+	}
+	/// Record up to this many frames. Frames after that are only counted.
+	pub fn node_limit(mut self, node_limit: usize) -> Self {
+		self.node_limit = node_limit;
+		self
+	}
+	/// Whether to record when each frame starts and ends.
+	///
+	/// Without timestamps, the Chrome trace uses the order of the events as time,
+	/// and the folded stacks count calls instead of time.
+	pub fn timestamps(mut self, timestamps: bool) -> Self {
+		self.timestamps = timestamps;
+		self
+	}
+	/// Run the future on the heap stack, like [`execute`](crate::execute), recording the call tree.
+	///
+	/// Frames that were already running when this is called are not recorded.
+	pub fn execute<F>(&self, fut: F) -> (F::Output, Recording)
+	where
+		F: Future + 'static,
+	{
+		let state = State {
+			id: NEXT_ID.with(|n| n.replace(n.get() + 1)),
+			frames: Vec::new(),
+			open: Vec::new(),
+			outer: Vec::new(),
+			parent: None,
+			node_limit: self.node_limit,
+			dropped: 0,
+			start: if self.timestamps {
+				Some(Instant::now())
+			} else {
+				None
+			},
+			tick: 0,
+		};
+		// Put back whatever was being recorded before, even if we panic.
+		struct Restore(Option<State>, bool);
+		impl Drop for Restore {
+			fn drop(&mut self) {
+				STATE.with(|s| *s.borrow_mut() = self.0.take());
+				set_active(self.1);
+			}
+		}
+		let restore = Restore(
+			STATE.with(|s| s.borrow_mut().replace(state)),
+			set_active(true),
+		);
+		let output = execute(fut);
+		// UNWRAP Safety: We put a state in, and `Restore` is the only thing that takes it out.
+		let state = STATE.with(|s| s.borrow_mut().take()).unwrap();
+		drop(restore);
+		let recording = Recording {
+			frames: state.frames,
+			dropped: state.dropped,
+			timestamps: self.timestamps,
+		};
+		(output, recording)
+	}
+}
+
+/// A frame recorded by a [`Recorder`].
+#[derive(Clone, Debug)]
+pub struct RecordedFrame {
+	frame: StackFrame,
+	result: Option<Rc<str>>,
+	parent: Option<usize>,
+	depth: usize,
+	start: u64,
+	end: Option<u64>,
+}
+
+impl RecordedFrame {
+	/// The function name and arguments.
+	pub fn frame(&self) -> &StackFrame {
+		&self.frame
+	}
+	/// The `Debug` representation of the result,
+	/// if the function opted in with `#[decurse(debug_result)]` or [`Recursion::debug_result`](crate::Recursion::debug_result),
+	/// and the frame completed.
+	pub fn result(&self) -> Option<&str> {
+		self.result.as_deref()
+	}
+	/// The index of the frame that called this one, in [`Recording::frames`].
+	pub fn parent(&self) -> Option<usize> {
+		self.parent
+	}
+	/// The depth of the frame, as in [`depth`](crate::depth).
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+	/// How long the frame ran for, from its first poll to when it completed or was dropped.
+	/// `None` if timestamps were disabled.
+	pub fn duration(&self, recording: &Recording) -> Option<Duration> {
+		match (recording.timestamps, self.end) {
+			(true, Some(end)) => Some(Duration::from_nanos(end - self.start)),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for RecordedFrame {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.result {
+			Some(result) => write!(f, "{} = {}", self.frame, result),
+			None => write!(f, "{}", self.frame),
+		}
+	}
+}
+
+/// The call tree recorded by [`Recorder::execute`].
+#[derive(Clone, Debug)]
+pub struct Recording {
+	frames: Vec<RecordedFrame>,
+	dropped: usize,
+	timestamps: bool,
+}
+
+impl Recording {
+	/// The recorded frames, in the order they started.
+	pub fn frames(&self) -> &[RecordedFrame] {
+		&self.frames
+	}
+	/// The number of frames that were not recorded because of the node limit.
+	pub fn dropped(&self) -> usize {
+		self.dropped
+	}
+	/// Write the call tree as a Graphviz DOT graph.
+	pub fn write_dot(&self, mut out: impl Write) -> io::Result<()> {
+		writeln!(out, "digraph decurse {{")?;
+		writeln!(out, "\tnode [shape=box];")?;
+		for (index, frame) in self.frames.iter().enumerate() {
+			let label = frame.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+			writeln!(out, "\tn{} [label=\"{}\"];", index, label)?;
+			if let Some(parent) = frame.parent {
+				writeln!(out, "\tn{} -> n{};", parent, index)?;
+			}
+		}
+		if self.dropped > 0 {
+			writeln!(
+				out,
+				"\tdropped [label=\"{} more frames\", shape=plaintext];",
+				self.dropped
+			)?;
+		}
+		writeln!(out, "}}")
+	}
+	/// Write the frames as Chrome `trace_event` JSON.
+	pub fn write_chrome_trace(&self, mut out: impl Write) -> io::Result<()> {
+		// Frames that never ended (because of a panic) end with the last one.
+		let last = self
+			.frames
+			.iter()
+			.filter_map(|f| f.end)
+			.max()
+			.unwrap_or_default();
+		// The format uses microseconds.
+		let scale = if self.timestamps { 1000.0 } else { 1.0 };
+		write!(out, "{{\"traceEvents\":[")?;
+		for (index, frame) in self.frames.iter().enumerate() {
+			if index > 0 {
+				write!(out, ",")?;
+			}
+			write!(
+				out,
+				"\n{{\"name\":{},\"ph\":\"X\",\"pid\":0,\"tid\":0,\"ts\":{},\"dur\":{},\"args\":{{\"depth\":{}",
+				json_string(frame.frame.name()),
+				frame.start as f64 / scale,
+				(frame.end.unwrap_or(last) - frame.start) as f64 / scale,
+				frame.depth
+			)?;
+			if let Some(args) = frame.frame.args() {
+				write!(out, ",\"args\":{}", json_string(args))?;
+			}
+			if let Some(result) = &frame.result {
+				write!(out, ",\"result\":{}", json_string(result))?;
+			}
+			write!(out, "}}}}")?;
+		}
+		writeln!(out, "\n]}}")
+	}
+	/// Write the frames as folded stacks, one `outer;...;inner value` line per distinct stack.
+	///
+	/// The value is the time spent in the frame itself (excluding what it called) in nanoseconds,
+	/// or the number of calls if timestamps were disabled.
+	pub fn write_folded(&self, mut out: impl Write) -> io::Result<()> {
+		let mut paths: Vec<String> = Vec::with_capacity(self.frames.len());
+		let mut values = vec![0u64; self.frames.len()];
+		for (index, frame) in self.frames.iter().enumerate() {
+			let path = match frame.parent {
+				Some(parent) => format!("{};{}", paths[parent], frame.frame.name()),
+				None => frame.frame.name().to_owned(),
+			};
+			paths.push(path);
+			values[index] = match (self.timestamps, frame.end) {
+				(false, _) => 1,
+				(true, Some(end)) => end - frame.start,
+				(true, None) => 0,
+			};
+		}
+		if self.timestamps {
+			// Take the time of the children out of their parent.
+			for frame in &self.frames {
+				if let (Some(parent), Some(end)) = (frame.parent, frame.end) {
+					values[parent] = values[parent].saturating_sub(end - frame.start);
+				}
+			}
+		}
+		let mut folded = BTreeMap::new();
+		for (path, value) in paths.into_iter().zip(values) {
+			*folded.entry(path).or_insert(0) += value;
+		}
+		for (path, value) in folded {
+			writeln!(out, "{} {}", path, value)?;
+		}
+		Ok(())
+	}
+}
+
+fn json_string(s: &str) -> String {
+	let mut escaped = String::with_capacity(s.len() + 2);
+	escaped.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+			c => escaped.push(c),
+		}
+	}
+	escaped.push('"');
+	escaped
+}
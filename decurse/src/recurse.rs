@@ -0,0 +1,345 @@
+use crate::{
+	cycle::{self, Cycles, LiveGuard, Tracker},
+	introspect::Entered,
+	memo::{CacheRef, Memo, MemoIn},
+	record,
+	runtime::{self, Frame, Slot},
+	AbortHandle, Call, Cancelled,
+};
+use std::{
+	any::{type_name, TypeId},
+	cell::Cell,
+	fmt::Debug,
+	future::Future,
+	hash::Hash,
+	rc::Rc,
+};
+
+type FormatArgs<A> = dyn Fn(&A) -> String;
+
+// The body of a recursion, with its future type erased.
+trait Body<A, O> {
+	// A frame of its own.
+	fn frame(&self, rec: Recurse<A, O>, args: A, state: FrameState<A, O>) -> (Frame, Slot<O>);
+	// A frame that the recursive calls it makes are put on, see `runtime::new_segment`.
+	fn segment(&self, rec: Recurse<A, O>, args: A, state: FrameState<A, O>) -> (Frame, Slot<O>);
+	// A frame on the segment of the caller if it's one of ours, or a new segment otherwise.
+	fn call(&self, rec: Recurse<A, O>, args: A, state: FrameState<A, O>) -> Call<O>;
+}
+
+struct BodyFn<B>(B);
+
+impl<A, O, B, Fut> Body<A, O> for BodyFn<B>
+where
+	A: 'static,
+	O: 'static,
+	B: Fn(Recurse<A, O>, A) -> Fut,
+	Fut: Future<Output = O> + 'static,
+{
+	fn frame(&self, rec: Recurse<A, O>, args: A, state: FrameState<A, O>) -> (Frame, Slot<O>) {
+		runtime::new_frame(running((self.0)(rec, args), state))
+	}
+	fn segment(&self, rec: Recurse<A, O>, args: A, state: FrameState<A, O>) -> (Frame, Slot<O>) {
+		runtime::new_segment(running((self.0)(rec, args), state))
+	}
+	fn call(&self, rec: Recurse<A, O>, args: A, state: FrameState<A, O>) -> Call<O> {
+		Call::new(running((self.0)(rec, args), state))
+	}
+}
+
+// Bookkeeping for a frame, before it starts.
+struct FrameState<A, O> {
+	live_guard: Option<LiveGuard<A>>,
+	name: &'static str,
+	args: Option<Rc<str>>,
+	debug_result: Option<Rc<FormatArgs<O>>>,
+}
+
+// Run the future of a frame, keeping its bookkeeping until it completes or is dropped.
+async fn running<A, O>(fut: impl Future<Output = O>, state: FrameState<A, O>) -> O {
+	let _live_guard = state.live_guard;
+	let _entered = Entered::push(state.name, state.args);
+	let output = fut.await;
+	if let Some(format) = &state.debug_result {
+		if record::is_recording() {
+			record::result(format(&output));
+		}
+	}
+	output
+}
+
+// How `Recursion::start` puts the new frame on the heap stack.
+enum Place {
+	// A frame for `Runtime::drive`.
+	Drive,
+	// A call from a running frame.
+	Call,
+}
+
+// A new frame, put as asked by `Place`.
+enum Started<O> {
+	Frame(Frame, Slot<O>),
+	Call(Call<O>),
+}
+
+/// A recursive function, written as an `async` body that recurses through a [`Recurse`] handle.
+///
+/// The `#[decurse]` macro builds one for a function with options such as `memoize` or `calls(...)`,
+/// while a function without options runs directly on the heap stack. It can also be used by hand-written code.
+/// `A` is the argument (use a tuple for several arguments) and `O` is the output.
+///
+/// ```
+/// use decurse::{Recurse, Recursion};
+///
+/// async fn triangular(rec: Recurse<u64, u64>, x: u64) -> u64 {
+///     if x == 0 {
+///         0
+///     } else {
+///         rec.call(x - 1).await + x
+///     }
+/// }
+///
+/// let triangular = Recursion::new(triangular);
+/// assert_eq!(triangular.run(200000), 20000100000);
+/// ```
+pub struct Recursion<A, O> {
+	body: Rc<dyn Body<A, O>>,
+	memo: Option<Rc<dyn Memo<A, O>>>,
+	cycles: Option<Rc<dyn Cycles<A>>>,
+	// The type of the body, which the macro shares the cycle detection by.
+	body_type: TypeId,
+	name: &'static str,
+	debug_args: Option<Rc<FormatArgs<A>>>,
+	debug_result: Option<Rc<FormatArgs<O>>>,
+}
+
+impl<A, O> Clone for Recursion<A, O> {
+	fn clone(&self) -> Self {
+		Self {
+			body: self.body.clone(),
+			memo: self.memo.clone(),
+			cycles: self.cycles.clone(),
+			body_type: self.body_type,
+			name: self.name,
+			debug_args: self.debug_args.clone(),
+			debug_result: self.debug_result.clone(),
+		}
+	}
+}
+
+impl<A: 'static, O: 'static> Recursion<A, O> {
+	/// Create a recursion from its body.
+	/// The body is given a [`Recurse`] handle for making recursive calls, and the argument.
+	pub fn new<B, Fut>(body: B) -> Self
+	where
+		B: Fn(Recurse<A, O>, A) -> Fut + 'static,
+		Fut: Future<Output = O> + 'static,
+	{
+		Self {
+			body: Rc::new(BodyFn(body)),
+			memo: None,
+			cycles: None,
+			body_type: TypeId::of::<B>(),
+			name: type_name::<B>(),
+			debug_args: None,
+			debug_result: None,
+		}
+	}
+	/// Run the recursion on the heap stack, returning its output.
+	///
+	/// If this thread is already running a decurse executor, the recursion shares its heap stack.
+	pub fn run(&self, args: A) -> O {
+		// UNWRAP Safety: Without an abort handle the computation is never cancelled.
+		self.run_with(args, None).unwrap()
+	}
+	/// Call the recursion from inside [`execute`](crate::execute) (or a [`Generator`](crate::Generator)),
+	/// as a new frame on the same heap stack.
+	///
+	/// ### Panics
+	/// Panics if called outside of the executor.
+	pub fn call(&self, args: A) -> Call<O> {
+		runtime::with_current(|_| ());
+		Rc::new(self.clone()).call_in(args)
+	}
+	/// Like [`Recursion::run`], but returns `Err(Cancelled)` if the [`AbortHandle`] is aborted before the recursion finishes.
+	pub fn run_abortable(&self, args: A, abort_handle: AbortHandle) -> Result<O, Cancelled> {
+		self.run_with(args, Some(&abort_handle))
+	}
+	/// Memoize the recursion: look up the argument in the cache before putting a new frame on the heap stack,
+	/// and store the output in the cache once the frame finishes.
+	///
+	/// See the [`memo`](crate::memo) module.
+	pub fn memoize<C>(mut self, cache: C) -> Self
+	where
+		A: Hash + Eq + Clone,
+		O: Clone,
+		C: CacheRef<A, O>,
+	{
+		self.memo = Some(Rc::new(MemoIn(cache)));
+		self
+	}
+	/// Detect infinite recursion: record the arguments of the live frames,
+	/// and panic if a call is made with the same arguments as one of its ancestors.
+	///
+	/// The panic message shows the chain of arguments from the ancestor to the repeated call.
+	pub fn detect_cycles(mut self) -> Self
+	where
+		A: Hash + Eq + Clone + Debug,
+	{
+		self.cycles = Some(Rc::new(Tracker::new()));
+		self
+	}
+	// Like `detect_cycles`, but every recursion made from the same body on this thread shares the live arguments.
+	// The macro makes the recursion again for each call from outside it, including calls from other functions.
+	#[doc(hidden)]
+	pub fn detect_cycles_by_body(mut self) -> Self
+	where
+		A: Hash + Eq + Clone + Debug,
+	{
+		self.cycles = Some(cycle::shared(self.body_type));
+		self
+	}
+	/// Set the name shown for the frames of this recursion in [`stack`](crate::stack).
+	/// By default, it is the type name of the body.
+	pub fn named(mut self, name: &'static str) -> Self {
+		self.name = name;
+		self
+	}
+	/// Record the `Debug` representation of the argument of each frame, to be shown in [`stack`](crate::stack).
+	pub fn debug_args(self) -> Self
+	where
+		A: Debug,
+	{
+		self.debug_args_with(|args| format!("{:?}", args))
+	}
+	/// Like [`Recursion::debug_args`], but with a custom formatting function.
+	pub fn debug_args_with<F>(mut self, format: F) -> Self
+	where
+		F: Fn(&A) -> String + 'static,
+	{
+		self.debug_args = Some(Rc::new(format));
+		self
+	}
+	/// Record the `Debug` representation of the output of each frame, to be shown in a [`Recording`](crate::record::Recording).
+	pub fn debug_result(self) -> Self
+	where
+		O: Debug,
+	{
+		self.debug_result_with(|output| format!("{:?}", output))
+	}
+	/// Like [`Recursion::debug_result`], but with a custom formatting function.
+	pub fn debug_result_with<F>(mut self, format: F) -> Self
+	where
+		F: Fn(&O) -> String + 'static,
+	{
+		self.debug_result = Some(Rc::new(format));
+		self
+	}
+	fn run_with(&self, args: A, abort_handle: Option<&AbortHandle>) -> Result<O, Cancelled> {
+		let (frame, slot) = match Rc::new(self.clone()).start(args, Place::Drive) {
+			Ok(output) => return Ok(output),
+			Err(Started::Frame(frame, slot)) => (frame, slot),
+			Err(Started::Call(_)) => {
+				unreachable!("Decurse: a recursion to drive was started as a call.")
+			}
+		};
+		runtime::with_runtime(|runtime| runtime.drive(frame, abort_handle))?;
+		// UNWRAP Safety: `drive` only returns Ok once the frame completed, which fills the slot.
+		Ok(slot.take().unwrap())
+	}
+	fn call_in(self: &Rc<Self>, args: A) -> Call<O> {
+		match self.start(args, Place::Call) {
+			Ok(output) => Call::ready(output),
+			Err(Started::Frame(frame, slot)) => Call::from_frame(frame, slot),
+			Err(Started::Call(call)) => call,
+		}
+	}
+	// Either the memoized output, or a new frame that computes it.
+	fn start(self: &Rc<Self>, args: A, place: Place) -> Result<O, Started<O>> {
+		let memo = self.memo.as_ref();
+		if let Some(output) = memo.and_then(|memo| memo.get(&args)) {
+			return Ok(output);
+		}
+		let rec = Recurse {
+			recursion: self.clone(),
+		};
+		let formatted: Option<Rc<str>> =
+			self.debug_args.as_ref().map(|format| format(&args).into());
+		// The caller is running, so the new frame goes right above it.
+		#[cfg(feature = "tracing")]
+		let span = crate::trace::span(self.name, formatted.as_deref(), crate::depth() + 1);
+		#[cfg(feature = "tracing")]
+		let traced = span.is_some();
+		#[cfg(not(feature = "tracing"))]
+		let traced = false;
+		let state = FrameState {
+			live_guard: self
+				.cycles
+				.as_ref()
+				.map(|cycles| LiveGuard::enter(cycles, &args)),
+			name: self.name,
+			args: formatted,
+			debug_result: self.debug_result.clone(),
+		};
+		let started = match memo {
+			Some(memo) => {
+				let state = Cell::new(Some(state));
+				// UNWRAP Safety: The body is only called once.
+				let body = |args| self.body.frame(rec.clone(), args, state.take().unwrap());
+				let (frame, slot) = memo.remember(args, &body);
+				Started::Frame(frame, slot)
+			}
+			// A traced frame needs a span of its own.
+			None if traced => {
+				let (frame, slot) = self.body.frame(rec, args, state);
+				Started::Frame(frame, slot)
+			}
+			None => match place {
+				Place::Drive => {
+					let (frame, slot) = self.body.segment(rec, args, state);
+					Started::Frame(frame, slot)
+				}
+				Place::Call => Started::Call(self.body.call(rec, args, state)),
+			},
+		};
+		#[cfg(feature = "tracing")]
+		let started = match (started, span) {
+			(Started::Frame(frame, slot), Some(span)) => {
+				Started::Frame(crate::trace::instrument(frame, span), slot)
+			}
+			(started, _) => started,
+		};
+		Err(started)
+	}
+}
+
+/// Handle for making recursive calls from inside the body of a [`Recursion`].
+///
+/// Calls made through the handle are put on the heap stack of the executor running the recursion.
+/// The handle should not be moved out of its recursion.
+pub struct Recurse<A, O> {
+	recursion: Rc<Recursion<A, O>>,
+}
+
+impl<A, O> Clone for Recurse<A, O> {
+	fn clone(&self) -> Self {
+		Self {
+			recursion: self.recursion.clone(),
+		}
+	}
+}
+
+impl<A: 'static, O: 'static> Recurse<A, O> {
+	/// Recursively call the body with the given argument.
+	pub fn call(&self, args: A) -> Call<O> {
+		self.recursion.call_in(args)
+	}
+	/// Call another recursion, putting it on the same heap stack.
+	pub fn call_with<A2: 'static, O2: 'static>(
+		&self,
+		other: &Recursion<A2, O2>,
+		args: A2,
+	) -> Call<O2> {
+		Rc::new(other.clone()).call_in(args)
+	}
+}
@@ -0,0 +1,579 @@
+use crate::{
+	hooks,
+	incremental::{self, NodeId},
+	AbortHandle, Cancelled,
+};
+use pinned_vec::PinnedVec;
+use scoped_tls::scoped_thread_local;
+use std::{
+	any::Any,
+	cell::{Cell, RefCell},
+	future::Future,
+	pin::Pin,
+	rc::Rc,
+	task::{Context, Poll},
+};
+
+// A type-erased frame. It writes its output to a slot shared with whoever is waiting for it.
+pub(crate) type Frame = Pin<Box<dyn Future<Output = ()>>>;
+
+pub(crate) type Slot<T> = Rc<Cell<Option<T>>>;
+
+pub(crate) fn new_frame<F>(fut: F) -> (Frame, Slot<F::Output>)
+where
+	F: Future + 'static,
+{
+	let slot = Rc::new(Cell::new(None));
+	let slot_clone = slot.clone();
+	let frame = Box::pin(async move {
+		slot_clone.set(Some(fut.await));
+	});
+	(frame, slot)
+}
+
+// A frame holding `fut`, and the frames of the same type it calls, see `Segment`.
+pub(crate) fn new_segment<F>(fut: F) -> (Frame, Slot<F::Output>)
+where
+	F: Future + 'static,
+{
+	let slot = Rc::new(Cell::new(None));
+	let queue = Rc::new(Queue {
+		next: Cell::new(None),
+		state: Cell::new(QueueState::Empty),
+		output: Cell::new(None),
+	});
+	let mut frames = PinnedVec::new();
+	frames.push(fut);
+	let segment = Segment {
+		frames,
+		context: Box::new(queue.clone()),
+		queue,
+		slot: slot.clone(),
+	};
+	(Box::pin(segment), slot)
+}
+
+scoped_thread_local! (static SEGMENT: Box<dyn Any>);
+
+// Frames of the same type, each called by the one below it.
+// They are kept in a `PinnedVec` instead of being boxed one by one, and the whole segment is a single frame of the runtime.
+// Calls to futures of another type go on the runtime above the segment.
+struct Segment<F: Future> {
+	frames: PinnedVec<F>,
+	queue: Rc<Queue<F>>,
+	// The `Rc<Queue<F>>` again, for frames to find through `SEGMENT`.
+	context: Box<dyn Any>,
+	slot: Slot<F::Output>,
+}
+
+// Where a frame of a segment puts the frame it calls, and gets its output back.
+struct Queue<F: Future> {
+	next: Cell<Option<F>>,
+	state: Cell<QueueState>,
+	output: Cell<Option<F::Output>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum QueueState {
+	Empty,
+	// A `Call` put a frame in `next`, but hasn't been awaited yet.
+	// If it's dropped instead, the frame in `next` is left there to be replaced.
+	Queued,
+	// The frame in `next` is to be pushed once the caller pends.
+	Awaited,
+}
+
+// The side of a `Queue` seen by a `Call`.
+trait Queued<T> {
+	// Whether the segment being polled is the one the queue belongs to.
+	fn in_current_segment(&self) -> bool;
+	fn awaited(&self);
+	// Take the frame back out of `next`, as a segment of its own.
+	fn detach(&self) -> (Frame, Slot<T>);
+	fn dropped(&self);
+	fn take(&self) -> Option<T>;
+}
+
+impl<F: Future + 'static> Queued<F::Output> for Queue<F> {
+	fn in_current_segment(&self) -> bool {
+		SEGMENT.is_set()
+			&& SEGMENT.with(|context| {
+				matches!(context.downcast_ref::<Rc<Queue<F>>>(), Some(queue) if std::ptr::eq(&**queue, self))
+			})
+	}
+	fn awaited(&self) {
+		self.state.set(QueueState::Awaited);
+	}
+	fn detach(&self) -> (Frame, Slot<F::Output>) {
+		self.state.set(QueueState::Empty);
+		// UNWRAP Safety: The frame stays in `next` while the call is queued.
+		new_segment(self.next.take().unwrap())
+	}
+	fn dropped(&self) {
+		self.state.set(QueueState::Empty);
+	}
+	fn take(&self) -> Option<F::Output> {
+		self.output.take()
+	}
+}
+
+// The queue of the segment being polled, if its frames have the type `F` and it's free.
+fn current_queue<F>() -> Option<Rc<Queue<F>>>
+where
+	F: Future + 'static,
+{
+	if !SEGMENT.is_set() {
+		return None;
+	}
+	SEGMENT.with(|context| {
+		context
+			.downcast_ref::<Rc<Queue<F>>>()
+			.filter(|queue| queue.state.get() == QueueState::Empty)
+			.cloned()
+	})
+}
+
+// The frames are pinned in the blocks of the `PinnedVec`, which don't move with the segment.
+impl<F: Future> Unpin for Segment<F> {}
+
+impl<F: Future + 'static> Future for Segment<F> {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+		let this = self.get_mut();
+		let abort_handle = RUNTIME.with(|runtime| runtime.abort_handle.borrow().clone());
+		let Self {
+			frames,
+			queue,
+			context,
+			slot,
+		} = this;
+		SEGMENT.set(context, || loop {
+			if matches!(&abort_handle, Some(h) if h.is_aborted()) {
+				// The runtime sees the abort too, and drops the segment.
+				return Poll::Pending;
+			}
+			// UNWRAP Safety: The segment completes as soon as it has no frame left.
+			let top = frames.get_mut(frames.len() - 1).unwrap();
+			match top.poll(cx) {
+				Poll::Ready(output) => {
+					frames.pop();
+					if frames.is_empty() {
+						slot.set(Some(output));
+						return Poll::Ready(());
+					}
+					queue.output.set(Some(output));
+				}
+				Poll::Pending => {
+					if queue.state.get() != QueueState::Awaited {
+						// Waiting for a frame on the runtime, or yielding.
+						return Poll::Pending;
+					}
+					queue.state.set(QueueState::Empty);
+					// UNWRAP Safety: A call is only awaited after putting its frame in `next`.
+					frames.push(queue.next.take().unwrap());
+				}
+			}
+		})
+	}
+}
+
+impl<F: Future> Drop for Segment<F> {
+	fn drop(&mut self) {
+		// Drop the innermost frames first, as if the calls were unwinding.
+		while !self.frames.is_empty() {
+			self.frames.pop();
+		}
+	}
+}
+
+// The heap stack of one thread.
+// Every `execute` on the same thread shares it, so frames of different functions live in the same stack.
+pub(crate) struct Runtime {
+	// Each frame is kept with the memoized frame it works for, see the `incremental` module.
+	stack: RefCell<Vec<(Frame, Option<NodeId>)>>,
+	next: Cell<Option<Frame>>,
+	// For the runtime of a `Generator`, the `Cell<Option<T>>` its items are yielded into.
+	items: Option<Rc<dyn Any>>,
+	yielded: Cell<bool>,
+	// The abort handle of the innermost `drive`, for segments to check between their frames.
+	abort_handle: RefCell<Option<AbortHandle>>,
+}
+
+scoped_thread_local! (static RUNTIME: Rc<Runtime>);
+
+// Call `f` with the runtime running on this thread, or a new one if there is none.
+pub(crate) fn with_runtime<R>(f: impl FnOnce(&Rc<Runtime>) -> R) -> R {
+	if RUNTIME.is_set() {
+		RUNTIME.with(f)
+	} else {
+		let runtime = Rc::new(Runtime::new(None));
+		let output = RUNTIME.set(&runtime, || f(&runtime));
+		hooks::complete();
+		output
+	}
+}
+
+// Identifies the runtime while it's alive.
+pub(crate) fn id(runtime: &Rc<Runtime>) -> usize {
+	Rc::as_ptr(runtime) as usize
+}
+
+// The id of the runtime running on this thread, if any.
+pub(crate) fn current_id() -> Option<usize> {
+	if RUNTIME.is_set() {
+		Some(RUNTIME.with(id))
+	} else {
+		None
+	}
+}
+
+// Call `f` with the runtime running on this thread.
+pub(crate) fn with_current<R>(f: impl FnOnce(&Rc<Runtime>) -> R) -> R {
+	assert!(
+		RUNTIME.is_set(),
+		"Decurse: decurse call used outside of the decurse executor."
+	);
+	RUNTIME.with(f)
+}
+
+// Hand an item to the generator running on this thread.
+// The frame that yields must then return `Pending` without calling anything.
+pub(crate) fn yield_item<T: 'static>(item: T) {
+	let items = if RUNTIME.is_set() {
+		RUNTIME.with(|runtime| {
+			runtime.yielded.set(true);
+			runtime.items.clone()
+		})
+	} else {
+		None
+	};
+	let items = items.expect("Decurse: yield_item! used outside of a generator.");
+	let items = items
+		.downcast_ref::<Cell<Option<T>>>()
+		.expect("Decurse: yield_item! used with a different type than the items of the generator.");
+	items.set(Some(item));
+}
+
+impl Runtime {
+	fn new(items: Option<Rc<dyn Any>>) -> Self {
+		Self {
+			stack: RefCell::new(Vec::new()),
+			next: Cell::new(None),
+			items,
+			yielded: Cell::new(false),
+			abort_handle: RefCell::new(None),
+		}
+	}
+	// The runtime of a generator, starting with `frame`. Items are yielded into `items`.
+	pub(crate) fn generator<T: 'static>(frame: Frame, items: Rc<Cell<Option<T>>>) -> Rc<Self> {
+		let runtime = Self::new(Some(items));
+		runtime.stack.borrow_mut().push((frame, None));
+		Rc::new(runtime)
+	}
+	// Run the frames of a generator until one of them yields (returning false) or they all complete (returning true).
+	// If this panics, the frames are left in the stack, to be dropped with `clear`.
+	pub(crate) fn resume(self: &Rc<Self>) -> bool {
+		// Put back the memoized frame of the caller, even if we panic.
+		struct RestoreNode(Option<NodeId>);
+		impl Drop for RestoreNode {
+			fn drop(&mut self) {
+				incremental::set_current(self.0);
+			}
+		}
+		let _restore = RestoreNode(incremental::current());
+		let completed = RUNTIME.set(self, || self.run(0, None, true));
+		// UNWRAP Safety: Without an abort handle the computation is never cancelled.
+		completed.unwrap()
+	}
+	// Whether there are no frames left.
+	pub(crate) fn is_empty(&self) -> bool {
+		self.stack.borrow().is_empty()
+	}
+	// Drop all the frames, innermost first.
+	pub(crate) fn clear(&self) {
+		drop(Truncate {
+			runtime: self,
+			floor: 0,
+			outer_node: incremental::current(),
+			outer_abort_handle: self.abort_handle.take(),
+		});
+	}
+	// Run `frame` and everything it calls, until `frame` completes.
+	pub(crate) fn drive(
+		&self,
+		frame: Frame,
+		abort_handle: Option<&AbortHandle>,
+	) -> Result<(), Cancelled> {
+		let floor = self.stack.borrow().len();
+		let outer_node = incremental::current();
+		let outer_abort_handle = self.abort_handle.replace(abort_handle.cloned());
+		// If we exit early (panic or cancel), get rid of the frames we pushed.
+		let _guard = Truncate {
+			runtime: self,
+			floor,
+			outer_node,
+			outer_abort_handle,
+		};
+		self.stack.borrow_mut().push((frame, outer_node));
+		self.run(floor, abort_handle, false).map(|_| ())
+	}
+	// Poll the frames above `floor` until they all complete (returning true),
+	// or, if `can_yield`, one of them yields (returning false).
+	fn run(
+		&self,
+		floor: usize,
+		abort_handle: Option<&AbortHandle>,
+		can_yield: bool,
+	) -> Result<bool, Cancelled> {
+		let dummy_waker = waker_fn::waker_fn(|| {});
+		let mut dummy_async_cx = Context::from_waker(&dummy_waker);
+		// Frames polled by the runtime are not in a segment, even if this runs inside one.
+		let no_segment: Box<dyn Any> = Box::new(());
+		loop {
+			if matches!(abort_handle, Some(h) if h.is_aborted()) {
+				return Err(Cancelled);
+			}
+			// The frame is taken out of the stack while being polled,
+			// so that a nested `execute` inside it can use the stack.
+			// UNWRAP Safety: We return as soon as the stack gets back to `floor`.
+			let (mut frame, node) = self.stack.borrow_mut().pop().unwrap();
+			incremental::set_current(node);
+			let polled = SEGMENT.set(&no_segment, || frame.as_mut().poll(&mut dummy_async_cx));
+			match polled {
+				Poll::Ready(()) => {
+					if self.stack.borrow().len() == floor {
+						return Ok(true);
+					}
+				}
+				Poll::Pending => {
+					// A memoized frame sets itself as current when first polled.
+					let node = incremental::current();
+					let next = self.next.take();
+					let mut stack = self.stack.borrow_mut();
+					stack.push((frame, node));
+					match next {
+						Some(next) => stack.push((next, node)),
+						None if self.yielded.take() => {
+							// The frame continues from the `yield_item!` when resumed.
+							assert!(
+								can_yield,
+								"Decurse: yield_item! used in a nested execution inside a generator."
+							);
+							return Ok(false);
+						}
+						None if matches!(abort_handle, Some(h) if h.is_aborted()) => {
+							// A segment stopped in the middle of its frames.
+							return Err(Cancelled);
+						}
+						None => panic!(
+							"Decurse: a decursed function awaited something other than a decurse call."
+						),
+					}
+				}
+			}
+		}
+	}
+}
+
+struct Truncate<'a> {
+	runtime: &'a Runtime,
+	floor: usize,
+	outer_node: Option<NodeId>,
+	outer_abort_handle: Option<AbortHandle>,
+}
+
+impl Drop for Truncate<'_> {
+	fn drop(&mut self) {
+		// Drop the innermost frames first, as if the calls were unwinding.
+		// Each frame is dropped outside the borrow, since dropping it might run arbitrary code.
+		loop {
+			let frame = {
+				let mut stack = self.runtime.stack.borrow_mut();
+				if stack.len() <= self.floor {
+					break;
+				}
+				stack.pop()
+			};
+			drop(frame);
+		}
+		incremental::set_current(self.outer_node);
+		self.runtime
+			.abort_handle
+			.replace(self.outer_abort_handle.take());
+	}
+}
+
+pub(crate) fn execute_with<F>(
+	fut: F,
+	abort_handle: Option<&AbortHandle>,
+) -> Result<F::Output, Cancelled>
+where
+	F: Future + 'static,
+{
+	let (frame, slot) = new_segment(fut);
+	with_runtime(|runtime| runtime.drive(frame, abort_handle))?;
+	// UNWRAP Safety: `drive` only returns Ok once the frame completed, which fills the slot.
+	Ok(slot.take().unwrap())
+}
+
+/// Run a future on the heap stack, returning its output.
+///
+/// This is what `#[decurse]` functions use under the hood.
+/// Inside the future (and everything it [`call`]s), every `.await` must be on a [`call`].
+/// Awaiting anything else that is not immediately ready panics.
+///
+/// If this thread is already running `execute` (e.g. a decursed function calls another one),
+/// the future shares the heap stack of the outer `execute`.
+///
+/// ```
+/// use decurse::{call, execute};
+///
+/// async fn triangular(x: u64) -> u64 {
+///     if x == 0 {
+///         0
+///     } else {
+///         call(triangular(x - 1)).await + x
+///     }
+/// }
+/// assert_eq!(execute(triangular(200000)), 20000100000);
+/// ```
+pub fn execute<F>(fut: F) -> F::Output
+where
+	F: Future + 'static,
+{
+	// UNWRAP Safety: Without an abort handle the computation is never cancelled.
+	execute_with(fut, None).unwrap()
+}
+
+/// Run a future as a new frame on the heap stack, from inside [`execute`].
+///
+/// The future can be anything, including a `Pin<Box<dyn Future>>` coming from a callback or a trait object,
+/// so recursion that the `#[decurse]` macro cannot see is also stack-safe.
+///
+/// ```
+/// use decurse::{call, execute};
+/// use std::{future::Future, pin::Pin, rc::Rc};
+///
+/// trait Node {
+///     fn depth(self: Rc<Self>) -> Pin<Box<dyn Future<Output = usize>>>;
+/// }
+/// struct Leaf;
+/// struct Wrap(Rc<dyn Node>);
+/// impl Node for Leaf {
+///     fn depth(self: Rc<Self>) -> Pin<Box<dyn Future<Output = usize>>> {
+///         Box::pin(async { 0 })
+///     }
+/// }
+/// impl Node for Wrap {
+///     fn depth(self: Rc<Self>) -> Pin<Box<dyn Future<Output = usize>>> {
+///         Box::pin(async move { call(self.0.clone().depth()).await + 1 })
+///     }
+/// }
+///
+/// let mut node: Rc<dyn Node> = Rc::new(Leaf);
+/// for _ in 0..10000 {
+///     node = Rc::new(Wrap(node));
+/// }
+/// assert_eq!(execute(node.depth()), 10000);
+/// ```
+///
+/// ### Panics
+/// Awaiting the returned future outside of [`execute`] panics.
+pub fn call<F>(fut: F) -> Call<F::Output>
+where
+	F: Future + 'static,
+{
+	Call::new(fut)
+}
+
+/// Future returned by [`call`].
+pub struct Call<T> {
+	state: CallState<T>,
+}
+
+enum CallState<T> {
+	// A frame to push on the runtime.
+	Start(Frame, Slot<T>),
+	// A frame put on the segment of the caller.
+	Queued(Rc<dyn Queued<T>>),
+	InSegment(Rc<dyn Queued<T>>),
+	Ready(T),
+	Waiting(Slot<T>),
+	Done,
+}
+
+impl<T: 'static> Call<T> {
+	pub(crate) fn new<F>(fut: F) -> Self
+	where
+		F: Future<Output = T> + 'static,
+	{
+		match current_queue::<F>() {
+			Some(queue) => {
+				queue.next.set(Some(fut));
+				queue.state.set(QueueState::Queued);
+				Self {
+					state: CallState::Queued(queue),
+				}
+			}
+			None => {
+				let (frame, slot) = new_segment(fut);
+				Self::from_frame(frame, slot)
+			}
+		}
+	}
+	pub(crate) fn ready(output: T) -> Self {
+		Self {
+			state: CallState::Ready(output),
+		}
+	}
+	pub(crate) fn from_frame(frame: Frame, slot: Slot<T>) -> Self {
+		Self {
+			state: CallState::Start(frame, slot),
+		}
+	}
+}
+
+// The output is never pinned, it's only moved out.
+impl<T> Unpin for Call<T> {}
+
+impl<T> Future for Call<T> {
+	type Output = T;
+
+	fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+		match std::mem::replace(&mut self.state, CallState::Done) {
+			CallState::Start(frame, slot) => {
+				with_current(|runtime| runtime.next.set(Some(frame)));
+				self.state = CallState::Waiting(slot);
+				Poll::Pending
+			}
+			// Awaited from a frame of the segment it was created in.
+			CallState::Queued(queued) if queued.in_current_segment() => {
+				queued.awaited();
+				self.state = CallState::InSegment(queued);
+				Poll::Pending
+			}
+			// Awaited from somewhere else, e.g. passed to a call of another type, so it goes on the runtime instead.
+			CallState::Queued(queued) => {
+				let (frame, slot) = queued.detach();
+				with_current(|runtime| runtime.next.set(Some(frame)));
+				self.state = CallState::Waiting(slot);
+				Poll::Pending
+			}
+			CallState::Ready(output) => Poll::Ready(output),
+			// UNWRAP Safety: The executor only polls us again once the frame we pushed completed.
+			CallState::Waiting(slot) => Poll::Ready(slot.take().unwrap()),
+			// UNWRAP Safety: The segment only polls us again once the frame we queued completed.
+			CallState::InSegment(queued) => Poll::Ready(queued.take().unwrap()),
+			CallState::Done => panic!("Decurse: call polled after completion."),
+		}
+	}
+}
+
+impl<T> Drop for Call<T> {
+	fn drop(&mut self) {
+		if let CallState::Queued(queued) = &self.state {
+			queued.dropped();
+		}
+	}
+}
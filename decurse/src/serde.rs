@@ -0,0 +1,114 @@
+//! Serializing and deserializing deeply nested values with serde. Needs the `serde` feature.
+//!
+//! A recursive type serializes and deserializes by native recursion,
+//! and formats like `serde_json` only lift their own depth limit if asked to
+//! (`serde_json` has `Deserializer::disable_recursion_limit`, behind its `unbounded_depth` feature).
+//! Mark the fields that hold children with `#[serde(with = "decurse::serde::nested")]`:
+//!
+//! ```
+//! # use serde::{Deserialize, Serialize};
+//! #[derive(Serialize, Deserialize)]
+//! struct Node {
+//!     value: u32,
+//!     #[serde(with = "decurse::serde::nested")]
+//!     next: Option<Box<Node>>,
+//! }
+//! ```
+//!
+//! The traits of serde call each other directly instead of returning to a loop,
+//! so unlike the rest of this crate the levels can't become frames of a heap stack.
+//! Instead, a marked field continues on a new stack segment, allocated on the heap, once the stack runs low.
+//!
+//! For untrusted input, limit how deep the marked fields can nest with [`with_max_depth`].
+//!
+//! Attributes that make serde buffer the input first, like `untagged` and `flatten`,
+//! read the whole nested value before any marked field is reached, so they still recurse natively.
+
+use std::cell::Cell;
+
+use ::serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+// Grow the stack when less than this is left.
+const RED_ZONE: usize = 128 * 1024;
+// The size of the new stack segments.
+const SEGMENT: usize = 2 * 1024 * 1024;
+
+thread_local! {
+	static DEPTH: Cell<usize> = const { Cell::new(0) };
+	// The depth marked fields can't go beyond, and the maximum it was set from.
+	static LIMIT: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+/// Run `f`, where marked fields nested more than `max` levels deep fail to serialize or deserialize.
+///
+/// ```
+/// # use serde::Deserialize;
+/// #[derive(Deserialize)]
+/// struct Node {
+///     #[serde(with = "decurse::serde::nested")]
+///     next: Option<Box<Node>>,
+/// }
+///
+/// let json = r#"{"next": {"next": {"next": null}}}"#;
+/// let parse = |max| decurse::serde::with_max_depth(max, || serde_json::from_str::<Node>(json));
+/// // Each `next` counts, the `null` one included.
+/// assert!(parse(3).is_ok());
+/// assert!(parse(2).is_err());
+/// ```
+pub fn with_max_depth<R>(max: usize, f: impl FnOnce() -> R) -> R {
+	struct Restore(Option<(usize, usize)>);
+	impl Drop for Restore {
+		fn drop(&mut self) {
+			LIMIT.with(|limit| limit.set(self.0));
+		}
+	}
+	let depth = DEPTH.with(Cell::get);
+	let _restore = Restore(LIMIT.with(|limit| limit.replace(Some((depth + max, max)))));
+	f()
+}
+
+// Run `f` one level deeper, on a new stack segment if needed.
+fn nest<R, E>(f: impl FnOnce() -> Result<R, E>, error: impl FnOnce(String) -> E) -> Result<R, E> {
+	let depth = DEPTH.with(Cell::get) + 1;
+	if let Some((limit, max)) = LIMIT.with(Cell::get) {
+		if depth > limit {
+			return Err(error(format!(
+				"Decurse: nested deeper than the maximum depth of {}.",
+				max
+			)));
+		}
+	}
+	struct Leave;
+	impl Drop for Leave {
+		fn drop(&mut self) {
+			DEPTH.with(|d| d.set(d.get() - 1));
+		}
+	}
+	DEPTH.with(|d| d.set(depth));
+	let _leave = Leave;
+	stacker::maybe_grow(RED_ZONE, SEGMENT, f)
+}
+
+/// Use with `#[serde(with = "decurse::serde::nested")]` on the fields that hold children.
+///
+/// The field is serialized and deserialized with its own `Serialize` and `Deserialize`,
+/// counting one level towards [`with_max_depth`].
+pub mod nested {
+	use super::*;
+
+	pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		T: Serialize + ?Sized,
+		S: Serializer,
+	{
+		nest(|| value.serialize(serializer), ser::Error::custom)
+	}
+
+	pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+	where
+		T: Deserialize<'de>,
+		D: Deserializer<'de>,
+	{
+		nest(|| T::deserialize(deserializer), de::Error::custom)
+	}
+}
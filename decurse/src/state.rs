@@ -0,0 +1,102 @@
+use std::{
+	any::{Any, TypeId},
+	cell::RefCell,
+	collections::HashMap,
+	marker::PhantomData,
+	rc::Rc,
+};
+
+thread_local! {
+	// The value of each type of state in scope, as an `Rc<RefCell<*mut T>>` pointing to the value lent to `scope`.
+	static STATES: RefCell<HashMap<TypeId, Rc<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// Mutable state shared by every frame of a computation.
+///
+/// Decursed functions can't take `&mut` arguments, since their frames live on the heap.
+/// Instead, put the value in scope with [`State::scope`] (or the `#[decurse(state(T))]` option),
+/// and access it from any frame with [`State::with`].
+/// The access is a closure, so the borrow can't be held across a recursive call.
+///
+/// There is one state per type and thread. Use a newtype to have several states of the same type.
+///
+/// ```
+/// use decurse::State;
+///
+/// #[decurse::decurse]
+/// fn collect(x: u32) {
+///     State::<Vec<u32>>::with(|found| found.push(x));
+///     if x > 0 {
+///         collect(x - 1);
+///     }
+/// }
+///
+/// let mut found: Vec<u32> = Vec::new();
+/// State::scope(&mut found, || collect(100000));
+/// assert_eq!(found.len(), 100001);
+/// ```
+pub struct State<T> {
+	_marker: PhantomData<T>,
+}
+
+impl<T: 'static> State<T> {
+	/// Put the value in scope while `f` runs.
+	///
+	/// The value is borrowed until `f` returns or panics, and every change made with [`State::with`] is made to it.
+	pub fn scope<R>(value: &mut T, f: impl FnOnce() -> R) -> R {
+		// Takes the value out of scope even if we panic.
+		struct Exit<T: 'static> {
+			previous: Option<Rc<dyn Any>>,
+			_marker: PhantomData<T>,
+		}
+		impl<T: 'static> Drop for Exit<T> {
+			fn drop(&mut self) {
+				State::<T>::exit(self.previous.take());
+			}
+		}
+		let state: Rc<dyn Any> = Rc::new(RefCell::new(value as *mut T));
+		let _exit = Exit::<T> {
+			previous: STATES.with(|s| s.borrow_mut().insert(TypeId::of::<T>(), state)),
+			_marker: PhantomData,
+		};
+		f()
+	}
+	/// Put the value in scope while `f` runs, then return it along with the output of `f`.
+	pub fn scope_owned<R>(value: T, f: impl FnOnce() -> R) -> (R, T) {
+		let mut value = value;
+		let output = Self::scope(&mut value, f);
+		(output, value)
+	}
+	/// Borrow the state mutably while `f` runs.
+	///
+	/// ### Panics
+	/// Panics if no state of this type is in scope, or if it is already borrowed (i.e. if `f` calls `with` for the same type).
+	pub fn with<R>(f: impl FnOnce(&mut T) -> R) -> R {
+		let state = STATES
+			.with(|s| s.borrow().get(&TypeId::of::<T>()).cloned())
+			.expect("Decurse: no state of this type in scope.");
+		// UNWRAP Safety: States are always stored under the `TypeId` of their type.
+		let state = state.downcast::<RefCell<*mut T>>().ok().unwrap();
+		let borrowed = state
+			.try_borrow_mut()
+			.expect("Decurse: state already borrowed.");
+		let value = *borrowed;
+		// Safety: The pointer comes from the `&mut T` lent to `scope`, which takes it out of scope before returning,
+		// and the `RefCell` stays borrowed while `f` runs, so only one `&mut T` is made from it at a time.
+		f(unsafe { &mut *value })
+	}
+	/// Whether a state of this type is in scope.
+	pub fn in_scope() -> bool {
+		STATES.with(|s| s.borrow().contains_key(&TypeId::of::<T>()))
+	}
+	// Take the value out of scope, putting back what it replaced.
+	fn exit(previous: Option<Rc<dyn Any>>) {
+		STATES.with(|s| {
+			let mut states = s.borrow_mut();
+			match previous {
+				Some(previous) => states.insert(TypeId::of::<T>(), previous),
+				None => states.remove(&TypeId::of::<T>()),
+			}
+		});
+	}
+}
@@ -0,0 +1,59 @@
+//! Spans for decursed frames, with the `tracing` feature.
+//!
+//! Each frame of a `#[decurse]` function (or [`Recursion`](crate::Recursion)) gets a `TRACE` level span named `frame`,
+//! entered whenever the executor polls the frame and exited when it pends or completes.
+//! The span has these fields:
+//! * `function`: the name of the function, as in [`stack`](crate::stack).
+//! * `depth`: the depth of the frame, as in [`depth`](crate::depth).
+//! * `args`: the arguments, for functions with `#[decurse(debug_args)]` or `#[decurse(debug_args(x, y))]`.
+//!
+//! Deep recursions would make a lot of spans,
+//! so [`set_max_depth`] and [`set_sample_every`] limit which frames get one.
+
+use crate::runtime::Frame;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{field, Instrument, Level, Span};
+
+static MAX_DEPTH: AtomicUsize = AtomicUsize::new(usize::MAX);
+static SAMPLE_EVERY: AtomicUsize = AtomicUsize::new(1);
+
+/// Only give spans to frames at most this deep. `None` for no limit, which is the default.
+pub fn set_max_depth(max_depth: Option<usize>) {
+	MAX_DEPTH.store(max_depth.unwrap_or(usize::MAX), Ordering::Relaxed);
+}
+
+/// Only give spans to one in every `every` levels of depth: depth 1, `1 + every`, `1 + 2 * every` and so on.
+/// The default is 1, giving spans to every frame.
+///
+/// ### Panics
+/// Panics if `every` is 0.
+pub fn set_sample_every(every: usize) {
+	assert!(every > 0, "Decurse: cannot sample every 0 frames.");
+	SAMPLE_EVERY.store(every, Ordering::Relaxed);
+}
+
+// The span for a frame, if it should have one.
+// `%` rather than `is_multiple_of`, which needs a newer Rust.
+#[allow(unknown_lints, clippy::manual_is_multiple_of)]
+pub(crate) fn span(function: &str, args: Option<&str>, depth: usize) -> Option<Span> {
+	if depth > MAX_DEPTH.load(Ordering::Relaxed)
+		|| (depth - 1) % SAMPLE_EVERY.load(Ordering::Relaxed) != 0
+		|| !tracing::enabled!(Level::TRACE)
+	{
+		return None;
+	}
+	let span: Span = tracing::trace_span!(
+		"frame",
+		function = function,
+		depth = depth,
+		args = field::Empty
+	);
+	if let Some(args) = args {
+		span.record("args", args);
+	}
+	Some(span)
+}
+
+pub(crate) fn instrument(frame: Frame, span: Span) -> Frame {
+	Box::pin(frame.instrument(span))
+}
@@ -0,0 +1,166 @@
+use crate::{call, execute};
+use std::{future::Future, mem, pin::Pin};
+
+type Step<T> = Pin<Box<dyn Future<Output = Trampoline<T>>>>;
+
+// A pending `and_then`, with the type of the inner value erased.
+trait Bind<T> {
+	fn step(self: Box<Self>) -> Step<T>;
+	// The inner computation, to be dropped next.
+	fn unlink(self: Box<Self>) -> Box<dyn Unlink>;
+}
+
+// A computation of any type, being dropped.
+trait Unlink {
+	// Drop everything but the nested computation, and return it.
+	fn unlink(&mut self) -> Option<Box<dyn Unlink>>;
+}
+
+struct BindWith<A, T> {
+	inner: Trampoline<A>,
+	then: Box<dyn FnOnce(A) -> Trampoline<T>>,
+}
+
+impl<A: 'static, T: 'static> Bind<T> for BindWith<A, T> {
+	fn step(self: Box<Self>) -> Step<T> {
+		let BindWith { inner, then } = *self;
+		Box::pin(async move {
+			// The inner computation runs as a new frame on the heap stack.
+			let a = call(inner.eval()).await;
+			then(a)
+		})
+	}
+	fn unlink(self: Box<Self>) -> Box<dyn Unlink> {
+		Box::new(self.inner)
+	}
+}
+
+/// A stack-safe computation written in continuation style.
+///
+/// Build it from [`Trampoline::done`] and [`Trampoline::suspend`],
+/// chain it with [`Trampoline::map`] and [`Trampoline::and_then`],
+/// and evaluate it with [`Trampoline::run`].
+/// Evaluation runs on the heap stack like [`execute`] does,
+/// so arbitrarily long `and_then` chains and deeply nested suspensions don't grow the stack.
+///
+/// ```
+/// use decurse::Trampoline;
+///
+/// fn is_even(x: u64) -> Trampoline<bool> {
+///     if x == 0 {
+///         Trampoline::done(true)
+///     } else {
+///         Trampoline::suspend(move || is_odd(x - 1))
+///     }
+/// }
+/// fn is_odd(x: u64) -> Trampoline<bool> {
+///     if x == 0 {
+///         Trampoline::done(false)
+///     } else {
+///         Trampoline::suspend(move || is_even(x - 1))
+///     }
+/// }
+/// assert!(is_even(1000000).run());
+///
+/// fn triangular(x: u64) -> Trampoline<u64> {
+///     if x == 0 {
+///         Trampoline::done(0)
+///     } else {
+///         Trampoline::suspend(move || triangular(x - 1)).map(move |t| t + x)
+///     }
+/// }
+/// assert_eq!(triangular(200000).run(), 20000100000);
+/// ```
+pub struct Trampoline<T> {
+	state: State<T>,
+}
+
+enum State<T> {
+	Done(T),
+	Suspend(Box<dyn FnOnce() -> Trampoline<T>>),
+	Bind(Box<dyn Bind<T>>),
+	// Left once the state is taken out, since `Trampoline` has a `Drop`.
+	Taken,
+}
+
+impl<T: 'static> Trampoline<T> {
+	/// A computation that is already finished, with the given value.
+	pub fn done(value: T) -> Self {
+		Self {
+			state: State::Done(value),
+		}
+	}
+	/// A computation that continues with whatever the function returns.
+	/// The function is only called when the computation is run.
+	pub fn suspend<F>(f: F) -> Self
+	where
+		F: FnOnce() -> Trampoline<T> + 'static,
+	{
+		Self {
+			state: State::Suspend(Box::new(f)),
+		}
+	}
+	/// Transform the result of this computation.
+	pub fn map<U, F>(self, f: F) -> Trampoline<U>
+	where
+		U: 'static,
+		F: FnOnce(T) -> U + 'static,
+	{
+		self.and_then(move |t| Trampoline::done(f(t)))
+	}
+	/// Continue this computation with another one that depends on its result.
+	pub fn and_then<U, F>(self, f: F) -> Trampoline<U>
+	where
+		U: 'static,
+		F: FnOnce(T) -> Trampoline<U> + 'static,
+	{
+		Trampoline {
+			state: State::Bind(Box::new(BindWith {
+				inner: self,
+				then: Box::new(f),
+			})),
+		}
+	}
+	/// Run the computation, returning its result.
+	pub fn run(self) -> T {
+		execute(self.eval())
+	}
+	async fn eval(self) -> T {
+		let mut current = self;
+		// Steps in tail position are run in a loop, so they don't pile up on the heap stack either.
+		loop {
+			current = match current.take() {
+				State::Done(value) => return value,
+				State::Suspend(f) => f(),
+				State::Bind(bind) => bind.step().await,
+				State::Taken => unreachable!("Decurse: trampoline evaluated twice."),
+			};
+		}
+	}
+}
+
+impl<T> Trampoline<T> {
+	fn take(&mut self) -> State<T> {
+		mem::replace(&mut self.state, State::Taken)
+	}
+}
+
+impl<T> Unlink for Trampoline<T> {
+	fn unlink(&mut self) -> Option<Box<dyn Unlink>> {
+		match self.take() {
+			State::Bind(bind) => Some(bind.unlink()),
+			_ => None,
+		}
+	}
+}
+
+impl<T> Drop for Trampoline<T> {
+	fn drop(&mut self) {
+		// A chain of `and_then` nests as deep as it is long,
+		// so it's dropped one computation at a time instead of recursively.
+		let mut next = self.unlink();
+		while let Some(mut current) = next {
+			next = current.unlink();
+		}
+	}
+}
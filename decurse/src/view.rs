@@ -0,0 +1,492 @@
+//! Owned views into shared data, for passing parts of a value into `#[decurse]` functions.
+//!
+//! The sound macro doesn't allow borrows in the arguments, so a recursion over a slice, a string or a tree
+//! can't take `&[T]`, `&str` or `&Node`.
+//! These types hold the data in an `Rc` (or an `Arc`) and point at a part of it,
+//! so they are `'static`, cloning them is cheap, and narrowing them doesn't copy anything.
+//! They contain no unsafe code.
+//!
+//! ```
+//! use decurse::view::RcSlice;
+//!
+//! #[decurse::decurse]
+//! fn sum(values: RcSlice<u64>) -> u64 {
+//!     match values.split_first() {
+//!         Some((first, rest)) => first + sum(rest),
+//!         None => 0,
+//!     }
+//! }
+//!
+//! let values: RcSlice<u64> = (0..100000).collect::<Vec<_>>().into();
+//! assert_eq!(sum(values.slice(..1000)), 499500);
+//! assert_eq!(sum(values), 4999950000);
+//! ```
+
+use std::{
+	borrow::Borrow,
+	cmp::Ordering,
+	fmt,
+	hash::{Hash, Hasher},
+	iter::FromIterator,
+	ops::{Bound, Deref, RangeBounds},
+	rc::Rc,
+	sync::Arc,
+};
+
+// The absolute start and end of `range`, taken relative to `start..end`.
+fn sub_range(start: usize, end: usize, range: impl RangeBounds<usize>) -> (usize, usize) {
+	let len = end - start;
+	// `None` for a bound one past `usize::MAX`, which is out of bounds of any view.
+	let from = match range.start_bound() {
+		Bound::Included(&from) => Some(from),
+		Bound::Excluded(&from) => from.checked_add(1),
+		Bound::Unbounded => Some(0),
+	};
+	let to = match range.end_bound() {
+		Bound::Included(&to) => to.checked_add(1),
+		Bound::Excluded(&to) => Some(to),
+		Bound::Unbounded => Some(len),
+	};
+	let show = |bound: Option<usize>| {
+		bound.map_or_else(|| "usize::MAX + 1".to_string(), |b| b.to_string())
+	};
+	assert!(
+		matches!((from, to), (Some(from), Some(to)) if from <= to && to <= len),
+		"Decurse: range {}..{} out of bounds of a view of length {}.",
+		show(from),
+		show(to),
+		len
+	);
+	// UNWRAP Safety: The assertion checked that both bounds are there.
+	(start + from.unwrap(), start + to.unwrap())
+}
+
+/// A part of a slice held in an `Rc`, like an `Rc<[T]>` that can be narrowed.
+pub struct RcSlice<T> {
+	data: Rc<[T]>,
+	start: usize,
+	end: usize,
+}
+
+impl<T> RcSlice<T> {
+	/// A view of the whole slice.
+	pub fn new(data: Rc<[T]>) -> Self {
+		let end = data.len();
+		Self {
+			data,
+			start: 0,
+			end,
+		}
+	}
+	/// A view of the part of this view in `range`, sharing the data.
+	///
+	/// ### Panics
+	/// If the range is out of bounds, like indexing a slice.
+	pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+		let (start, end) = sub_range(self.start, self.end, range);
+		Self {
+			data: self.data.clone(),
+			start,
+			end,
+		}
+	}
+	/// Split the view in two at `mid`, like [`slice::split_at`].
+	pub fn split_at(&self, mid: usize) -> (Self, Self) {
+		(self.slice(..mid), self.slice(mid..))
+	}
+	/// The first element and a view of the rest, or `None` if the view is empty.
+	pub fn split_first(&self) -> Option<(&T, Self)> {
+		let first = self.first()?;
+		Some((first, self.slice(1..)))
+	}
+	/// The last element and a view of the rest, or `None` if the view is empty.
+	pub fn split_last(&self) -> Option<(&T, Self)> {
+		let last = self.last()?;
+		Some((last, self.slice(..self.len() - 1)))
+	}
+	/// The whole slice this view is a part of.
+	pub fn whole(&self) -> &Rc<[T]> {
+		&self.data
+	}
+	/// The range of the whole slice this view covers.
+	pub fn range(&self) -> std::ops::Range<usize> {
+		self.start..self.end
+	}
+}
+
+impl<T> Clone for RcSlice<T> {
+	fn clone(&self) -> Self {
+		Self {
+			data: self.data.clone(),
+			start: self.start,
+			end: self.end,
+		}
+	}
+}
+
+impl<T> Deref for RcSlice<T> {
+	type Target = [T];
+	fn deref(&self) -> &[T] {
+		&self.data[self.start..self.end]
+	}
+}
+
+impl<T> AsRef<[T]> for RcSlice<T> {
+	fn as_ref(&self) -> &[T] {
+		self
+	}
+}
+
+impl<T> Borrow<[T]> for RcSlice<T> {
+	fn borrow(&self) -> &[T] {
+		self
+	}
+}
+
+impl<T> From<Rc<[T]>> for RcSlice<T> {
+	fn from(data: Rc<[T]>) -> Self {
+		Self::new(data)
+	}
+}
+
+impl<T> From<Vec<T>> for RcSlice<T> {
+	fn from(data: Vec<T>) -> Self {
+		Self::new(data.into())
+	}
+}
+
+impl<T> FromIterator<T> for RcSlice<T> {
+	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+		Self::new(iter.into_iter().collect())
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for RcSlice<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(&**self, f)
+	}
+}
+
+impl<T: PartialEq> PartialEq for RcSlice<T> {
+	fn eq(&self, other: &Self) -> bool {
+		**self == **other
+	}
+}
+
+impl<T: Eq> Eq for RcSlice<T> {}
+
+impl<T: PartialOrd> PartialOrd for RcSlice<T> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		(**self).partial_cmp(&**other)
+	}
+}
+
+impl<T: Ord> Ord for RcSlice<T> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		(**self).cmp(&**other)
+	}
+}
+
+impl<T: Hash> Hash for RcSlice<T> {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		(**self).hash(state)
+	}
+}
+
+/// A part of a string held in an `Arc`, like an `Arc<str>` that can be narrowed.
+///
+/// Unlike the other views, it can be sent to other threads.
+#[derive(Clone)]
+pub struct ArcStr {
+	data: Arc<str>,
+	start: usize,
+	end: usize,
+}
+
+impl ArcStr {
+	/// A view of the whole string.
+	pub fn new(data: Arc<str>) -> Self {
+		let end = data.len();
+		Self {
+			data,
+			start: 0,
+			end,
+		}
+	}
+	/// A view of the bytes of this view in `range`, sharing the data.
+	///
+	/// ### Panics
+	/// If the range is out of bounds or not on character boundaries, like indexing a `str`.
+	pub fn substr(&self, range: impl RangeBounds<usize>) -> Self {
+		let (start, end) = sub_range(self.start, self.end, range);
+		// Check the character boundaries.
+		let _ = &self.data[start..end];
+		Self {
+			data: self.data.clone(),
+			start,
+			end,
+		}
+	}
+	/// Split the view in two at the byte `mid`, like [`str::split_at`].
+	pub fn split_at(&self, mid: usize) -> (Self, Self) {
+		(self.substr(..mid), self.substr(mid..))
+	}
+	/// The view as a `&str`.
+	pub fn as_str(&self) -> &str {
+		&self.data[self.start..self.end]
+	}
+	/// The whole string this view is a part of.
+	pub fn whole(&self) -> &Arc<str> {
+		&self.data
+	}
+	/// The byte range of the whole string this view covers.
+	pub fn range(&self) -> std::ops::Range<usize> {
+		self.start..self.end
+	}
+}
+
+impl Deref for ArcStr {
+	type Target = str;
+	fn deref(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl AsRef<str> for ArcStr {
+	fn as_ref(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl Borrow<str> for ArcStr {
+	fn borrow(&self) -> &str {
+		self.as_str()
+	}
+}
+
+impl From<Arc<str>> for ArcStr {
+	fn from(data: Arc<str>) -> Self {
+		Self::new(data)
+	}
+}
+
+impl From<String> for ArcStr {
+	fn from(data: String) -> Self {
+		Self::new(data.into())
+	}
+}
+
+impl From<&str> for ArcStr {
+	fn from(data: &str) -> Self {
+		Self::new(data.into())
+	}
+}
+
+impl fmt::Debug for ArcStr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Debug::fmt(self.as_str(), f)
+	}
+}
+
+impl fmt::Display for ArcStr {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(self.as_str(), f)
+	}
+}
+
+impl PartialEq for ArcStr {
+	fn eq(&self, other: &Self) -> bool {
+		self.as_str() == other.as_str()
+	}
+}
+
+impl PartialEq<str> for ArcStr {
+	fn eq(&self, other: &str) -> bool {
+		self.as_str() == other
+	}
+}
+
+impl PartialEq<&str> for ArcStr {
+	fn eq(&self, other: &&str) -> bool {
+		self.as_str() == *other
+	}
+}
+
+impl Eq for ArcStr {}
+
+impl PartialOrd for ArcStr {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for ArcStr {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.as_str().cmp(other.as_str())
+	}
+}
+
+impl Hash for ArcStr {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.as_str().hash(state)
+	}
+}
+
+struct TreeNode<T> {
+	value: T,
+	parent: Option<usize>,
+	children: Vec<usize>,
+}
+
+/// Builds the tree of [`RcTreeRef`], from the root down.
+///
+/// Nodes are numbered in the order they're added, the root being 0.
+///
+/// ```
+/// use decurse::view::RcTreeBuilder;
+///
+/// let mut builder = RcTreeBuilder::new("root");
+/// let a = builder.push(0, "a");
+/// builder.push(a, "a1");
+/// builder.push(0, "b");
+/// let root = builder.build();
+///
+/// let names: Vec<_> = root.children().map(|child| *child).collect();
+/// assert_eq!(names, ["a", "b"]);
+/// let a1 = root.child(0).unwrap().child(0).unwrap();
+/// assert_eq!(*a1.parent().unwrap().parent().unwrap(), "root");
+/// ```
+pub struct RcTreeBuilder<T> {
+	nodes: Vec<TreeNode<T>>,
+}
+
+impl<T> RcTreeBuilder<T> {
+	/// A tree of only the root.
+	pub fn new(root: T) -> Self {
+		Self {
+			nodes: vec![TreeNode {
+				value: root,
+				parent: None,
+				children: Vec::new(),
+			}],
+		}
+	}
+	/// Add a node as the last child of the node numbered `parent`, returning its number.
+	///
+	/// ### Panics
+	/// If there is no node numbered `parent` yet.
+	pub fn push(&mut self, parent: usize, value: T) -> usize {
+		let index = self.nodes.len();
+		self.nodes
+			.get_mut(parent)
+			.expect("Decurse: the parent wasn't added to the tree.")
+			.children
+			.push(index);
+		self.nodes.push(TreeNode {
+			value,
+			parent: Some(parent),
+			children: Vec::new(),
+		});
+		index
+	}
+	/// Finish the tree, returning a cursor at its root.
+	pub fn build(self) -> RcTreeRef<T> {
+		RcTreeRef {
+			nodes: self.nodes.into(),
+			index: 0,
+		}
+	}
+}
+
+/// A cursor at a node of a tree held in an `Rc`, which can move to the children and the parent of the node.
+///
+/// Build the tree with [`RcTreeBuilder`]. The cursor dereferences to the value of the node.
+///
+/// ```
+/// use decurse::view::{RcTreeBuilder, RcTreeRef};
+///
+/// #[decurse::decurse]
+/// fn depth(node: RcTreeRef<u32>) -> usize {
+///     let mut deepest = 0;
+///     for child in node.children() {
+///         deepest = deepest.max(depth(child));
+///     }
+///     deepest + 1
+/// }
+///
+/// let mut builder = RcTreeBuilder::new(0);
+/// let mut last = 0;
+/// for value in 1..100000 {
+///     last = builder.push(last, value);
+/// }
+/// assert_eq!(depth(builder.build()), 100000);
+/// ```
+pub struct RcTreeRef<T> {
+	nodes: Rc<[TreeNode<T>]>,
+	index: usize,
+}
+
+impl<T> RcTreeRef<T> {
+	fn at(&self, index: usize) -> Self {
+		Self {
+			nodes: self.nodes.clone(),
+			index,
+		}
+	}
+	fn node(&self) -> &TreeNode<T> {
+		&self.nodes[self.index]
+	}
+	/// The value of the node.
+	pub fn value(&self) -> &T {
+		&self.node().value
+	}
+	/// The number of the node, in the order it was added to the [`RcTreeBuilder`].
+	pub fn index(&self) -> usize {
+		self.index
+	}
+	/// A cursor at the root of the tree.
+	pub fn root(&self) -> Self {
+		self.at(0)
+	}
+	/// A cursor at the parent, or `None` at the root.
+	pub fn parent(&self) -> Option<Self> {
+		self.node().parent.map(|parent| self.at(parent))
+	}
+	/// A cursor at the `i`th child, if there are that many.
+	pub fn child(&self, i: usize) -> Option<Self> {
+		self.node().children.get(i).map(|&child| self.at(child))
+	}
+	/// Cursors at the children, in the order they were added.
+	pub fn children(&self) -> impl ExactSizeIterator<Item = Self> + '_ {
+		self.node()
+			.children
+			.iter()
+			.map(move |&child| self.at(child))
+	}
+	/// Whether both cursors are at the same node of the same tree.
+	pub fn ptr_eq(&self, other: &Self) -> bool {
+		Rc::ptr_eq(&self.nodes, &other.nodes) && self.index == other.index
+	}
+}
+
+impl<T> Clone for RcTreeRef<T> {
+	fn clone(&self) -> Self {
+		self.at(self.index)
+	}
+}
+
+impl<T> Deref for RcTreeRef<T> {
+	type Target = T;
+	fn deref(&self) -> &T {
+		self.value()
+	}
+}
+
+impl<T: fmt::Debug> fmt::Debug for RcTreeRef<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("RcTreeRef")
+			.field("index", &self.index)
+			.field("value", self.value())
+			.finish()
+	}
+}
@@ -0,0 +1,261 @@
+//! Walking trees and graphs of any node type, on the heap stack.
+//!
+//! A [`Walk`] is given a function listing the children of a node,
+//! and calls back when it enters a node, between its first and second child, and when it exits the node.
+//! The callbacks decide whether to go on with a [`Control`].
+//! Every level of the walk is a frame on a heap stack, so it doesn't overflow however deep the nodes go.
+//!
+//! ```
+//! use decurse::walk::{Control, Walk};
+//!
+//! struct Tree {
+//!     name: String,
+//!     children: Vec<Tree>,
+//! }
+//!
+//! let tree = Tree {
+//!     name: "root".into(),
+//!     children: vec![
+//!         Tree { name: "a".into(), children: vec![] },
+//!         Tree { name: "b".into(), children: vec![] },
+//!     ],
+//! };
+//! let mut lines = Vec::new();
+//! Walk::new(|tree: &&Tree| tree.children.iter())
+//!     .enter(|tree, depth| {
+//!         lines.push(format!("{}{}", "  ".repeat(depth), tree.name));
+//!         Control::Continue
+//!     })
+//!     .run(&tree);
+//! assert_eq!(lines, ["root", "  a", "  b"]);
+//! ```
+//!
+//! For the common cases, [`pre_order`], [`post_order`] and (for binary trees) [`in_order`] list the nodes.
+//!
+//! The nodes are owned by the walk, and are usually references or indices.
+//! The children are listed lazily, when the walk gets to them,
+//! so in a graph they can skip the nodes visited since they were listed (see [`Walk::new`]).
+
+use crate::deep::{Scope, ScopedCall};
+use std::cell::{Cell, RefCell};
+
+/// What a callback of a [`Walk`] wants to happen next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+	/// Go on walking.
+	Continue,
+	/// Skip the remaining children of this node, and go on with the rest of the walk.
+	///
+	/// From [`Walk::enter`], this skips all the children. From [`Walk::exit`], this is the same as `Continue`.
+	Prune,
+	/// End the walk. No callback is called after this.
+	Stop,
+}
+
+type Children<'a, N> = Box<dyn FnMut(&N) -> Box<dyn Iterator<Item = N> + 'a> + 'a>;
+type Callback<'a, N> = Option<Box<dyn FnMut(&N, usize) -> Control + 'a>>;
+
+/// A walk over the nodes reachable from a root, with callbacks. See the [module docs](self).
+///
+/// The callbacks get the node and its depth, the root being at depth 0.
+pub struct Walk<'a, N> {
+	children: Children<'a, N>,
+	enter: Callback<'a, N>,
+	between: Callback<'a, N>,
+	exit: Callback<'a, N>,
+}
+
+impl<'a, N: 'a> Walk<'a, N> {
+	/// A walk that finds the children of a node with `children`, without any callbacks yet.
+	///
+	/// The returned children are only iterated once the walk gets to them, after the previous ones were walked.
+	/// In a graph, filtering out the visited nodes in the iterator (rather than in `children` itself)
+	/// makes it a depth first search that visits every node once.
+	///
+	/// ```
+	/// use decurse::walk::{Control, Walk};
+	/// use std::cell::RefCell;
+	///
+	/// // A path 0 -> 1 -> ... -> 99999, where every node also points back to 0.
+	/// let len = 100000;
+	/// let visited = RefCell::new(vec![false; len]);
+	/// let mut depth = 0;
+	/// Walk::new(|&node: &usize| {
+	///     let next = vec![node + 1, 0].into_iter().filter(|&next| next < len);
+	///     next.filter(|&next| !visited.borrow()[next])
+	/// })
+	/// .enter(|&node, d| {
+	///     visited.borrow_mut()[node] = true;
+	///     depth = depth.max(d);
+	///     Control::Continue
+	/// })
+	/// .run(0);
+	/// assert_eq!(depth, len - 1);
+	/// ```
+	pub fn new<I>(mut children: impl FnMut(&N) -> I + 'a) -> Self
+	where
+		I: IntoIterator<Item = N>,
+		I::IntoIter: 'a,
+	{
+		Self {
+			children: Box::new(move |node| Box::new(children(node).into_iter())),
+			enter: None,
+			between: None,
+			exit: None,
+		}
+	}
+	/// Call `f` on each node before its children, i.e. in pre-order.
+	pub fn enter(mut self, f: impl FnMut(&N, usize) -> Control + 'a) -> Self {
+		self.enter = Some(Box::new(f));
+		self
+	}
+	/// Call `f` on each node after its first child and before the others.
+	///
+	/// For a node without children (or whose children were pruned), this is called right after `enter`.
+	/// For a binary tree, this is in-order if a missing left child is still listed, see [`in_order`].
+	pub fn between(mut self, f: impl FnMut(&N, usize) -> Control + 'a) -> Self {
+		self.between = Some(Box::new(f));
+		self
+	}
+	/// Call `f` on each node after its children, i.e. in post-order.
+	///
+	/// This is called for every node that was entered, even if its children were pruned.
+	pub fn exit(mut self, f: impl FnMut(&N, usize) -> Control + 'a) -> Self {
+		self.exit = Some(Box::new(f));
+		self
+	}
+	/// Walk the nodes reachable from `root`. Returns `false` if a callback stopped the walk.
+	pub fn run(&mut self, root: N) -> bool {
+		let walk = RefCell::new(self);
+		let stopped = Cell::new(false);
+		Scope::run(|scope| walk_in(root, 0, &walk, &stopped, scope));
+		!stopped.get()
+	}
+}
+
+// Call the callback, if there is one.
+fn callback<N>(callback: &mut Callback<'_, N>, node: &N, depth: usize) -> Control {
+	match callback {
+		Some(callback) => callback(node, depth),
+		None => Control::Continue,
+	}
+}
+
+fn walk_in<'b, 'a: 'b, N: 'a>(
+	node: N,
+	depth: usize,
+	walk: &'b RefCell<&'b mut Walk<'a, N>>,
+	stopped: &'b Cell<bool>,
+	scope: &Scope<'b>,
+) -> ScopedCall<'b, ()> {
+	let inner = scope.clone();
+	scope.call(async move {
+		// Whether the walk goes on, noting if it was stopped.
+		let go_on = |control| match control {
+			Control::Stop => {
+				stopped.set(true);
+				None
+			}
+			control => Some(control),
+		};
+		let control = callback(&mut walk.borrow_mut().enter, &node, depth);
+		let mut children = match go_on(control) {
+			None => return,
+			Some(Control::Prune) => None,
+			Some(_) => Some((walk.borrow_mut().children)(&node)),
+		};
+		let mut first = true;
+		loop {
+			// Don't hold the borrow while the child is walked.
+			let child = children.as_mut().and_then(Iterator::next);
+			let child = match child {
+				Some(child) => child,
+				None => break,
+			};
+			walk_in(child, depth + 1, walk, stopped, &inner).await;
+			if stopped.get() {
+				return;
+			}
+			if first {
+				first = false;
+				let control = callback(&mut walk.borrow_mut().between, &node, depth);
+				match go_on(control) {
+					None => return,
+					Some(Control::Prune) => children = None,
+					Some(_) => {}
+				}
+			}
+		}
+		if first {
+			let control = callback(&mut walk.borrow_mut().between, &node, depth);
+			if go_on(control).is_none() {
+				return;
+			}
+		}
+		let control = callback(&mut walk.borrow_mut().exit, &node, depth);
+		go_on(control);
+	})
+}
+
+/// The nodes reachable from `root`, each before its children.
+pub fn pre_order<'a, N, I>(root: N, children: impl FnMut(&N) -> I + 'a) -> Vec<N>
+where
+	N: Clone + 'a,
+	I: IntoIterator<Item = N>,
+	I::IntoIter: 'a,
+{
+	let mut nodes = Vec::new();
+	Walk::new(children)
+		.enter(|node, _| {
+			nodes.push(node.clone());
+			Control::Continue
+		})
+		.run(root);
+	nodes
+}
+
+/// The nodes of a binary tree in order: the left subtree, the node, then the right subtree.
+///
+/// `children` returns the left and right children of a node.
+pub fn in_order<'a, N>(
+	root: N,
+	mut children: impl FnMut(&N) -> (Option<N>, Option<N>) + 'a,
+) -> Vec<N>
+where
+	N: Clone + 'a,
+{
+	let mut nodes = Vec::new();
+	// A missing left child is walked as `None`, so that the node still comes after it.
+	Walk::new(move |node: &Option<N>| {
+		let children = node.as_ref().map(|node| {
+			let (left, right) = children(node);
+			[left, right]
+		});
+		children.into_iter().flatten()
+	})
+	.between(|node, _| {
+		if let Some(node) = node {
+			nodes.push(node.clone());
+		}
+		Control::Continue
+	})
+	.run(Some(root));
+	nodes
+}
+
+/// The nodes reachable from `root`, each after its children.
+pub fn post_order<'a, N, I>(root: N, children: impl FnMut(&N) -> I + 'a) -> Vec<N>
+where
+	N: Clone + 'a,
+	I: IntoIterator<Item = N>,
+	I::IntoIter: 'a,
+{
+	let mut nodes = Vec::new();
+	Walk::new(children)
+		.exit(|node, _| {
+			nodes.push(node.clone());
+			Control::Continue
+		})
+		.run(root);
+	nodes
+}
@@ -0,0 +1,26 @@
+// The linked list that the tests of the deep derives share. Each test file uses only some of it.
+#![allow(dead_code)]
+
+use decurse::{
+	DeepClone, DeepDebug, DeepDrop, DeepEq, DeepHash, DeepOrd, DeepPartialEq, DeepPartialOrd,
+};
+
+// Deep enough to overflow the stack with the std derives.
+pub const DEEP: usize = 200000;
+
+#[derive(
+	DeepDrop, DeepClone, DeepPartialEq, DeepEq, DeepPartialOrd, DeepOrd, DeepHash, DeepDebug,
+)]
+pub struct Node<T> {
+	pub value: T,
+	pub next: Option<Box<Node<T>>>,
+}
+
+// A list of the values, the last one first.
+pub fn list<T>(values: impl IntoIterator<Item = T>) -> Option<Box<Node<T>>> {
+	let mut list = None;
+	for value in values {
+		list = Some(Box::new(Node { value, next: list }));
+	}
+	list
+}
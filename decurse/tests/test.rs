@@ -89,3 +89,162 @@ fn test_generic() {
 	}
 	assert_eq!(recursive(5, 0), 0);
 }
+
+#[test]
+fn test_abortable() {
+	#[decurse(abortable)]
+	fn triangular(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			triangular(x - 1) + x
+		}
+	}
+	let handle = decurse::AbortHandle::new();
+	assert_eq!(
+		triangular_abortable(200000, handle.clone()),
+		Ok(20000100000)
+	);
+	assert_eq!(triangular(200000), 20000100000);
+	handle.abort();
+	assert_eq!(
+		triangular_abortable(200000, handle),
+		Err(decurse::Cancelled)
+	);
+}
+
+#[test]
+#[deny(deprecated)]
+fn test_attributes() {
+	// Only doc and cfg attributes are copied to the items generated next to the wrapper.
+	#[decurse(abortable, calls())]
+	#[deprecated]
+	fn countdown(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			countdown(x - 1) + 1
+		}
+	}
+	let handle = decurse::AbortHandle::new();
+	assert_eq!(countdown_abortable(100000, handle), Ok(100000));
+	// Nothing is left when the function is configured out, so its body is never checked.
+	#[decurse(abortable, calls())]
+	#[cfg(any())]
+	fn gone(x: u64) -> u64 {
+		missing(x)
+	}
+}
+
+#[test]
+fn test_abort_from_other_thread() {
+	#[decurse(abortable)]
+	fn forever(x: u64) -> u64 {
+		forever(x + 1)
+	}
+	let handle = decurse::AbortHandle::new();
+	let thread = {
+		let handle = handle.clone();
+		std::thread::spawn(move || forever_abortable(0, handle))
+	};
+	std::thread::sleep(std::time::Duration::from_millis(50));
+	handle.abort();
+	assert_eq!(thread.join().unwrap(), Err(decurse::Cancelled));
+}
+
+#[test]
+fn test_calls() {
+	#[decurse(calls(is_odd))]
+	fn is_even(x: u64) -> bool {
+		if x == 0 {
+			true
+		} else {
+			is_odd(x - 1)
+		}
+	}
+	#[decurse(calls(is_even))]
+	fn is_odd(x: u64) -> bool {
+		if x == 0 {
+			false
+		} else {
+			is_even(x - 1)
+		}
+	}
+	assert!(is_even(1000000));
+	assert!(is_odd(999999));
+	assert!(!is_odd(200000));
+}
+
+#[test]
+fn test_nested_without_calls() {
+	#[decurse]
+	fn length(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			length(x - 1) + 1
+		}
+	}
+	#[decurse]
+	fn sum_of_lengths(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			sum_of_lengths(x - 1) + length(x % 10)
+		}
+	}
+	// Deep enough to overflow the native stack.
+	assert_eq!(sum_of_lengths(100000), 450000);
+}
+
+#[test]
+fn test_calls_paths() {
+	struct Parity;
+	impl Parity {
+		#[decurse(calls(Self::odd))]
+		fn even(x: u64) -> bool {
+			if x == 0 {
+				true
+			} else {
+				Self::odd(x - 1)
+			}
+		}
+		#[decurse(calls(Self::even))]
+		fn odd(x: u64) -> bool {
+			if x == 0 {
+				false
+			} else {
+				Self::even(x - 1)
+			}
+		}
+	}
+	assert!(Parity::even(100000));
+	// Only the whole path is matched, so `Vec::new()` is left alone.
+	#[decurse(calls(new))]
+	fn new(x: u64) -> Vec<u64> {
+		if x == 0 {
+			Vec::new()
+		} else {
+			let mut v = new(x - 1);
+			v.push(x);
+			v
+		}
+	}
+	assert_eq!(new(100000).len(), 100000);
+}
+
+#[test]
+fn test_associated_fn() {
+	struct Triangle;
+	impl Triangle {
+		#[decurse]
+		fn number(x: u64) -> u64 {
+			if x == 0 {
+				0
+			} else {
+				number(x - 1) + x
+			}
+		}
+	}
+	assert_eq!(Triangle::number(100000), 5000050000);
+}
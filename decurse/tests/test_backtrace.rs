@@ -0,0 +1,73 @@
+use decurse::{decurse, try_execute, Backtrace};
+
+#[decurse(debug_args)]
+fn count_down(x: u32) -> u32 {
+	if x == 0 {
+		panic!("reached zero")
+	} else {
+		count_down(x - 1)
+	}
+}
+
+#[test]
+fn test_try_execute() {
+	assert_eq!(try_execute(async { 1 + 1 }).unwrap(), 2);
+	let panicked = try_execute(async { count_down(100000) }).unwrap_err();
+	assert_eq!(panicked.message(), Some("reached zero"));
+	let frames = panicked.backtrace().frames();
+	assert_eq!(frames.len(), 100001);
+	assert_eq!(frames[0].args(), Some("100000"));
+	assert_eq!(frames[100000].args(), Some("0"));
+	// The stack is cleaned up.
+	assert_eq!(decurse::depth(), 0);
+}
+
+#[test]
+fn test_try_execute_formatted_message() {
+	#[decurse]
+	fn fail(x: u32) {
+		if x == 0 {
+			panic!("failed at {}", x)
+		} else {
+			fail(x - 1)
+		}
+	}
+	let panicked = try_execute(async { fail(3) }).unwrap_err();
+	assert_eq!(panicked.message(), Some("failed at 0"));
+	assert_eq!(panicked.backtrace().frames().len(), 4);
+	let payload = panicked.into_payload();
+	assert!(payload.downcast_ref::<String>().is_some());
+}
+
+#[test]
+fn test_display() {
+	#[decurse]
+	fn outer(x: u32) -> String {
+		if x == 0 {
+			inner(2)
+		} else {
+			outer(x - 1)
+		}
+	}
+	#[decurse(debug_args)]
+	fn inner(x: u32) -> String {
+		if x == 0 {
+			Backtrace::capture().to_string()
+		} else {
+			inner(x - 1)
+		}
+	}
+	assert_eq!(
+		outer(9),
+		"decurse logical stack (13 frames):
+      13: test_backtrace::inner(0)
+      12: test_backtrace::inner(1)
+      11: test_backtrace::inner(2)
+      10: test_backtrace::outer
+       9: test_backtrace::outer
+          ... 6 more frames of test_backtrace::outer
+       2: test_backtrace::outer
+       1: test_backtrace::outer
+"
+	);
+}
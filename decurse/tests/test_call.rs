@@ -0,0 +1,98 @@
+use std::{future::Future, pin::Pin, rc::Rc};
+
+use decurse::{call, execute};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T>>>;
+
+#[test]
+fn test_call_async_fn() {
+	async fn triangular(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			call(triangular(x - 1)).await + x
+		}
+	}
+	assert_eq!(execute(triangular(200000)), 20000100000);
+}
+
+#[test]
+fn test_call_callback() {
+	// The recursion goes through a boxed callback, which the macro can never see.
+	struct Counter {
+		step: Box<dyn Fn(Rc<Counter>, u64) -> BoxFuture<u64>>,
+	}
+	let counter = Rc::new(Counter {
+		step: Box::new(|counter, x| {
+			Box::pin(async move {
+				if x == 0 {
+					0
+				} else {
+					let next = (counter.step)(counter.clone(), x - 1);
+					call(next).await + 1
+				}
+			})
+		}),
+	});
+	let fut = (counter.step)(counter.clone(), 500000);
+	assert_eq!(execute(fut), 500000);
+}
+
+#[test]
+fn test_call_from_decursed() {
+	async fn double(x: u64) -> u64 {
+		x * 2
+	}
+	#[decurse::decurse]
+	fn sum_doubles(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			sum_doubles(x - 1) + call(double(x)).await
+		}
+	}
+	assert_eq!(sum_doubles(100000), 10000100000);
+}
+
+#[test]
+fn test_calls_out_of_order() {
+	// Calls can be dropped without being awaited, or made before the earlier ones are awaited.
+	async fn leaves(x: u64) -> u64 {
+		if x == 0 {
+			return 1;
+		}
+		drop(call(leaves(x - 1)));
+		let left = call(leaves(x - 1));
+		let right = call(leaves(x - 1));
+		right.await + left.await
+	}
+	assert_eq!(execute(leaves(12)), 4096);
+}
+
+#[test]
+fn test_call_awaited_elsewhere() {
+	// The call is made in a frame of one type, and awaited in a frame of another.
+	async fn awaiter(call: decurse::Call<u64>) -> u64 {
+		call.await + 1
+	}
+	async fn chain(x: u64) -> u64 {
+		if x == 0 {
+			return 0;
+		}
+		let inner = call(chain(x - 1));
+		call(awaiter(inner)).await
+	}
+	assert_eq!(execute(chain(100000)), 100000);
+}
+
+#[test]
+#[should_panic(expected = "outside of the decurse executor")]
+fn test_call_outside() {
+	struct NoopWaker;
+	impl std::task::Wake for NoopWaker {
+		fn wake(self: std::sync::Arc<Self>) {}
+	}
+	let waker = std::sync::Arc::new(NoopWaker).into();
+	let mut cx = std::task::Context::from_waker(&waker);
+	let _ = Pin::new(&mut call(async { 1 })).poll(&mut cx);
+}
@@ -0,0 +1,84 @@
+use decurse::{decurse, Recurse, Recursion};
+
+#[decurse(detect_cycles)]
+fn walk(next: &'static [usize], node: usize) -> usize {
+	if node == 0 {
+		0
+	} else {
+		walk(next, next[node]) + 1
+	}
+}
+
+#[test]
+fn test_no_cycle() {
+	static NEXT: [usize; 4] = [0, 0, 1, 2];
+	assert_eq!(walk(&NEXT, 3), 3);
+	// The recorded arguments are cleared once the frames finish.
+	assert_eq!(walk(&NEXT, 3), 3);
+}
+
+#[test]
+fn test_deep_no_cycle() {
+	#[decurse(detect_cycles)]
+	fn triangular(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			triangular(x - 1) + x
+		}
+	}
+	assert_eq!(triangular(100000), 5000050000);
+}
+
+#[test]
+fn test_repeat_after_return() {
+	// The same arguments in sibling calls are not a cycle.
+	#[decurse(detect_cycles)]
+	fn fibonacci(x: u64) -> u64 {
+		if x == 0 || x == 1 {
+			1
+		} else {
+			fibonacci(x - 1) + fibonacci(x - 2)
+		}
+	}
+	assert_eq!(fibonacci(15), 987);
+}
+
+#[test]
+#[should_panic(
+	expected = "infinite recursion detected, arguments repeat: ([0, 3, 1, 2], 1) -> ([0, 3, 1, 2], 3) -> ([0, 3, 1, 2], 2) -> ([0, 3, 1, 2], 1)"
+)]
+fn test_cycle() {
+	static NEXT: [usize; 4] = [0, 3, 1, 2];
+	walk(&NEXT, 1);
+}
+
+#[test]
+#[should_panic(expected = "arguments repeat: 5 -> 6 -> 7 -> 5")]
+fn test_recursion_detect_cycles() {
+	async fn step(rec: Recurse<u32, u32>, x: u32) -> u32 {
+		rec.call(if x == 7 { 5 } else { x + 1 }).await
+	}
+	Recursion::new(step).detect_cycles().run(5);
+}
+
+#[decurse(detect_cycles, calls(bounce_back))]
+fn bounce(x: u32) -> u32 {
+	if x == 0 {
+		0
+	} else {
+		bounce_back(x - 1) + 1
+	}
+}
+
+#[decurse(detect_cycles, calls(bounce))]
+fn bounce_back(x: u32) -> u32 {
+	// Comes back to 2 from 0, so `bounce(2)` calls itself again through `bounce_back`.
+	bounce(if x == 0 { 2 } else { x })
+}
+
+#[test]
+#[should_panic(expected = "arguments repeat: (2,) -> (1,) -> (2,)")]
+fn test_mutual_cycle() {
+	bounce(2);
+}
@@ -0,0 +1,239 @@
+use std::{
+	cell::RefCell,
+	collections::{BTreeMap, HashMap},
+	marker::PhantomData,
+	rc::{Rc, Weak},
+};
+
+use decurse::{DeepClone, DeepDrop};
+
+mod common;
+use common::{list, Node, DEEP};
+
+fn values<T: Clone>(mut list: &Option<Box<Node<T>>>) -> Vec<T> {
+	let mut values = Vec::new();
+	while let Some(node) = list {
+		values.push(node.value.clone());
+		list = &node.next;
+	}
+	values
+}
+
+#[test]
+fn test_list() {
+	let list = list((0..DEEP).map(|value| value.to_string()));
+	let cloned = list.clone();
+	assert_eq!(values(&cloned), values(&list));
+	// The clone owns its nodes.
+	let first = cloned.as_ref().unwrap();
+	assert!(!std::ptr::eq(&**first, &**list.as_ref().unwrap()));
+}
+
+#[derive(DeepClone, DeepDrop, Default, Debug, PartialEq)]
+enum Expr {
+	#[default]
+	Zero,
+	Num(i64),
+	Add(Box<Expr>, Box<Expr>),
+	Neg {
+		inner: Box<Expr>,
+	},
+}
+
+fn eval(expr: &Expr) -> i64 {
+	// Iterative, so it can check deep expressions.
+	let mut stack = vec![(expr, 1)];
+	let mut total = 0;
+	while let Some((expr, sign)) = stack.pop() {
+		match expr {
+			Expr::Zero => {}
+			Expr::Num(n) => total += sign * n,
+			Expr::Add(a, b) => {
+				stack.push((a, sign));
+				stack.push((b, sign));
+			}
+			Expr::Neg { inner } => stack.push((inner, -sign)),
+		}
+	}
+	total
+}
+
+#[test]
+fn test_variants() {
+	let mut expr = Expr::Num(1);
+	for i in 0..DEEP {
+		expr = match i % 3 {
+			0 => Expr::Add(Box::new(expr), Box::new(Expr::Num(2))),
+			1 => Expr::Neg {
+				inner: Box::new(expr),
+			},
+			_ => Expr::Add(Box::new(Expr::Zero), Box::new(expr)),
+		};
+	}
+	let cloned = expr.clone();
+	assert_eq!(eval(&cloned), eval(&expr));
+	let small = Expr::Add(Box::new(Expr::Num(3)), Box::new(Expr::Zero));
+	assert_eq!(small.clone(), small);
+}
+
+#[derive(DeepClone, DeepDrop)]
+enum Json {
+	Null,
+	Number(f64),
+	Array(Vec<Json>),
+	Object(BTreeMap<String, Json>),
+	Pairs(Vec<(String, Json)>),
+}
+
+fn count(json: &Json) -> usize {
+	let mut stack = vec![json];
+	let mut count = 0;
+	while let Some(json) = stack.pop() {
+		count += 1;
+		match json {
+			Json::Null | Json::Number(_) => {}
+			Json::Array(items) => stack.extend(items),
+			Json::Object(object) => stack.extend(object.values()),
+			Json::Pairs(pairs) => stack.extend(pairs.iter().map(|(_, json)| json)),
+		}
+	}
+	count
+}
+
+#[test]
+fn test_collections() {
+	let mut json = Json::Null;
+	for i in 0..DEEP {
+		json = match i % 3 {
+			0 => Json::Array(vec![json, Json::Number(i as f64)]),
+			1 => {
+				let mut object = BTreeMap::new();
+				object.insert("inner".to_string(), json);
+				object.insert("null".to_string(), Json::Null);
+				Json::Object(object)
+			}
+			_ => Json::Pairs(vec![("inner".to_string(), json)]),
+		};
+	}
+	json = Json::Array(vec![json, Json::Number(-1.0)]);
+	let cloned = json.clone();
+	assert_eq!(count(&cloned), count(&json));
+	match &cloned {
+		Json::Array(items) => {
+			assert!(matches!(items[1], Json::Number(n) if n == -1.0))
+		}
+		_ => panic!("expected an array"),
+	}
+}
+
+#[derive(DeepClone)]
+struct Shared {
+	data: Rc<Vec<u32>>,
+	children: HashMap<u32, Shared>,
+}
+
+#[test]
+fn test_rc_shared() {
+	let data = Rc::new(vec![1, 2, 3]);
+	let mut children = HashMap::new();
+	children.insert(
+		0,
+		Shared {
+			data: data.clone(),
+			children: HashMap::new(),
+		},
+	);
+	let shared = Shared { data, children };
+	let cloned = shared.clone();
+	assert!(Rc::ptr_eq(&cloned.data, &shared.data));
+	assert!(Rc::ptr_eq(&cloned.children[&0].data, &shared.data));
+	assert_eq!(Rc::strong_count(&shared.data), 4);
+}
+
+#[derive(DeepClone, DeepDrop)]
+struct Statement {
+	#[deep]
+	body: Option<Box<Block>>,
+	line: u32,
+}
+
+#[derive(DeepClone, DeepDrop)]
+struct Block {
+	#[deep]
+	statements: Vec<Statement>,
+}
+
+#[test]
+fn test_mutual() {
+	let mut statement = Statement {
+		body: None,
+		line: 0,
+	};
+	for line in 1..DEEP as u32 {
+		statement = Statement {
+			body: Some(Box::new(Block {
+				statements: vec![statement],
+			})),
+			line,
+		};
+	}
+	let cloned = statement.clone();
+	let mut lines = Vec::new();
+	let mut current = Some(&cloned);
+	while let Some(statement) = current {
+		lines.push(statement.line);
+		current = statement
+			.body
+			.as_ref()
+			.and_then(|block| block.statements.first());
+	}
+	assert_eq!(lines.len(), DEEP);
+	assert_eq!(lines[0], DEEP as u32 - 1);
+}
+
+#[derive(DeepClone, Debug, PartialEq)]
+struct Unit;
+
+#[derive(DeepClone, Debug, PartialEq)]
+struct Pair(u32, Option<Box<Pair>>);
+
+#[test]
+fn test_shapes() {
+	assert_eq!(Unit.clone(), Unit);
+	let pair = Pair(1, Some(Box::new(Pair(2, None))));
+	assert_eq!(pair.clone(), pair);
+}
+
+#[derive(DeepClone, DeepDrop)]
+struct Linked {
+	next: Option<Box<Linked>>,
+	// Cloned as a whole, sharing the parent.
+	parent: RefCell<Weak<Linked>>,
+	// Cloned with `Clone`, not level by level.
+	#[deep(skip)]
+	marker: PhantomData<Linked>,
+}
+
+#[test]
+fn test_not_children() {
+	let root = Rc::new(Linked {
+		next: None,
+		parent: RefCell::new(Weak::new()),
+		marker: PhantomData,
+	});
+	let mut list = None;
+	for _ in 0..DEEP {
+		list = Some(Box::new(Linked {
+			next: list,
+			parent: RefCell::new(Rc::downgrade(&root)),
+			marker: PhantomData,
+		}));
+	}
+	let cloned = list.clone().unwrap();
+	assert!(Rc::ptr_eq(
+		&cloned.parent.borrow().upgrade().unwrap(),
+		&root
+	));
+	assert_eq!(cloned.marker, PhantomData);
+	assert_eq!(Rc::weak_count(&root), DEEP * 2);
+}
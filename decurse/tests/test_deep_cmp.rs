@@ -0,0 +1,197 @@
+use std::{
+	cmp::Ordering,
+	collections::{BTreeMap, HashMap},
+	rc::Rc,
+};
+
+use decurse::{DeepDrop, DeepEq, DeepOrd, DeepPartialEq, DeepPartialOrd};
+
+mod common;
+use common::{list, DEEP};
+
+#[test]
+fn test_list() {
+	let a = list(0..DEEP as u32);
+	let b = list(0..DEEP as u32);
+	assert_eq!(a, b);
+	assert_eq!(a.cmp(&b), Ordering::Equal);
+	// The last difference is at the bottom.
+	let c = list((0..DEEP as u32).map(|v| if v == 0 { 1 } else { v }));
+	assert_ne!(a, c);
+	assert!(a < c);
+	assert_eq!(c.partial_cmp(&a), Some(Ordering::Greater));
+	// A prefix is less.
+	let d = list(1..DEEP as u32);
+	assert!(d < a);
+}
+
+// The same type twice, to check the results against the std derives.
+#[derive(DeepPartialEq, DeepPartialOrd, Debug, Clone)]
+enum Deep {
+	Num(f64),
+	Neg(Box<Deep>),
+	List(Vec<Deep>),
+	Pair {
+		left: Box<Deep>,
+		right: Option<Rc<Deep>>,
+	},
+	Map(BTreeMap<u8, Deep>),
+	Tuple((u8, Box<Deep>)),
+}
+
+#[derive(PartialEq, PartialOrd, Debug)]
+enum Std {
+	Num(f64),
+	Neg(Box<Std>),
+	List(Vec<Std>),
+	Pair {
+		left: Box<Std>,
+		right: Option<Rc<Std>>,
+	},
+	Map(BTreeMap<u8, Std>),
+	Tuple((u8, Box<Std>)),
+}
+
+impl Deep {
+	fn to_std(&self) -> Std {
+		match self {
+			Deep::Num(n) => Std::Num(*n),
+			Deep::Neg(inner) => Std::Neg(Box::new(inner.to_std())),
+			Deep::List(items) => Std::List(items.iter().map(Deep::to_std).collect()),
+			Deep::Pair { left, right } => Std::Pair {
+				left: Box::new(left.to_std()),
+				right: right.as_ref().map(|right| Rc::new(right.to_std())),
+			},
+			Deep::Map(map) => Std::Map(map.iter().map(|(k, v)| (*k, v.to_std())).collect()),
+			Deep::Tuple((n, inner)) => Std::Tuple((*n, Box::new(inner.to_std()))),
+		}
+	}
+}
+
+fn samples() -> Vec<Deep> {
+	let num = |n| Deep::Num(n);
+	let map = |items: &[(u8, Deep)]| Deep::Map(items.iter().cloned().collect());
+	vec![
+		num(0.0),
+		num(1.0),
+		num(f64::NAN),
+		Deep::Neg(Box::new(num(1.0))),
+		Deep::Neg(Box::new(num(f64::NAN))),
+		Deep::List(vec![]),
+		Deep::List(vec![num(1.0)]),
+		Deep::List(vec![num(1.0), num(2.0)]),
+		Deep::List(vec![num(2.0)]),
+		Deep::List(vec![num(f64::NAN), num(2.0)]),
+		Deep::Pair {
+			left: Box::new(num(1.0)),
+			right: None,
+		},
+		Deep::Pair {
+			left: Box::new(num(1.0)),
+			right: Some(Rc::new(num(0.0))),
+		},
+		Deep::Pair {
+			left: Box::new(num(0.0)),
+			right: Some(Rc::new(num(3.0))),
+		},
+		map(&[]),
+		map(&[(1, num(1.0))]),
+		map(&[(1, num(2.0))]),
+		map(&[(2, num(0.0))]),
+		map(&[(1, num(1.0)), (2, num(0.0))]),
+		Deep::Tuple((1, Box::new(num(1.0)))),
+		Deep::Tuple((1, Box::new(num(2.0)))),
+		Deep::Tuple((0, Box::new(num(3.0)))),
+	]
+}
+
+#[test]
+fn test_same_as_std() {
+	let samples = samples();
+	for a in &samples {
+		for b in &samples {
+			let (std_a, std_b) = (a.to_std(), b.to_std());
+			assert_eq!(a == b, std_a == std_b, "{:?} == {:?}", a, b);
+			assert_eq!(
+				a.partial_cmp(b),
+				std_a.partial_cmp(&std_b),
+				"{:?} cmp {:?}",
+				a,
+				b
+			);
+		}
+	}
+}
+
+#[derive(DeepPartialEq, DeepEq, DeepPartialOrd, DeepOrd, Debug)]
+#[repr(i32)]
+enum Discriminants {
+	A = 5,
+	B = 1,
+	C,
+	D(Box<Discriminants>),
+}
+
+#[test]
+fn test_discriminants() {
+	use Discriminants::*;
+	// Ordered by the discriminants, not the order of declaration.
+	assert!(B < C);
+	assert!(C < D(Box::new(A)));
+	assert!(D(Box::new(A)) < A);
+	assert!(D(Box::new(B)) < D(Box::new(A)));
+	assert_eq!(D(Box::new(C)).cmp(&D(Box::new(C))), Ordering::Equal);
+}
+
+#[derive(DeepPartialEq, DeepEq, DeepDrop)]
+struct Tree {
+	children: HashMap<String, Tree>,
+}
+
+fn tree(depth: usize, name: &str) -> Tree {
+	let mut tree = Tree {
+		children: HashMap::new(),
+	};
+	for _ in 0..depth {
+		let mut children = HashMap::new();
+		children.insert(name.to_string(), tree);
+		children.insert(
+			"leaf".to_string(),
+			Tree {
+				children: HashMap::new(),
+			},
+		);
+		tree = Tree { children };
+	}
+	tree
+}
+
+#[test]
+fn test_hash_map() {
+	assert!(tree(DEEP, "child") == tree(DEEP, "child"));
+	assert!(tree(DEEP, "child") != tree(DEEP, "other"));
+	assert!(tree(3, "child") != tree(4, "child"));
+}
+
+#[derive(DeepPartialEq, DeepPartialOrd, Debug)]
+struct Unit;
+
+#[derive(DeepPartialEq, DeepPartialOrd, Debug)]
+enum Empty {}
+
+#[test]
+fn test_shapes() {
+	assert_eq!(Unit, Unit);
+	assert_eq!(Unit.partial_cmp(&Unit), Some(Ordering::Equal));
+	let _ = |empty: &Empty| empty == empty;
+}
+
+#[derive(DeepPartialEq, DeepPartialOrd, Debug)]
+struct Shared(Rc<Vec<Shared>>);
+
+#[test]
+fn test_collection_behind_rc() {
+	let leaf = || Shared(Rc::new(Vec::new()));
+	assert_eq!(Shared(Rc::new(vec![leaf()])), Shared(Rc::new(vec![leaf()])));
+	assert!(Shared(Rc::new(vec![])) < Shared(Rc::new(vec![leaf()])));
+}
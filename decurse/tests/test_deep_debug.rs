@@ -0,0 +1,139 @@
+use std::{collections::BTreeMap, rc::Rc};
+
+use decurse::DeepDebug;
+
+mod common;
+use common::{list, DEEP};
+
+#[test]
+fn test_list() {
+	let list = list(0..DEEP);
+	let printed = format!("{:?}", list);
+	assert!(printed.starts_with("Some(Node { value: 199999, next: Some(Node { value: 199998, "));
+	assert!(printed.contains("{ value: 0, next: None })"));
+	assert_eq!(printed.matches(" })").count(), DEEP);
+}
+
+#[derive(DeepDebug, Clone)]
+enum Deep {
+	Leaf,
+	Empty {},
+	Unit(),
+	Num(u32),
+	Neg(Box<Deep>),
+	List(Vec<Deep>),
+	Pair {
+		left: Rc<Deep>,
+		r#type: Option<Box<Deep>>,
+		data: Vec<u32>,
+	},
+	Map(BTreeMap<String, Deep>),
+	Tuple((u8, Box<Deep>), (Box<Deep>,)),
+}
+
+// Only printed.
+#[allow(dead_code)]
+#[derive(Debug)]
+enum Std {
+	Leaf,
+	Empty {},
+	Unit(),
+	Num(u32),
+	Neg(Box<Std>),
+	List(Vec<Std>),
+	Pair {
+		left: Rc<Std>,
+		r#type: Option<Box<Std>>,
+		data: Vec<u32>,
+	},
+	Map(BTreeMap<String, Std>),
+	Tuple((u8, Box<Std>), (Box<Std>,)),
+}
+
+impl Deep {
+	fn to_std(&self) -> Std {
+		match self {
+			Deep::Leaf => Std::Leaf,
+			Deep::Empty {} => Std::Empty {},
+			Deep::Unit() => Std::Unit(),
+			Deep::Num(n) => Std::Num(*n),
+			Deep::Neg(inner) => Std::Neg(Box::new(inner.to_std())),
+			Deep::List(items) => Std::List(items.iter().map(Deep::to_std).collect()),
+			Deep::Pair { left, r#type, data } => Std::Pair {
+				left: Rc::new(left.to_std()),
+				r#type: r#type.as_ref().map(|t| Box::new(t.to_std())),
+				data: data.clone(),
+			},
+			Deep::Map(map) => Std::Map(map.iter().map(|(k, v)| (k.clone(), v.to_std())).collect()),
+			Deep::Tuple((n, a), (b,)) => {
+				Std::Tuple((*n, Box::new(a.to_std())), (Box::new(b.to_std()),))
+			}
+		}
+	}
+}
+
+#[test]
+fn test_same_as_std() {
+	let mut map = BTreeMap::new();
+	map.insert("a".to_string(), Deep::Num(1));
+	map.insert("b".to_string(), Deep::Neg(Box::new(Deep::Leaf)));
+	let samples = vec![
+		Deep::Leaf,
+		Deep::Empty {},
+		Deep::Unit(),
+		Deep::Num(3),
+		Deep::Neg(Box::new(Deep::Num(4))),
+		Deep::List(Vec::new()),
+		Deep::List(vec![Deep::Leaf, Deep::Num(1)]),
+		Deep::Pair {
+			left: Rc::new(Deep::Leaf),
+			r#type: None,
+			data: vec![1, 2],
+		},
+		Deep::Pair {
+			left: Rc::new(Deep::Num(2)),
+			r#type: Some(Box::new(Deep::List(vec![Deep::Num(5)]))),
+			data: Vec::new(),
+		},
+		Deep::Map(BTreeMap::new()),
+		Deep::Map(map),
+		Deep::Tuple((7, Box::new(Deep::Leaf)), (Box::new(Deep::Num(8)),)),
+	];
+	let nested = Deep::List(samples.clone());
+	for sample in samples.iter().chain([&nested]) {
+		let std = sample.to_std();
+		assert_eq!(format!("{:?}", sample), format!("{:?}", std));
+		assert_eq!(format!("{:#?}", sample), format!("{:#?}", std));
+		assert_eq!(format!("{:5?}", sample), format!("{:5?}", std));
+	}
+	// Inside something printed by the std.
+	assert_eq!(
+		format!("{:#?}", Some(&nested)),
+		format!("{:#?}", Some(nested.to_std()))
+	);
+}
+
+#[derive(DeepDebug)]
+struct Generic<T> {
+	value: T,
+	children: Vec<Generic<T>>,
+}
+
+#[derive(DeepDebug)]
+struct Unit;
+
+#[test]
+fn test_shapes() {
+	let generic = Generic {
+		value: "a",
+		children: vec![Generic {
+			value: "b",
+			children: Vec::new(),
+		}],
+	};
+	assert_eq!(
+		format!("{:?}", generic),
+		r#"Generic { value: "a", children: [Generic { value: "b", children: [] }] }"#
+	);
+	assert_eq!(format!("{:?}", Unit), "Unit");
+}
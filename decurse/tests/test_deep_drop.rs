@@ -0,0 +1,311 @@
+use std::{
+	cell::{Cell, RefCell},
+	collections::BTreeMap,
+	marker::PhantomData,
+	rc::{Rc, Weak},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc,
+	},
+};
+
+use decurse::DeepDrop;
+
+mod common;
+use common::{list, DEEP};
+
+#[test]
+fn test_list() {
+	let list = list(0..DEEP);
+	assert_eq!(list.as_ref().map(|node| node.value), Some(DEEP - 1));
+	drop(list);
+}
+
+struct Counted(Rc<Cell<usize>>);
+
+impl Drop for Counted {
+	fn drop(&mut self) {
+		self.0.set(self.0.get() + 1);
+	}
+}
+
+#[test]
+fn test_generic_drops_everything() {
+	let count = Rc::new(Cell::new(0));
+	let list = list((0..DEEP).map(|_| Counted(count.clone())));
+	drop(list);
+	assert_eq!(count.get(), DEEP);
+}
+
+#[derive(DeepDrop)]
+enum Expr {
+	Num(i64),
+	Add(Box<Expr>, Box<Expr>),
+	Neg(Box<Expr>),
+}
+
+#[test]
+fn test_boxed_in_place() {
+	let mut expr = Expr::Num(1);
+	for i in 0..DEEP {
+		expr = if i % 2 == 0 {
+			Expr::Add(Box::new(expr), Box::new(Expr::Num(2)))
+		} else {
+			Expr::Neg(Box::new(expr))
+		};
+	}
+	assert!(matches!(&expr, Expr::Neg(inner) if matches!(**inner, Expr::Add(..))));
+	drop(expr);
+	assert!(matches!(Expr::Num(1), Expr::Num(n) if n == 1));
+}
+
+#[derive(DeepDrop)]
+struct Tree {
+	children: Vec<Tree>,
+}
+
+#[test]
+fn test_vec() {
+	let mut tree = Tree {
+		children: Vec::new(),
+	};
+	for _ in 0..DEEP {
+		tree = Tree {
+			children: vec![
+				tree,
+				Tree {
+					children: Vec::new(),
+				},
+			],
+		};
+	}
+	drop(tree);
+}
+
+#[derive(DeepDrop)]
+enum Json {
+	Null,
+	Array(Vec<Json>),
+	Object(BTreeMap<String, Json>),
+}
+
+#[test]
+fn test_map() {
+	let mut json = Json::Null;
+	for i in 0..DEEP {
+		json = if i % 2 == 0 {
+			Json::Array(vec![json, Json::Null])
+		} else {
+			let mut object = BTreeMap::new();
+			object.insert("inner".to_string(), json);
+			Json::Object(object)
+		};
+	}
+	drop(json);
+}
+
+#[derive(DeepDrop)]
+struct Shared {
+	next: Option<Rc<Shared>>,
+	count: Rc<Cell<usize>>,
+}
+
+struct Counter(Arc<AtomicUsize>);
+
+impl Drop for Counter {
+	fn drop(&mut self) {
+		self.0.fetch_add(1, Ordering::Relaxed);
+	}
+}
+
+#[derive(DeepDrop)]
+struct Threaded {
+	next: Option<Arc<Threaded>>,
+	_counter: Counter,
+}
+
+#[test]
+fn test_rc() {
+	let count = Rc::new(Cell::new(0));
+	let mut list = None;
+	for _ in 0..DEEP {
+		list = Some(Rc::new(Shared {
+			next: list,
+			count: count.clone(),
+		}));
+	}
+	let list = list.unwrap();
+	// Keep the middle of the list alive.
+	let mut middle = list.clone();
+	for _ in 0..DEEP / 2 {
+		middle = middle.next.clone().unwrap();
+	}
+	drop(list);
+	assert_eq!(Rc::strong_count(&middle), 1);
+	assert_eq!(middle.count.get(), 0);
+	drop(middle);
+
+	let counted = Arc::new(AtomicUsize::new(0));
+	let mut list = None;
+	for _ in 0..DEEP {
+		list = Some(Arc::new(Threaded {
+			next: list,
+			_counter: Counter(counted.clone()),
+		}));
+	}
+	drop(list);
+	assert_eq!(counted.load(Ordering::Relaxed), DEEP);
+}
+
+// Mutual recursion goes through another type, so it has to be marked.
+#[derive(DeepDrop)]
+struct Statement {
+	#[deep]
+	body: Vec<Block>,
+}
+
+#[derive(DeepDrop)]
+struct Block {
+	#[deep]
+	statements: Vec<Statement>,
+	_name: String,
+}
+
+#[test]
+fn test_mutual() {
+	let mut statement = Statement { body: Vec::new() };
+	for _ in 0..DEEP {
+		statement = Statement {
+			body: vec![Block {
+				statements: vec![statement],
+				_name: "block".to_string(),
+			}],
+		};
+	}
+	drop(statement);
+}
+
+#[derive(DeepDrop)]
+struct Tuple(u32, Option<(String, Box<Tuple>)>);
+
+#[test]
+fn test_tuple() {
+	let mut tuple = Tuple(0, None);
+	for i in 0..DEEP {
+		tuple = Tuple(i as u32, Some((i.to_string(), Box::new(tuple))));
+	}
+	assert_eq!(tuple.0, DEEP as u32 - 1);
+	drop(tuple);
+}
+
+#[derive(DeepDrop)]
+enum Counting {
+	Leaf(Counted),
+	Pair(Box<Counting>, Counted, Box<Counting>),
+	Shared(Rc<Counting>),
+}
+
+#[test]
+fn test_in_place_drops_everything() {
+	let count = Rc::new(Cell::new(0));
+	let counted = || Counted(count.clone());
+	let mut expr = Counting::Leaf(counted());
+	let mut created = 1;
+	let mut kept = None;
+	for i in 0..DEEP {
+		expr = match i % 3 {
+			0 => Counting::Pair(
+				Box::new(expr),
+				counted(),
+				Box::new(Counting::Leaf(counted())),
+			),
+			1 => Counting::Pair(
+				Box::new(Counting::Leaf(counted())),
+				counted(),
+				Box::new(expr),
+			),
+			_ => {
+				let shared = Rc::new(expr);
+				if kept.is_none() && i >= DEEP / 2 {
+					kept = Some((shared.clone(), created));
+				}
+				Counting::Shared(shared)
+			}
+		};
+		if i % 3 != 2 {
+			created += 2;
+		}
+	}
+	drop(expr);
+	let (kept, below) = kept.unwrap();
+	assert_eq!(Rc::strong_count(&kept), 1);
+	assert_eq!(count.get(), created - below);
+	drop(kept);
+	assert_eq!(count.get(), created);
+}
+
+#[derive(DeepDrop)]
+struct Parent {
+	children: Vec<Rc<Parent>>,
+	// Not owned, so not a child.
+	parent: RefCell<Weak<Parent>>,
+	// Not a `DeepDrop`, so it has to be skipped.
+	#[deep(skip)]
+	_marker: PhantomData<Parent>,
+}
+
+#[test]
+fn test_weak_and_skip() {
+	let mut node = Rc::new(Parent {
+		children: Vec::new(),
+		parent: RefCell::new(Weak::new()),
+		_marker: PhantomData,
+	});
+	let leaf = Rc::downgrade(&node);
+	for _ in 0..DEEP {
+		let child = node;
+		node = Rc::new(Parent {
+			children: vec![child.clone()],
+			parent: RefCell::new(Weak::new()),
+			_marker: PhantomData,
+		});
+		*child.parent.borrow_mut() = Rc::downgrade(&node);
+	}
+	assert!(leaf.upgrade().is_some());
+	drop(node);
+	assert!(leaf.upgrade().is_none());
+}
+
+#[derive(DeepDrop)]
+struct Celled {
+	next: RefCell<Option<Box<Celled>>>,
+	shared: Option<Rc<Cell<Option<Box<Celled>>>>>,
+	_counted: Counted,
+}
+
+#[test]
+fn test_cells() {
+	let count = Rc::new(Cell::new(0));
+	let mut list = Celled {
+		next: RefCell::new(None),
+		shared: None,
+		_counted: Counted(count.clone()),
+	};
+	for i in 0..DEEP {
+		list = if i % 2 == 0 {
+			Celled {
+				next: RefCell::new(Some(Box::new(list))),
+				shared: None,
+				_counted: Counted(count.clone()),
+			}
+		} else {
+			Celled {
+				next: RefCell::new(None),
+				shared: Some(Rc::new(Cell::new(Some(Box::new(list))))),
+				_counted: Counted(count.clone()),
+			}
+		};
+	}
+	drop(list);
+	assert_eq!(count.get(), DEEP + 1);
+}
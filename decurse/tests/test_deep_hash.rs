@@ -0,0 +1,166 @@
+use std::{
+	collections::{hash_map::DefaultHasher, BTreeMap, HashSet},
+	hash::{Hash, Hasher},
+	rc::Rc,
+};
+
+use decurse::DeepHash;
+
+mod common;
+use common::{list, DEEP};
+
+fn hash(value: &impl Hash) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	value.hash(&mut hasher);
+	hasher.finish()
+}
+
+#[test]
+fn test_list() {
+	assert_eq!(hash(&list(0..DEEP as u32)), hash(&list(0..DEEP as u32)));
+	assert_ne!(hash(&list(0..DEEP as u32)), hash(&list(0..DEEP as u32 - 1)));
+	let mut set = HashSet::new();
+	set.insert(list(0..DEEP as u32));
+	assert!(set.contains(&list(0..DEEP as u32)));
+	assert!(!set.contains(&list(0..3)));
+}
+
+// Records what it's fed, to check it against the std derives.
+#[derive(Default)]
+struct Recorder(Vec<String>);
+
+impl Hasher for Recorder {
+	fn finish(&self) -> u64 {
+		0
+	}
+	fn write(&mut self, bytes: &[u8]) {
+		self.0.push(format!("{:?}", bytes));
+	}
+	fn write_u8(&mut self, i: u8) {
+		self.0.push(format!("{}u8", i));
+	}
+	fn write_u32(&mut self, i: u32) {
+		self.0.push(format!("{}u32", i));
+	}
+	fn write_usize(&mut self, i: usize) {
+		self.0.push(format!("{}usize", i));
+	}
+	fn write_isize(&mut self, i: isize) {
+		self.0.push(format!("{}isize", i));
+	}
+}
+
+fn record(value: &impl Hash) -> Vec<String> {
+	let mut recorder = Recorder::default();
+	value.hash(&mut recorder);
+	recorder.0
+}
+
+#[derive(DeepHash, Clone)]
+enum Deep {
+	Leaf,
+	Num(u32),
+	Neg(Box<Deep>),
+	List(Vec<Deep>),
+	Pair {
+		left: Rc<Deep>,
+		right: Option<Box<Deep>>,
+	},
+	Map(BTreeMap<String, Deep>),
+	Tuple((u8, Box<Deep>)),
+}
+
+#[derive(Hash)]
+enum Std {
+	Leaf,
+	Num(u32),
+	Neg(Box<Std>),
+	List(Vec<Std>),
+	Pair {
+		left: Rc<Std>,
+		right: Option<Box<Std>>,
+	},
+	Map(BTreeMap<String, Std>),
+	Tuple((u8, Box<Std>)),
+}
+
+impl Deep {
+	fn to_std(&self) -> Std {
+		match self {
+			Deep::Leaf => Std::Leaf,
+			Deep::Num(n) => Std::Num(*n),
+			Deep::Neg(inner) => Std::Neg(Box::new(inner.to_std())),
+			Deep::List(items) => Std::List(items.iter().map(Deep::to_std).collect()),
+			Deep::Pair { left, right } => Std::Pair {
+				left: Rc::new(left.to_std()),
+				right: right.as_ref().map(|right| Box::new(right.to_std())),
+			},
+			Deep::Map(map) => Std::Map(map.iter().map(|(k, v)| (k.clone(), v.to_std())).collect()),
+			Deep::Tuple((n, inner)) => Std::Tuple((*n, Box::new(inner.to_std()))),
+		}
+	}
+}
+
+#[test]
+fn test_same_as_std() {
+	let mut map = BTreeMap::new();
+	map.insert("a".to_string(), Deep::Num(1));
+	map.insert("b".to_string(), Deep::Neg(Box::new(Deep::Leaf)));
+	let samples = vec![
+		Deep::Leaf,
+		Deep::Num(3),
+		Deep::Neg(Box::new(Deep::Num(4))),
+		Deep::List(vec![Deep::Leaf, Deep::Num(1)]),
+		Deep::Pair {
+			left: Rc::new(Deep::Leaf),
+			right: None,
+		},
+		Deep::Pair {
+			left: Rc::new(Deep::Num(2)),
+			right: Some(Box::new(Deep::List(Vec::new()))),
+		},
+		Deep::Map(map),
+		Deep::Tuple((7, Box::new(Deep::Leaf))),
+	];
+	for sample in &samples {
+		assert_eq!(record(sample), record(&sample.to_std()));
+	}
+	let nested = Deep::List(samples.clone());
+	assert_eq!(record(&nested), record(&nested.to_std()));
+}
+
+#[derive(DeepHash)]
+#[repr(u8)]
+enum Repr {
+	A = 3,
+	B,
+	C(Box<Repr>) = 9,
+}
+
+#[derive(Hash)]
+#[repr(u8)]
+enum StdRepr {
+	A = 3,
+	B,
+	C(Box<StdRepr>) = 9,
+}
+
+#[derive(DeepHash)]
+struct Single(Option<Box<Single>>);
+
+#[derive(Hash)]
+struct StdSingle(Option<Box<StdSingle>>);
+
+#[test]
+fn test_discriminants() {
+	assert_eq!(record(&Repr::A), record(&StdRepr::A));
+	assert_eq!(record(&Repr::B), record(&StdRepr::B));
+	assert_eq!(
+		record(&Repr::C(Box::new(Repr::B))),
+		record(&StdRepr::C(Box::new(StdRepr::B)))
+	);
+	assert_eq!(
+		record(&Single(Some(Box::new(Single(None))))),
+		record(&StdSingle(Some(Box::new(StdSingle(None)))))
+	);
+}
@@ -0,0 +1,228 @@
+use std::{
+	cell::Cell,
+	panic::{self, AssertUnwindSafe},
+	rc::Rc,
+};
+
+use decurse::{call, decurse, generator, yield_item, Generator};
+
+struct Tree {
+	value: u32,
+	children: Vec<Rc<Tree>>,
+}
+
+fn tree(depth: u32, width: u32, next: &mut u32) -> Rc<Tree> {
+	let value = *next;
+	*next += 1;
+	let children = if depth == 0 {
+		Vec::new()
+	} else {
+		(0..width).map(|_| tree(depth - 1, width, next)).collect()
+	};
+	Rc::new(Tree { value, children })
+}
+
+#[generator]
+fn preorder(node: Rc<Tree>) -> Generator<u32> {
+	yield_item!(node.value);
+	for child in node.children.iter() {
+		preorder(child.clone());
+	}
+}
+
+#[test]
+fn test_preorder() {
+	let root = tree(3, 3, &mut 0);
+	let values: Vec<u32> = preorder(root).collect();
+	assert_eq!(values, (0..40).collect::<Vec<_>>());
+}
+
+#[generator]
+fn count_down(x: u32) -> Generator<u32> {
+	if x > 0 {
+		count_down(x - 1);
+	}
+	yield_item!(x);
+}
+
+#[test]
+fn test_deep() {
+	let mut generator = count_down(200000);
+	assert_eq!(generator.next(), Some(0));
+	assert_eq!(generator.next(), Some(1));
+	assert_eq!(generator.last(), Some(200000));
+}
+
+#[generator]
+fn naturals(x: u64, polled: Rc<Cell<u64>>) -> Generator<u64> {
+	polled.set(x);
+	yield_item!(x);
+	naturals(x + 1, polled);
+}
+
+#[test]
+fn test_lazy() {
+	let polled = Rc::new(Cell::new(0));
+	let mut generator = naturals(0, polled.clone());
+	assert_eq!(polled.get(), 0);
+	let first: Vec<u64> = generator.by_ref().take(5).collect();
+	assert_eq!(first, [0, 1, 2, 3, 4]);
+	assert_eq!(polled.get(), 4);
+	assert_eq!(generator.next(), Some(5));
+}
+
+#[generator(calls(leaves))]
+fn labelled(node: Rc<Tree>) -> Generator<u32> {
+	yield_item!(node.value * 100);
+	leaves(node);
+}
+
+#[generator(calls(labelled))]
+fn leaves(node: Rc<Tree>) -> Generator<u32> {
+	if node.children.is_empty() {
+		yield_item!(node.value);
+	}
+	for child in node.children.iter() {
+		labelled(child.clone());
+	}
+}
+
+#[test]
+fn test_calls_other_generator() {
+	let root = tree(2, 2, &mut 0);
+	let values: Vec<u32> = leaves(root).collect();
+	assert_eq!(values, [100, 200, 2, 300, 3, 400, 500, 5, 600, 6]);
+}
+
+#[decurse]
+fn sum_of(x: u32) -> u32 {
+	count_down(x).sum()
+}
+
+#[generator]
+fn sums(x: u32) -> Generator<u32> {
+	yield_item!(sum_of(x));
+	if x > 0 {
+		sums(x - 1);
+	}
+}
+
+#[test]
+fn test_nested() {
+	let values: Vec<u32> = sums(4).collect();
+	assert_eq!(values, [10, 6, 3, 1, 0]);
+}
+
+#[generator]
+fn depths(x: u32) -> Generator<usize> {
+	yield_item!(decurse::depth());
+	if x > 0 {
+		depths(x - 1);
+	}
+}
+
+#[decurse]
+fn deep_depths(x: u32, generator: Rc<std::cell::RefCell<Generator<usize>>>) -> Vec<usize> {
+	if x > 0 {
+		deep_depths(x - 1, generator)
+	} else {
+		let mut depths = vec![decurse::depth()];
+		depths.extend(generator.borrow_mut().next());
+		depths
+	}
+}
+
+#[test]
+fn test_depth() {
+	let mut generator = depths(3);
+	assert_eq!(generator.next(), Some(1));
+	assert_eq!(decurse::depth(), 0);
+	assert!(decurse::stack().is_empty());
+	let generator = Rc::new(std::cell::RefCell::new(generator));
+	// Resumed 11 frames deep.
+	assert_eq!(deep_depths(10, generator.clone()), [11, 13]);
+	assert_eq!(decurse::depth(), 0);
+	assert_eq!(generator.borrow_mut().next(), Some(3));
+	assert_eq!(generator.borrow_mut().next(), Some(4));
+	assert_eq!(generator.borrow_mut().next(), None);
+}
+
+struct Dropped(Rc<Cell<u32>>);
+
+impl Drop for Dropped {
+	fn drop(&mut self) {
+		self.0.set(self.0.get() + 1);
+	}
+}
+
+#[generator]
+fn holding(x: u32, dropped: Rc<Cell<u32>>) -> Generator<u32> {
+	let _dropped = Dropped(dropped.clone());
+	yield_item!(x);
+	holding(x + 1, dropped);
+}
+
+#[test]
+fn test_drop_suspended() {
+	let dropped = Rc::new(Cell::new(0));
+	let mut generator = holding(0, dropped.clone());
+	assert_eq!(generator.nth(1000), Some(1000));
+	assert_eq!(dropped.get(), 0);
+	drop(generator);
+	assert_eq!(dropped.get(), 1001);
+	assert_eq!(decurse::depth(), 0);
+}
+
+#[generator]
+fn fails(x: u32) -> Generator<u32> {
+	yield_item!(x);
+	if x == 3 {
+		panic!("three");
+	}
+	fails(x + 1);
+}
+
+#[test]
+fn test_panic() {
+	let mut generator = fails(0);
+	assert_eq!(generator.by_ref().take(4).count(), 4);
+	let result = panic::catch_unwind(AssertUnwindSafe(|| generator.next()));
+	assert!(result.is_err());
+	assert_eq!(generator.next(), None);
+	assert_eq!(decurse::depth(), 0);
+}
+
+async fn odd_numbers(x: u32) {
+	if x > 0 {
+		call(odd_numbers(x - 1)).await;
+		yield_item!(2 * x - 1);
+	}
+}
+
+#[test]
+fn test_without_macro() {
+	let generator: Generator<u32> = Generator::new(odd_numbers(100000));
+	assert_eq!(generator.map(u64::from).sum::<u64>(), 100000 * 100000);
+}
+
+#[test]
+#[should_panic(expected = "Decurse: yield_item! used outside of a generator.")]
+fn test_yield_outside() {
+	decurse::execute(async {
+		yield_item!(1);
+	});
+}
+
+#[test]
+fn test_recorded() {
+	let root = tree(1, 2, &mut 0);
+	let (values, recording) =
+		decurse::record::Recorder::new().execute(async move { preorder(root).collect::<Vec<_>>() });
+	assert_eq!(values, [0, 1, 2]);
+	let parents: Vec<_> = recording.frames().iter().map(|f| f.parent()).collect();
+	assert_eq!(parents, [None, Some(0), Some(0)]);
+	assert!(recording
+		.frames()
+		.iter()
+		.all(|f| f.duration(&recording).is_some()));
+}
@@ -0,0 +1,237 @@
+use std::{
+	cell::RefCell,
+	collections::{HashMap, HashSet},
+};
+
+use decurse::{
+	graph::{articulation_points, strongly_connected_components, topological_sort, Cycle, Dfs},
+	walk::Control,
+};
+
+const DEEP: usize = 200000;
+
+// A small deterministic random number generator, for the graphs checked against brute force.
+struct Lcg(u64);
+
+impl Lcg {
+	fn below(&mut self, n: usize) -> usize {
+		self.0 = self
+			.0
+			.wrapping_mul(6364136223846793005)
+			.wrapping_add(1442695040888963407);
+		((self.0 >> 33) % n as u64) as usize
+	}
+}
+
+fn random_graph(rng: &mut Lcg, nodes: usize, edges: usize) -> Vec<Vec<usize>> {
+	let mut graph = vec![Vec::new(); nodes];
+	for _ in 0..edges {
+		let (a, b) = (rng.below(nodes), rng.below(nodes));
+		graph[a].push(b);
+	}
+	graph
+}
+
+fn reachable(graph: &[Vec<usize>], from: usize, skip: Option<usize>) -> HashSet<usize> {
+	let mut seen = HashSet::new();
+	let mut todo = vec![from];
+	while let Some(node) = todo.pop() {
+		if Some(node) == skip || !seen.insert(node) {
+			continue;
+		}
+		todo.extend(graph[node].iter().copied());
+	}
+	seen
+}
+
+#[test]
+fn test_dfs() {
+	// 0 -> 1, 0 -> 2, 1 -> 3, 2 -> 3, 3 -> 0
+	let graph = [vec![1, 2], vec![3], vec![3], vec![0], vec![]];
+	let events = RefCell::new(Vec::new());
+	let mut dfs = Dfs::new(|&node: &usize| graph[node].clone())
+		.discover(|&node| {
+			events.borrow_mut().push(format!("discover {}", node));
+			Control::Continue
+		})
+		.finish(|&node| {
+			events.borrow_mut().push(format!("finish {}", node));
+			Control::Continue
+		});
+	assert!(dfs.run(0));
+	assert!(dfs.is_visited(&3));
+	assert!(!dfs.is_visited(&4));
+	// Already visited nodes are skipped.
+	assert!(dfs.run(2));
+	assert!(dfs.run(4));
+	drop(dfs);
+	assert_eq!(
+		events.into_inner(),
+		[
+			"discover 0",
+			"discover 1",
+			"discover 3",
+			"finish 3",
+			"finish 1",
+			"discover 2",
+			"finish 2",
+			"finish 0",
+			"discover 4",
+			"finish 4",
+		]
+	);
+}
+
+#[test]
+fn test_dfs_prune_and_stop() {
+	let mut discovered = Vec::new();
+	let mut dfs = Dfs::new(|&node: &u32| vec![node * 2, node * 2 + 1])
+		.discover(|&node| {
+			discovered.push(node);
+			match node {
+				n if n >= 4 => Control::Prune,
+				_ => Control::Continue,
+			}
+		})
+		.finish(|&node| match node {
+			6 => Control::Stop,
+			_ => Control::Continue,
+		});
+	assert!(!dfs.run(1));
+	drop(dfs);
+	assert_eq!(discovered, [1, 2, 4, 5, 3, 6]);
+}
+
+#[test]
+fn test_deep_dfs() {
+	// A long path, with a shortcut back to the start from every node.
+	let mut finished = 0;
+	let mut dfs = Dfs::new(|&node: &usize| {
+		if node + 1 < DEEP {
+			vec![0, node + 1]
+		} else {
+			vec![0]
+		}
+	})
+	.finish(|_| {
+		finished += 1;
+		Control::Continue
+	});
+	assert!(dfs.run(0));
+	drop(dfs);
+	assert_eq!(finished, DEEP);
+}
+
+#[test]
+fn test_scc() {
+	let mut rng = Lcg(1);
+	for _ in 0..50 {
+		let graph = random_graph(&mut rng, 12, 16);
+		let components = strongly_connected_components(0..12, |&node| graph[node].clone());
+		let mut component_of = HashMap::new();
+		for (i, component) in components.iter().enumerate() {
+			for &node in component {
+				assert!(component_of.insert(node, i).is_none());
+			}
+		}
+		assert_eq!(component_of.len(), 12);
+		let reach: Vec<_> = (0..12).map(|node| reachable(&graph, node, None)).collect();
+		for a in 0..12 {
+			for b in 0..12 {
+				let same = reach[a].contains(&b) && reach[b].contains(&a);
+				assert_eq!(component_of[&a] == component_of[&b], same);
+				// Edges go to earlier components.
+				if graph[a].contains(&b) {
+					assert!(component_of[&b] <= component_of[&a]);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+fn test_deep_scc() {
+	// A cycle through every node, and a path hanging off it.
+	let neighbors = |&node: &usize| match node {
+		n if n < DEEP - 1 => vec![n + 1],
+		n if n == DEEP - 1 => vec![DEEP, 0],
+		n if n < 2 * DEEP => vec![n + 1],
+		_ => vec![],
+	};
+	let components = strongly_connected_components(vec![0], neighbors);
+	assert_eq!(components.len(), DEEP + 2);
+	assert_eq!(components[0], [2 * DEEP]);
+	assert_eq!(components[DEEP + 1].len(), DEEP);
+}
+
+#[test]
+fn test_topological_sort() {
+	let graph = [vec![1, 2], vec![3], vec![3], vec![], vec![0]];
+	let order = topological_sort(0..5, |&node| graph[node].clone()).unwrap();
+	let position: HashMap<_, _> = order
+		.iter()
+		.enumerate()
+		.map(|(i, &node)| (node, i))
+		.collect();
+	assert_eq!(position.len(), 5);
+	for (a, edges) in graph.iter().enumerate() {
+		for b in edges {
+			assert!(position[&a] < position[b]);
+		}
+	}
+
+	let graph = [vec![1], vec![2], vec![3], vec![1], vec![]];
+	let cycle = topological_sort(0..5, |&node| graph[node].clone()).unwrap_err();
+	assert_eq!(cycle, Cycle(vec![1, 2, 3]));
+	assert_eq!(cycle.to_string(), "graph has a cycle: 1 -> 2 -> 3 -> 1");
+
+	// A long chain.
+	let order = topological_sort(vec![0], |&node: &usize| (node + 1..DEEP).take(1)).unwrap();
+	assert_eq!(order, (0..DEEP).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_articulation_points() {
+	let mut rng = Lcg(2);
+	for _ in 0..50 {
+		let directed = random_graph(&mut rng, 10, 12);
+		let mut graph = vec![Vec::new(); 10];
+		for (a, edges) in directed.iter().enumerate() {
+			for &b in edges {
+				graph[a].push(b);
+				graph[b].push(a);
+			}
+		}
+		let points: HashSet<_> = articulation_points(0..10, |&node| graph[node].clone())
+			.into_iter()
+			.collect();
+		for node in 0..10 {
+			// Removing the node disconnects two of its neighbours.
+			let disconnects = graph[node].iter().filter(|&&a| a != node).any(|&a| {
+				let reach = reachable(&graph, a, Some(node));
+				graph[node].iter().any(|b| *b != node && !reach.contains(b))
+			});
+			assert_eq!(points.contains(&node), disconnects, "{:?} {}", graph, node);
+		}
+	}
+
+	// Parallel edges keep the nodes connected.
+	let graph = [vec![1, 1], vec![0, 0, 2], vec![1]];
+	assert_eq!(
+		articulation_points(vec![0], |&node| graph[node].clone()),
+		[1]
+	);
+	let graph = [vec![1, 1], vec![0, 0]];
+	assert!(articulation_points(vec![0], |&node| graph[node].clone()).is_empty());
+
+	// A long path, where every node but the ends is a point.
+	let points = articulation_points(vec![0], |&node: &usize| {
+		let mut next = vec![node + 1];
+		if node > 0 {
+			next.push(node - 1);
+		}
+		next.retain(|&n| n < DEEP);
+		next
+	});
+	assert_eq!(points.len(), DEEP - 2);
+}
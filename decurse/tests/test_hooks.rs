@@ -0,0 +1,214 @@
+use std::{
+	cell::{Cell, RefCell},
+	panic::AssertUnwindSafe,
+	rc::Rc,
+};
+
+use decurse::{
+	decurse, generator,
+	hooks::{add_thread_hook, execute_with_hook, remove_thread_hook, Hook, HookId},
+	yield_item, Generator, StackFrame,
+};
+
+#[decurse(debug_args)]
+fn count_down(x: u32) -> u32 {
+	if x == 0 {
+		0
+	} else {
+		count_down(x - 1) + 1
+	}
+}
+
+#[derive(Default)]
+struct Log {
+	events: RefCell<Vec<String>>,
+}
+
+impl Log {
+	fn take(&self) -> Vec<String> {
+		self.events.take()
+	}
+}
+
+impl Hook for Log {
+	fn on_push(&self, frame: &StackFrame, depth: usize) {
+		self.events
+			.borrow_mut()
+			.push(format!("push {} {}", depth, frame.args().unwrap()));
+	}
+	fn on_pop(&self, frame: &StackFrame, depth: usize) {
+		self.events
+			.borrow_mut()
+			.push(format!("pop {} {}", depth, frame.args().unwrap()));
+	}
+	fn depth_thresholds(&self) -> &[usize] {
+		&[2, 5]
+	}
+	fn on_depth_crossed(&self, depth: usize) {
+		self.events.borrow_mut().push(format!("depth {}", depth));
+	}
+	fn on_complete(&self) {
+		self.events.borrow_mut().push(String::from("complete"));
+	}
+}
+
+#[test]
+fn test_execute_with_hook() {
+	let log = Rc::new(Log::default());
+	assert_eq!(execute_with_hook(log.clone(), async { count_down(2) }), 2);
+	assert_eq!(
+		log.take(),
+		[
+			"push 1 2", "push 2 1", "depth 2", "push 3 0", "pop 3 0", "pop 2 1", "pop 1 2",
+			"complete"
+		]
+	);
+	// The hook is gone.
+	count_down(2);
+	assert!(log.take().is_empty());
+}
+
+#[test]
+fn test_thread_hook() {
+	let log = Rc::new(Log::default());
+	let id = add_thread_hook(log.clone());
+	count_down(1);
+	count_down(0);
+	assert_eq!(
+		log.take(),
+		[
+			"push 1 1", "push 2 0", "depth 2", "pop 2 0", "pop 1 1", "complete", "push 1 0",
+			"pop 1 0", "complete"
+		]
+	);
+	assert!(remove_thread_hook(id));
+	assert!(!remove_thread_hook(id));
+	count_down(1);
+	assert!(log.take().is_empty());
+}
+
+#[test]
+fn test_hook_on_panic() {
+	#[decurse]
+	fn explode(x: u32) -> u32 {
+		if x == 0 {
+			panic!("boom")
+		} else {
+			explode(x - 1)
+		}
+	}
+	struct Pops(Rc<RefCell<usize>>);
+	impl Hook for Pops {
+		fn on_pop(&self, _frame: &StackFrame, _depth: usize) {
+			*self.0.borrow_mut() += 1;
+		}
+	}
+	let pops = Rc::new(RefCell::new(0));
+	let hook = Pops(pops.clone());
+	assert!(std::panic::catch_unwind(AssertUnwindSafe(move || {
+		execute_with_hook(hook, async { explode(100) })
+	}))
+	.is_err());
+	// Every frame was popped.
+	assert_eq!(*pops.borrow(), 101);
+	count_down(1);
+	assert_eq!(*pops.borrow(), 101);
+}
+
+#[test]
+fn test_hook_added_inside() {
+	// The hook is only told about the frames pushed after it was added.
+	type Counts = Rc<RefCell<(usize, usize)>>;
+	struct Count(Counts);
+	impl Hook for Count {
+		fn on_push(&self, _frame: &StackFrame, _depth: usize) {
+			self.0.borrow_mut().0 += 1;
+		}
+		fn on_pop(&self, _frame: &StackFrame, _depth: usize) {
+			self.0.borrow_mut().1 += 1;
+		}
+	}
+	thread_local! {
+		static ID: Cell<Option<HookId>> = const { Cell::new(None) };
+	}
+	#[decurse]
+	fn nest(x: u32, counts: Counts) -> u32 {
+		if x == 3 {
+			ID.with(|id| id.set(Some(add_thread_hook(Count(counts.clone())))));
+		}
+		if x == 0 {
+			0
+		} else {
+			nest(x - 1, counts) + 1
+		}
+	}
+	let counts = Counts::default();
+	assert_eq!(nest(5, counts.clone()), 5);
+	assert!(remove_thread_hook(ID.with(Cell::take).unwrap()));
+	assert_eq!(*counts.borrow(), (3, 3));
+}
+
+#[derive(Default)]
+struct Crossings(RefCell<Vec<usize>>);
+
+impl Hook for Crossings {
+	fn depth_thresholds(&self) -> &[usize] {
+		&[3, 5]
+	}
+	fn on_depth_crossed(&self, depth: usize) {
+		self.0.borrow_mut().push(depth);
+	}
+}
+
+#[decurse]
+fn fibonacci(x: u32) -> u32 {
+	if x < 2 {
+		x
+	} else {
+		fibonacci(x - 1) + fibonacci(x - 2)
+	}
+}
+
+#[test]
+fn test_depth_crossed_once() {
+	// The depth goes up and down around the thresholds many times.
+	let crossings = Rc::new(Crossings::default());
+	assert_eq!(
+		execute_with_hook(crossings.clone(), async { fibonacci(10) }),
+		55
+	);
+	assert_eq!(crossings.0.take(), [3, 5]);
+	// Once per computation.
+	let id = add_thread_hook(crossings.clone());
+	fibonacci(10);
+	fibonacci(4);
+	assert!(remove_thread_hook(id));
+	assert_eq!(crossings.0.take(), [3, 5, 3]);
+}
+
+#[generator]
+fn naturals(x: u32) -> Generator<u32> {
+	yield_item!(x);
+	naturals(x + 1);
+}
+
+#[test]
+fn test_hook_scoped_to_computation() {
+	let mut numbers = naturals(0);
+	let pushes = Rc::new(Cell::new(0));
+	struct Pushes(Rc<Cell<usize>>);
+	impl Hook for Pushes {
+		fn on_push(&self, _frame: &StackFrame, _depth: usize) {
+			self.0.set(self.0.get() + 1);
+		}
+	}
+	let hook = Pushes(pushes.clone());
+	let taken = execute_with_hook(hook, async move {
+		// The generator runs on its own, so its frames aren't part of this computation.
+		let taken: Vec<u32> = numbers.by_ref().take(3).collect();
+		count_down(2);
+		taken
+	});
+	assert_eq!(taken, [0, 1, 2]);
+	assert_eq!(pushes.get(), 3);
+}
@@ -0,0 +1,239 @@
+use std::{cell::Cell, rc::Rc};
+
+use decurse::{
+	decurse,
+	incremental::Input,
+	memo::{clear_thread_caches, Cache},
+	Recurse, Recursion,
+};
+
+thread_local! {
+	static CALLS: Cell<usize> = const { Cell::new(0) };
+	static WEIGHTS: Input<usize, u64> = Input::new();
+}
+
+fn count_call() {
+	CALLS.with(|c| c.set(c.get() + 1));
+}
+
+fn take_calls() -> usize {
+	CALLS.with(|c| c.replace(0))
+}
+
+#[decurse(memoize)]
+fn total(node: usize) -> u64 {
+	count_call();
+	let own = WEIGHTS.with(|w| w.get(&node)).unwrap_or(0);
+	if node == 0 {
+		own
+	} else {
+		total(node - 1) + own
+	}
+}
+
+#[test]
+fn test_recompute_dependents() {
+	WEIGHTS.with(|w| (0..100000).for_each(|i| w.set(i, 1)));
+	take_calls();
+	assert_eq!(total(99999), 100000);
+	assert_eq!(take_calls(), 100000);
+	assert_eq!(total(99999), 100000);
+	assert_eq!(take_calls(), 0);
+
+	// Only the frames from 90000 up read it, directly or through `total(node - 1)`.
+	WEIGHTS.with(|w| w.set(90000, 11));
+	assert_eq!(total(99999), 100010);
+	assert_eq!(take_calls(), 10000);
+	assert_eq!(total(89999), 90000);
+	assert_eq!(take_calls(), 0);
+
+	// Setting the same value changes nothing.
+	WEIGHTS.with(|w| w.set(90000, 11));
+	assert_eq!(total(99999), 100010);
+	assert_eq!(take_calls(), 0);
+
+	WEIGHTS.with(|w| w.invalidate(&99999));
+	assert_eq!(total(99999), 100010);
+	assert_eq!(take_calls(), 1);
+}
+
+#[test]
+fn test_missing_input() {
+	thread_local! {
+		static NAMES: Input<u32, String> = Input::new();
+	}
+	#[decurse(memoize)]
+	fn name_length(x: u32) -> usize {
+		count_call();
+		NAMES.with(|n| n.get(&x)).map_or(0, |n| n.len())
+	}
+	take_calls();
+	assert_eq!(name_length(1), 0);
+	NAMES.with(|n| n.set(1, String::from("one")));
+	assert_eq!(name_length(1), 3);
+	NAMES.with(|n| n.remove(&1));
+	assert_eq!(name_length(1), 0);
+	assert_eq!(take_calls(), 3);
+}
+
+#[test]
+fn test_across_functions() {
+	thread_local! {
+		static BASE: Input<(), u64> = Input::new();
+	}
+	#[decurse(memoize)]
+	fn base(x: u64) -> u64 {
+		count_call();
+		BASE.with(|b| b.get(&())).unwrap() + x
+	}
+	#[decurse(memoize)]
+	fn sum(x: u64) -> u64 {
+		count_call();
+		if x == 0 {
+			0
+		} else {
+			sum(x - 1) + base(x)
+		}
+	}
+	BASE.with(|b| b.set((), 1));
+	take_calls();
+	assert_eq!(sum(100), 5150);
+	assert_eq!(take_calls(), 201);
+	BASE.with(|b| b.set((), 2));
+	// `sum(0)` doesn't depend on `base`.
+	assert_eq!(sum(100), 5250);
+	assert_eq!(take_calls(), 200);
+}
+
+#[test]
+fn test_through_non_memoized() {
+	// Reads made by non-memoized frames count for the memoized frame that called them.
+	thread_local! {
+		static VALUES: Input<u64, u64> = Input::new();
+	}
+	#[decurse]
+	fn sum_values(x: u64) -> u64 {
+		let own = VALUES.with(|v| v.get(&x)).unwrap_or(0);
+		if x == 0 {
+			own
+		} else {
+			sum_values(x - 1) + own
+		}
+	}
+	#[decurse(memoize)]
+	fn doubled(x: u64) -> u64 {
+		count_call();
+		sum_values(x) * 2
+	}
+	VALUES.with(|v| (0..1000).for_each(|i| v.set(i, i)));
+	take_calls();
+	assert_eq!(doubled(999), 999000);
+	assert_eq!(doubled(999), 999000);
+	assert_eq!(take_calls(), 1);
+	VALUES.with(|v| v.set(0, 1000));
+	assert_eq!(doubled(999), 1001000);
+	assert_eq!(take_calls(), 1);
+}
+
+#[test]
+fn test_removed_entry() {
+	thread_local! {
+		static FACTOR: Input<(), u64> = Input::new();
+	}
+	let inner_cache = Rc::new(Cache::new());
+	let outer_cache = Rc::new(Cache::new());
+	async fn inner(_rec: Recurse<u64, u64>, x: u64) -> u64 {
+		count_call();
+		FACTOR.with(|f| f.get(&())).unwrap() * x
+	}
+	let inner = Recursion::new(inner).memoize(inner_cache.clone());
+	let outer = Recursion::new(move |rec: Recurse<u64, u64>, x: u64| {
+		let inner = inner.clone();
+		async move {
+			count_call();
+			rec.call_with(&inner, x).await + 1
+		}
+	})
+	.memoize(outer_cache.clone());
+	FACTOR.with(|f| f.set((), 2));
+	take_calls();
+	assert_eq!(outer.run(5), 11);
+	assert_eq!(take_calls(), 2);
+	// Removing the inner entry keeps the outer one...
+	inner_cache.remove(&5);
+	assert_eq!(outer_cache.len(), 1);
+	assert_eq!(outer.run(5), 11);
+	assert_eq!(take_calls(), 0);
+	// ...which still depends on what the inner one read.
+	FACTOR.with(|f| f.set((), 3));
+	assert!(outer_cache.is_empty());
+	assert_eq!(outer.run(5), 16);
+	assert_eq!(take_calls(), 2);
+}
+
+#[test]
+fn test_limited_cache() {
+	thread_local! {
+		static OFFSET: Input<(), u64> = Input::new();
+	}
+	#[decurse(memoize(limit = 10))]
+	fn offset_fibonacci(x: u64) -> u64 {
+		if x == 0 || x == 1 {
+			OFFSET.with(|o| o.get(&())).unwrap()
+		} else {
+			offset_fibonacci(x - 1) + offset_fibonacci(x - 2)
+		}
+	}
+	OFFSET.with(|o| o.set((), 1));
+	assert_eq!(offset_fibonacci(50), 20365011074);
+	OFFSET.with(|o| o.set((), 2));
+	assert_eq!(offset_fibonacci(50), 40730022148);
+}
+
+#[test]
+fn test_dropped_cache() {
+	thread_local! {
+		static SCALE: Input<(), u64> = Input::new();
+	}
+	async fn scaled(rec: Recurse<u64, u64>, x: u64) -> u64 {
+		let scale = SCALE.with(|s| s.get(&())).unwrap();
+		if x == 0 {
+			0
+		} else {
+			rec.call(x - 1).await + scale
+		}
+	}
+	SCALE.with(|s| s.set((), 2));
+	let cache = Rc::new(Cache::new());
+	let scaled = Recursion::new(scaled).memoize(cache.clone());
+	assert_eq!(scaled.run(100), 200);
+	assert_eq!(cache.len(), 101);
+	// The dependency graph doesn't keep the cache alive.
+	let weak = Rc::downgrade(&cache);
+	drop(scaled);
+	drop(cache);
+	assert!(weak.upgrade().is_none());
+	SCALE.with(|s| s.set((), 3));
+}
+
+#[test]
+fn test_clear_while_running() {
+	thread_local! {
+		static VALUE: Input<(), u64> = Input::new();
+	}
+	#[decurse(memoize)]
+	fn sum(x: u64) -> u64 {
+		let value = VALUE.with(|v| v.get(&())).unwrap();
+		if x == 0 {
+			clear_thread_caches();
+			value
+		} else {
+			sum(x - 1) + value
+		}
+	}
+	VALUE.with(|v| v.set((), 1));
+	assert_eq!(sum(3), 4);
+	// The frames still running when the caches were cleared still depend on the input.
+	VALUE.with(|v| v.set((), 2));
+	assert_eq!(sum(3), 8);
+}
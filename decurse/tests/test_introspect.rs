@@ -0,0 +1,106 @@
+use decurse::{decurse, depth, stack, Recurse, Recursion};
+
+#[test]
+fn test_depth() {
+	#[decurse]
+	fn depths(x: u32) -> Vec<usize> {
+		let mut result = if x == 0 { Vec::new() } else { depths(x - 1) };
+		result.push(depth());
+		result
+	}
+	assert_eq!(depths(3), [4, 3, 2, 1]);
+	assert_eq!(depth(), 0);
+	assert!(stack().is_empty());
+}
+
+#[test]
+fn test_stack_names() {
+	#[decurse]
+	fn outer(x: u32) -> Vec<String> {
+		if x == 0 {
+			inner(())
+		} else {
+			outer(x - 1)
+		}
+	}
+	#[decurse]
+	fn inner(_unit: ()) -> Vec<String> {
+		stack().iter().map(ToString::to_string).collect()
+	}
+	assert_eq!(
+		outer(1),
+		[
+			"test_introspect::outer",
+			"test_introspect::outer",
+			"test_introspect::inner"
+		]
+	);
+}
+
+#[test]
+fn test_debug_args() {
+	#[decurse(debug_args)]
+	fn path(name: &'static str, x: u32) -> Vec<String> {
+		if x == 0 {
+			stack().iter().map(ToString::to_string).collect()
+		} else {
+			path(name, x - 1)
+		}
+	}
+	assert_eq!(
+		path("a", 2),
+		[
+			"test_introspect::path(\"a\", 2)",
+			"test_introspect::path(\"a\", 1)",
+			"test_introspect::path(\"a\", 0)"
+		]
+	);
+}
+
+#[test]
+fn test_recursion_debug_args() {
+	async fn last_args(rec: Recurse<u32, Option<String>>, x: u32) -> Option<String> {
+		if x == 0 {
+			stack()[0].args().map(str::to_owned)
+		} else {
+			rec.call(x - 1).await
+		}
+	}
+	let plain = Recursion::new(last_args).named("last_args");
+	assert_eq!(plain.run(5), None);
+	assert_eq!(plain.clone().debug_args().run(5), Some(String::from("5")));
+	assert_eq!(
+		plain.debug_args_with(|x| format!("x = {}", x)).run(5),
+		Some(String::from("x = 5"))
+	);
+}
+
+#[test]
+fn test_stack_after_panic() {
+	#[decurse]
+	fn explode(x: u32) -> u32 {
+		if x == 0 {
+			panic!("boom")
+		} else {
+			explode(x - 1)
+		}
+	}
+	assert!(std::panic::catch_unwind(|| explode(100)).is_err());
+	assert_eq!(depth(), 0);
+}
+
+#[test]
+fn test_debug_args_selected() {
+	#[decurse(debug_args(y, x))]
+	fn selected(x: u32, _ignored: Vec<u8>, y: bool) -> String {
+		if y {
+			stack()[0].to_string()
+		} else {
+			x.to_string()
+		}
+	}
+	assert_eq!(
+		selected(3, Vec::new(), true),
+		"test_introspect::selected(y = true, x = 3)"
+	);
+}
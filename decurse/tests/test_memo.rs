@@ -0,0 +1,135 @@
+use std::{cell::Cell, rc::Rc};
+
+use decurse::{
+	decurse,
+	memo::{clear_thread_caches, Cache},
+	Recurse, Recursion,
+};
+
+thread_local! {
+	static CALLS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count_call() {
+	CALLS.with(|c| c.set(c.get() + 1));
+}
+
+fn take_calls() -> usize {
+	CALLS.with(|c| c.replace(0))
+}
+
+#[test]
+fn test_memoize() {
+	#[decurse(memoize)]
+	fn fibonacci(x: u64) -> u64 {
+		count_call();
+		if x == 0 || x == 1 {
+			1
+		} else {
+			fibonacci(x - 1) + fibonacci(x - 2)
+		}
+	}
+	take_calls();
+	assert_eq!(fibonacci(90), 4660046610375530309);
+	assert_eq!(take_calls(), 91);
+	// Everything is cached now.
+	assert_eq!(fibonacci(90), 4660046610375530309);
+	assert_eq!(take_calls(), 0);
+	clear_thread_caches();
+	assert_eq!(fibonacci(10), 89);
+	assert_eq!(take_calls(), 11);
+}
+
+#[test]
+fn test_memoize_deep() {
+	#[decurse(memoize)]
+	fn triangular(x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			triangular(x - 1) + x
+		}
+	}
+	assert_eq!(triangular(200000), 20000100000);
+	assert_eq!(triangular(200001), 20000300001);
+}
+
+#[test]
+fn test_memoize_multiple_args() {
+	#[decurse(memoize)]
+	fn paths(x: u32, y: u32) -> u64 {
+		if x == 0 || y == 0 {
+			1
+		} else {
+			paths(x - 1, y) + paths(x, y - 1)
+		}
+	}
+	assert_eq!(paths(16, 16), 601080390);
+}
+
+#[test]
+fn test_memoize_supplied_cache() {
+	thread_local! {
+		static CACHE: Cache<(u64,), u64> = Cache::with_limit(10);
+	}
+	#[decurse(memoize(cache = CACHE))]
+	fn fibonacci(x: u64) -> u64 {
+		if x == 0 || x == 1 {
+			1
+		} else {
+			fibonacci(x - 1) + fibonacci(x - 2)
+		}
+	}
+	assert_eq!(fibonacci(50), 20365011074);
+	assert_eq!(CACHE.with(Cache::len), 10);
+	assert_eq!(CACHE.with(|c| c.get(&(50,))), Some(20365011074));
+	assert_eq!(CACHE.with(|c| c.get(&(40,))), None);
+	CACHE.with(Cache::clear);
+	assert!(CACHE.with(Cache::is_empty));
+}
+
+#[test]
+fn test_memoize_limit() {
+	#[decurse(memoize(limit = 5))]
+	fn fibonacci(x: u64) -> u64 {
+		count_call();
+		if x == 0 || x == 1 {
+			1
+		} else {
+			fibonacci(x - 1) + fibonacci(x - 2)
+		}
+	}
+	take_calls();
+	assert_eq!(fibonacci(30), 1346269);
+	assert_eq!(take_calls(), 31);
+	// Only the 5 most recent results are kept.
+	assert_eq!(fibonacci(25), 121393);
+	assert_eq!(take_calls(), 26);
+}
+
+#[test]
+fn test_recursion_memoize() {
+	async fn fibonacci(rec: Recurse<u64, u64>, x: u64) -> u64 {
+		if x == 0 || x == 1 {
+			1
+		} else {
+			rec.call(x - 1).await + rec.call(x - 2).await
+		}
+	}
+	let cache = Rc::new(Cache::new());
+	let fibonacci = Recursion::new(fibonacci).memoize(cache.clone());
+	assert_eq!(fibonacci.run(90), 4660046610375530309);
+	assert_eq!(cache.len(), 91);
+}
+
+#[test]
+fn test_cache_remove() {
+	let cache: Cache<u32, u32> = Cache::with_limit(2);
+	cache.insert(1, 10);
+	cache.insert(2, 20);
+	assert_eq!(cache.remove(&1), Some(10));
+	cache.insert(3, 30);
+	assert_eq!(cache.len(), 2);
+	assert_eq!(cache.get(&2), Some(20));
+	assert_eq!(cache.get(&3), Some(30));
+}
@@ -0,0 +1,176 @@
+use decurse::{
+	parse::{
+		alt, end, many, many1, opt, recursive, satisfy, sep_by, seq, tag, take_while, take_while1,
+		whitespace, ErrorKind, Parser,
+	},
+	DeepDrop,
+};
+
+// As many `[` as in a hostile input.
+const DEEP: usize = 100000;
+
+#[derive(Debug, PartialEq, DeepDrop)]
+enum Json {
+	Null,
+	Bool(bool),
+	Num(f64),
+	Str(String),
+	Array(Vec<Json>),
+	Object(Vec<(String, Json)>),
+}
+
+// A token followed by whitespace.
+fn token<'i>(s: &'i str) -> Parser<'i, &'i str> {
+	seq((tag(s), whitespace())).map(|(s, ())| s)
+}
+
+fn string<'i>() -> Parser<'i, String> {
+	seq((tag("\""), take_while(|c| c != '"'), token("\""))).map(|(_, s, _)| s.to_string())
+}
+
+fn json<'i>() -> Parser<'i, Json> {
+	recursive(|value| {
+		let number = seq((
+			opt(tag("-")),
+			take_while1("a digit", |c| c.is_ascii_digit()),
+			whitespace(),
+		))
+		.map(|(sign, digits, ())| {
+			let n: f64 = digits.parse().unwrap();
+			Json::Num(if sign.is_some() { -n } else { n })
+		});
+		let array = seq((token("["), sep_by(value.clone(), token(",")), token("]")))
+			.map(|(_, items, _)| Json::Array(items));
+		let member = seq((string(), token(":"), value)).map(|(key, _, value)| (key, value));
+		let object = seq((token("{"), sep_by(member, token(",")), token("}")))
+			.map(|(_, members, _)| Json::Object(members));
+		alt((
+			token("null").map(|_| Json::Null),
+			token("true").map(|_| Json::Bool(true)),
+			token("false").map(|_| Json::Bool(false)),
+			number,
+			string().map(Json::Str),
+			array,
+			object,
+		))
+		.label("a value")
+	})
+}
+
+fn document<'i>() -> Parser<'i, Json> {
+	seq((whitespace(), json())).map(|((), value)| value)
+}
+
+#[test]
+fn test_json() {
+	let parsed =
+		document().parse(r#" {"a": [1, -2, true], "b": {"c": null}, "d": "text", "e": []} "#);
+	assert_eq!(
+		parsed,
+		Ok(Json::Object(vec![
+			(
+				"a".to_string(),
+				Json::Array(vec![Json::Num(1.0), Json::Num(-2.0), Json::Bool(true)])
+			),
+			(
+				"b".to_string(),
+				Json::Object(vec![("c".to_string(), Json::Null)])
+			),
+			("d".to_string(), Json::Str("text".to_string())),
+			("e".to_string(), Json::Array(vec![])),
+		]))
+	);
+}
+
+#[test]
+fn test_errors() {
+	let error = document().parse("[1,\n  2,\n  nul]").unwrap_err();
+	assert_eq!((error.line(), error.column(), error.position()), (3, 3, 11));
+	assert_eq!(
+		error.kind(),
+		&ErrorKind::Expected(vec!["a value".to_string()])
+	);
+	assert_eq!(error.to_string(), "expected a value at line 3, column 3");
+
+	// The furthest failure wins, and what was expected there is merged.
+	let error = document().parse("[1 2]").unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		r#"expected "," or "]" at line 1, column 4"#
+	);
+	let error = document().parse("[1] x").unwrap_err();
+	assert_eq!(
+		error.to_string(),
+		"expected end of input at line 1, column 5"
+	);
+	let error = document().parse("").unwrap_err();
+	assert_eq!(error.to_string(), "expected a value at line 1, column 1");
+	// Columns count characters.
+	let error = document().parse("\"é\" x").unwrap_err();
+	assert_eq!((error.column(), error.position()), (5, 5));
+}
+
+#[test]
+fn test_deep() {
+	let deep = "[".repeat(DEEP) + &"]".repeat(DEEP);
+	let value = document().parse(&deep).unwrap();
+	let mut depth = 0;
+	let mut node = &value;
+	while let Json::Array(items) = node {
+		depth += 1;
+		match items.first() {
+			Some(item) => node = item,
+			None => break,
+		}
+	}
+	assert_eq!(depth, DEEP);
+
+	// Without the closing brackets, the error is at the end.
+	let unclosed = "[".repeat(DEEP);
+	let error = document().parse(&unclosed).unwrap_err();
+	assert_eq!(error.position(), DEEP);
+
+	// With a limit, the error is where it was reached.
+	let error = document()
+		.parse_with_max_depth(&unclosed, 1000)
+		.unwrap_err();
+	assert_eq!(error.kind(), &ErrorKind::TooDeep(1000));
+	assert_eq!(error.position(), 1000);
+	assert_eq!(
+		error.to_string(),
+		"nested deeper than the maximum depth of 1000 at line 1, column 1001"
+	);
+	assert!(document().parse_with_max_depth("[[1]]", 3).is_ok());
+	assert!(document().parse_with_max_depth("[[1]]", 2).is_err());
+}
+
+#[test]
+fn test_combinators() {
+	// `alt` backtracks to where it started.
+	let parser = alt((seq((tag("ab"), tag("c"))).map(|_| 1), tag("abd").map(|_| 2)));
+	assert_eq!(parser.parse("abc"), Ok(1));
+	assert_eq!(parser.parse("abd"), Ok(2));
+
+	let digit = || satisfy("a digit", |c| c.is_ascii_digit());
+	assert_eq!(many(digit()).parse(""), Ok(vec![]));
+	assert_eq!(many(digit()).parse("12"), Ok(vec!['1', '2']));
+	assert!(many1(digit()).parse("").is_err());
+	assert_eq!(
+		sep_by(digit(), tag(",")).parse("1,2,3"),
+		Ok(vec!['1', '2', '3'])
+	);
+	// A trailing separator isn't consumed.
+	assert!(sep_by(digit(), tag(",")).parse("1,2,").is_err());
+	assert_eq!(seq((opt(tag("-")), digit())).parse("5"), Ok((None, '5')));
+
+	// Repeating a parser that consumes nothing stops.
+	assert_eq!(many(take_while(|c| c == 'x')).parse(""), Ok(vec![""]));
+	assert_eq!(seq((many(end()), end())).parse(""), Ok((vec![()], ())));
+
+	// Long repetitions don't nest.
+	let long = "1".repeat(DEEP);
+	assert_eq!(
+		many(digit()).parse(&long).map(|digits| digits.len()),
+		Ok(DEEP)
+	);
+}
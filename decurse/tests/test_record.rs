@@ -0,0 +1,115 @@
+use decurse::{decurse, record::Recorder};
+
+#[decurse(debug_args, debug_result)]
+fn fibonacci(x: u32) -> u32 {
+	if x < 2 {
+		1
+	} else {
+		fibonacci(x - 1) + fibonacci(x - 2)
+	}
+}
+
+#[decurse]
+fn count_down(x: u32) -> u32 {
+	if x == 0 {
+		0
+	} else {
+		count_down(x - 1) + 1
+	}
+}
+
+fn to_string(write: impl FnOnce(&mut Vec<u8>) -> std::io::Result<()>) -> String {
+	let mut out = Vec::new();
+	write(&mut out).unwrap();
+	String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_frames() {
+	let (output, recording) = Recorder::new().execute(async { fibonacci(3) });
+	assert_eq!(output, 3);
+	let frames: Vec<String> = recording
+		.frames()
+		.iter()
+		.map(|f| format!("{:?} {} {}", f.parent(), f.depth(), f))
+		.collect();
+	assert_eq!(
+		frames,
+		[
+			"None 1 test_record::fibonacci(3) = 3",
+			"Some(0) 2 test_record::fibonacci(2) = 2",
+			"Some(1) 3 test_record::fibonacci(1) = 1",
+			"Some(1) 3 test_record::fibonacci(0) = 1",
+			"Some(0) 2 test_record::fibonacci(1) = 1",
+		]
+	);
+	for frame in recording.frames() {
+		assert!(frame.duration(&recording).is_some());
+	}
+	// Nothing is recorded outside.
+	assert_eq!(fibonacci(3), 3);
+	assert_eq!(recording.frames().len(), 5);
+}
+
+#[test]
+fn test_dot() {
+	let (_, recording) = Recorder::new().execute(async { fibonacci(2) });
+	assert_eq!(
+		to_string(|out| recording.write_dot(out)),
+		r#"digraph decurse {
+	node [shape=box];
+	n0 [label="test_record::fibonacci(2) = 2"];
+	n1 [label="test_record::fibonacci(1) = 1"];
+	n0 -> n1;
+	n2 [label="test_record::fibonacci(0) = 1"];
+	n0 -> n2;
+}
+"#
+	);
+}
+
+#[test]
+fn test_chrome_trace() {
+	let (_, recording) = Recorder::new()
+		.timestamps(false)
+		.execute(async { fibonacci(1) });
+	assert_eq!(
+		to_string(|out| recording.write_chrome_trace(out)),
+		r#"{"traceEvents":[
+{"name":"test_record::fibonacci","ph":"X","pid":0,"tid":0,"ts":1,"dur":1,"args":{"depth":1,"args":"1","result":"1"}}
+]}
+"#
+	);
+}
+
+#[test]
+fn test_folded() {
+	let (_, recording) = Recorder::new()
+		.timestamps(false)
+		.execute(async { fibonacci(3) });
+	assert_eq!(
+		to_string(|out| recording.write_folded(out)),
+		"test_record::fibonacci 1
+test_record::fibonacci;test_record::fibonacci 2
+test_record::fibonacci;test_record::fibonacci;test_record::fibonacci 2
+"
+	);
+	let (_, recording) = Recorder::new().execute(async { fibonacci(10) });
+	assert_eq!(
+		to_string(|out| recording.write_folded(out)).lines().count(),
+		10
+	);
+}
+
+#[test]
+fn test_node_limit() {
+	let (output, recording) = Recorder::new()
+		.node_limit(100)
+		.execute(async { count_down(100000) });
+	assert_eq!(output, 100000);
+	assert_eq!(recording.frames().len(), 100);
+	assert_eq!(recording.dropped(), 99901);
+	assert_eq!(recording.frames()[99].parent(), Some(98));
+	assert_eq!(recording.frames()[99].frame().args(), None);
+	assert_eq!(recording.frames()[99].result(), None);
+}
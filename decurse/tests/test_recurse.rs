@@ -0,0 +1,74 @@
+use decurse::{AbortHandle, Cancelled, Recurse, Recursion};
+
+#[test]
+fn test_factorial() {
+	async fn factorial(rec: Recurse<u32, u32>, x: u32) -> u32 {
+		if x == 0 {
+			1
+		} else {
+			x * rec.call(x - 1).await
+		}
+	}
+	assert_eq!(Recursion::new(factorial).run(6), 720);
+}
+
+#[test]
+fn test_tuple_args() {
+	async fn ackermann(rec: Recurse<(u64, u64), u64>, (m, n): (u64, u64)) -> u64 {
+		if m == 0 {
+			n + 1
+		} else if n == 0 {
+			rec.call((m - 1, 1)).await
+		} else {
+			let inner = rec.call((m, n - 1)).await;
+			rec.call((m - 1, inner)).await
+		}
+	}
+	assert_eq!(Recursion::new(ackermann).run((2, 3)), 9);
+	assert_eq!(Recursion::new(ackermann).run((3, 6)), 509);
+}
+
+#[test]
+fn test_closure_body() {
+	let offset = 3;
+	let sum = Recursion::new(move |rec: Recurse<u64, u64>, x: u64| async move {
+		if x == 0 {
+			0
+		} else {
+			rec.call(x - 1).await + x + offset
+		}
+	});
+	assert_eq!(sum.run(100000), 5000050000 + 300000);
+	// The same recursion can be run again.
+	assert_eq!(sum.run(1), 4);
+}
+
+#[test]
+fn test_call_with() {
+	async fn length(rec: Recurse<u64, u64>, x: u64) -> u64 {
+		if x == 0 {
+			0
+		} else {
+			rec.call(x - 1).await + 1
+		}
+	}
+	let length = Recursion::new(length);
+	let outer = Recursion::new(move |rec: Recurse<u64, u64>, x: u64| {
+		let length = length.clone();
+		async move { rec.call_with(&length, x).await * 2 }
+	});
+	assert_eq!(outer.run(300000), 600000);
+}
+
+#[test]
+fn test_run_abortable() {
+	async fn forever(rec: Recurse<(), ()>, _: ()) {
+		rec.call(()).await
+	}
+	let handle = AbortHandle::new();
+	handle.abort();
+	assert_eq!(
+		Recursion::new(forever).run_abortable((), handle),
+		Err(Cancelled)
+	);
+}
@@ -0,0 +1,138 @@
+use std::collections::BTreeMap;
+
+use decurse::{deep::Recursive, DeepDrop};
+
+const DEEP: usize = 200000;
+
+#[derive(decurse::Recursive, DeepDrop, Default, Debug, PartialEq)]
+enum Expr {
+	#[default]
+	Zero,
+	Num(i64),
+	Neg(Box<Expr>),
+	Add(Box<Expr>, Box<Expr>),
+	Sum(Vec<Expr>),
+	If {
+		cond: Box<Expr>,
+		then: Box<Expr>,
+		otherwise: Option<Box<Expr>>,
+	},
+	Let(BTreeMap<String, Expr>, Box<Expr>),
+	Pair((u8, Box<Expr>)),
+}
+
+fn eval(expr: Expr) -> i64 {
+	expr.fold(|layer: ExprLayer<i64>| match layer {
+		ExprLayer::Zero => 0,
+		ExprLayer::Num(n) => n,
+		ExprLayer::Neg(n) => -n,
+		ExprLayer::Add(a, b) => a + b,
+		ExprLayer::Sum(items) => items.into_iter().sum(),
+		ExprLayer::If {
+			cond,
+			then,
+			otherwise,
+		} => {
+			if cond != 0 {
+				then
+			} else {
+				otherwise.unwrap_or(0)
+			}
+		}
+		ExprLayer::Let(bindings, body) => bindings.values().sum::<i64>() + body,
+		ExprLayer::Pair((n, inner)) => n as i64 * inner,
+	})
+}
+
+#[test]
+fn test_deep_fold() {
+	let mut expr = Expr::Num(1);
+	for _ in 0..DEEP {
+		expr = Expr::Neg(Box::new(Expr::Add(Box::new(expr), Box::new(Expr::Num(1)))));
+	}
+	// Each level maps `x` to `-(x + 1)`.
+	let mut expected = 1;
+	for _ in 0..DEEP {
+		expected = -(expected + 1);
+	}
+	assert_eq!(eval(expr), expected);
+}
+
+#[test]
+fn test_shapes() {
+	let num = |n| Box::new(Expr::Num(n));
+	let mut bindings = BTreeMap::new();
+	bindings.insert("a".to_string(), Expr::Num(2));
+	bindings.insert("b".to_string(), Expr::Sum(vec![Expr::Num(3), Expr::Zero]));
+	let expr = Expr::Sum(vec![
+		Expr::If {
+			cond: num(0),
+			then: num(100),
+			otherwise: Some(num(7)),
+		},
+		Expr::If {
+			cond: num(1),
+			then: num(10),
+			otherwise: None,
+		},
+		Expr::Let(bindings, num(1)),
+		Expr::Pair((3, num(4))),
+	]);
+	assert_eq!(eval(expr), 7 + 10 + 6 + 12);
+}
+
+#[test]
+fn test_map_children() {
+	let expr = Expr::Add(
+		Box::new(Expr::Num(1)),
+		Box::new(Expr::Neg(Box::new(Expr::Num(2)))),
+	);
+	// Only one level is mapped.
+	let mapped = expr.map_children(|child| Expr::Neg(Box::new(child)));
+	assert_eq!(
+		mapped,
+		Expr::Add(
+			Box::new(Expr::Neg(Box::new(Expr::Num(1)))),
+			Box::new(Expr::Neg(Box::new(Expr::Neg(Box::new(Expr::Num(2))))))
+		)
+	);
+	// Taking apart and putting back together gives the same value.
+	let layer = mapped.into_layer();
+	assert!(matches!(layer, ExprLayer::Add(..)));
+	let back = Expr::from_layer(layer);
+	assert!(matches!(back, Expr::Add(..)));
+}
+
+#[derive(decurse::Recursive)]
+struct Tree<T> {
+	value: T,
+	children: Vec<Tree<T>>,
+}
+
+#[test]
+fn test_generic_struct() {
+	let mut tree = Tree {
+		value: 1u64,
+		children: Vec::new(),
+	};
+	for value in 2..=DEEP as u64 {
+		tree = Tree {
+			value,
+			children: vec![
+				tree,
+				Tree {
+					value: 0,
+					children: Vec::new(),
+				},
+			],
+		};
+	}
+	let (sum, depth) = tree.fold(|layer: TreeLayer<u64, (u64, usize)>| {
+		let depth = layer.children.iter().map(|c| c.1).max().unwrap_or(0);
+		let sum: u64 = layer.children.iter().map(|c| c.0).sum();
+		(layer.value + sum, depth + 1)
+	});
+	assert_eq!(sum, (DEEP as u64) * (DEEP as u64 + 1) / 2);
+	assert_eq!(depth, DEEP);
+	// The fold took the tree apart level by level, so dropping it didn't need `DeepDrop`.
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "serde")]
+
+use decurse::{DeepDebug, DeepDrop, DeepPartialEq};
+use serde::{Deserialize, Serialize};
+
+const DEEP: usize = 200000;
+
+#[derive(Serialize, Deserialize, DeepDrop)]
+struct Node {
+	value: u32,
+	#[serde(with = "decurse::serde::nested")]
+	next: Option<Box<Node>>,
+}
+
+fn list(len: u32) -> Option<Box<Node>> {
+	let mut list = None;
+	for value in 0..len {
+		list = Some(Box::new(Node { value, next: list }));
+	}
+	list
+}
+
+fn length(mut list: &Option<Box<Node>>) -> usize {
+	let mut length = 0;
+	while let Some(node) = list {
+		length += 1;
+		list = &node.next;
+	}
+	length
+}
+
+fn from_json<'de, T: Deserialize<'de>>(json: &'de str) -> serde_json::Result<T> {
+	let mut deserializer = serde_json::Deserializer::from_str(json);
+	deserializer.disable_recursion_limit();
+	T::deserialize(&mut deserializer)
+}
+
+#[test]
+fn test_list() {
+	let list = list(DEEP as u32).unwrap();
+	let json = serde_json::to_string(&list).unwrap();
+	assert!(json.starts_with(r#"{"value":199999,"next":{"value":199998,"#));
+	let parsed: Node = from_json(&json).unwrap();
+	assert_eq!(parsed.value, DEEP as u32 - 1);
+	assert_eq!(length(&parsed.next), DEEP - 1);
+}
+
+#[derive(Serialize, Deserialize, DeepDrop, DeepPartialEq, DeepDebug)]
+enum Expr {
+	Num(i64),
+	Neg(#[serde(with = "decurse::serde::nested")] Box<Expr>),
+	Sum(#[serde(with = "decurse::serde::nested")] Vec<Expr>),
+}
+
+impl Default for Expr {
+	fn default() -> Self {
+		Expr::Num(0)
+	}
+}
+
+#[test]
+fn test_enum() {
+	let mut expr = Expr::Num(1);
+	for i in 0..DEEP {
+		expr = if i % 2 == 0 {
+			Expr::Neg(Box::new(expr))
+		} else {
+			Expr::Sum(vec![Expr::Num(2), expr])
+		};
+	}
+	let json = serde_json::to_string(&expr).unwrap();
+	assert!(json.starts_with(r#"{"Sum":[{"Num":2},{"Neg":{"Sum":["#));
+	let parsed: Expr = from_json(&json).unwrap();
+	assert!(parsed == expr);
+	let small: Expr = from_json(r#"{"Sum":[{"Num":1},{"Neg":{"Num":2}}]}"#).unwrap();
+	assert_eq!(
+		small,
+		Expr::Sum(vec![Expr::Num(1), Expr::Neg(Box::new(Expr::Num(2)))])
+	);
+}
+
+#[test]
+fn test_max_depth() {
+	let json = serde_json::to_string(&list(10)).unwrap();
+	// The `next` of the last node counts too.
+	let parse = |max| decurse::serde::with_max_depth(max, || from_json::<Node>(&json));
+	assert!(parse(10).is_ok());
+	let error = parse(9).err().unwrap().to_string();
+	assert!(error.starts_with("Decurse: nested deeper than the maximum depth of 9."));
+	// The limit is gone afterwards, and the depth is back to zero after the error.
+	assert!(from_json::<Node>(&json).is_ok());
+	assert!(parse(10).is_ok());
+
+	let list = list(10);
+	let serialize = |max| decurse::serde::with_max_depth(max, || serde_json::to_string(&list));
+	assert!(serialize(10).is_ok());
+	assert!(serialize(5).is_err());
+}
+
+#[test]
+fn test_nested_max_depth() {
+	let json = serde_json::to_string(&list(3)).unwrap();
+	decurse::serde::with_max_depth(100, || {
+		// The inner limit is relative to where it's set, and applies until it returns.
+		assert!(decurse::serde::with_max_depth(2, || from_json::<Node>(&json)).is_err());
+		assert!(from_json::<Node>(&json).is_ok());
+	});
+}
@@ -0,0 +1,130 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use decurse::{decurse, State};
+
+#[decurse(state(Vec<u32>))]
+fn collatz(x: u32) {
+	State::<Vec<u32>>::with(|path| path.push(x));
+	if x > 1 {
+		collatz(if x % 2 == 0 { x / 2 } else { 3 * x + 1 });
+	}
+}
+
+#[test]
+fn test_accumulator() {
+	let mut path = vec![0];
+	collatz(&mut path, 27);
+	assert_eq!(path.len(), 113);
+	assert_eq!(path[..3], [0, 27, 82]);
+	assert_eq!(path.last(), Some(&1));
+	assert!(!State::<Vec<u32>>::in_scope());
+}
+
+#[derive(Default)]
+struct Count(u64);
+
+#[decurse(state(Count))]
+fn count_nodes(depth: u32) -> u32 {
+	State::<Count>::with(|count| count.0 += 1);
+	if depth == 0 {
+		0
+	} else {
+		count_nodes(depth - 1) + 1
+	}
+}
+
+#[test]
+fn test_deep() {
+	let mut count = Count(0);
+	assert_eq!(count_nodes(&mut count, 200000), 200000);
+	assert_eq!(count.0, 200001);
+}
+
+#[decurse(state(Count), abortable)]
+fn count_forever(x: u32) {
+	State::<Count>::with(|count| count.0 += 1);
+	count_forever(x + 1);
+}
+
+#[test]
+fn test_abortable() {
+	let handle = decurse::AbortHandle::new();
+	let mut count = Count(0);
+	let abort = handle.clone();
+	let result = std::thread::spawn(move || {
+		let result = count_forever_abortable(&mut count, 0, abort);
+		(result, count.0)
+	});
+	std::thread::sleep(std::time::Duration::from_millis(50));
+	handle.abort();
+	let (result, count) = result.join().unwrap();
+	assert!(result.is_err());
+	assert!(count > 0);
+}
+
+#[test]
+fn test_nested_scopes() {
+	let (inner, outer) = State::scope_owned(1u32, || {
+		State::<u32>::with(|x| *x += 1);
+		let ((), inner) = State::scope_owned(10u32, || State::<u32>::with(|x| *x += 1));
+		State::<u32>::with(|x| *x += 1);
+		inner
+	});
+	assert_eq!(inner, 11);
+	assert_eq!(outer, 3);
+}
+
+#[test]
+fn test_panic_restores() {
+	let mut value = 5u32;
+	let result = panic::catch_unwind(AssertUnwindSafe(|| {
+		State::scope(&mut value, || {
+			State::<u32>::with(|x| *x += 1);
+			panic!("oops");
+		})
+	}));
+	assert!(result.is_err());
+	assert_eq!(value, 6);
+	assert!(!State::<u32>::in_scope());
+}
+
+#[test]
+#[should_panic(expected = "Decurse: no state of this type in scope.")]
+fn test_outside_scope() {
+	State::<String>::with(|s| s.push('a'));
+}
+
+#[test]
+#[should_panic(expected = "Decurse: state already borrowed.")]
+fn test_borrowed_twice() {
+	State::scope_owned(0u8, || State::<u8>::with(|_| State::<u8>::with(|_| ())));
+}
+
+// Without `Default`, as the state is lent rather than swapped out.
+struct Tally {
+	name: &'static str,
+	count: u32,
+}
+
+#[decurse(state(Tally))]
+fn tally(x: u32) -> u32 {
+	State::<Tally>::with(|tally| tally.count += x);
+	if x == 0 {
+		0
+	} else {
+		tally(x - 1) + 1
+	}
+}
+
+#[test]
+fn test_non_default() {
+	let mut sum = Tally {
+		name: "sum",
+		count: 0,
+	};
+	assert_eq!(tally(&mut sum, 1000), 1000);
+	assert_eq!(sum.count, 500500);
+	assert_eq!(sum.name, "sum");
+	let ((), sum) = State::scope_owned(sum, || State::<Tally>::with(|tally| tally.count = 1));
+	assert_eq!(sum.count, 1);
+}
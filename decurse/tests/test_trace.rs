@@ -0,0 +1,112 @@
+#![cfg(feature = "tracing")]
+
+use std::sync::{Arc, Mutex};
+
+use decurse::{decurse, trace};
+use tracing::{
+	field::{Field, Visit},
+	span::{Attributes, Id, Record},
+	Event, Metadata, Subscriber,
+};
+
+// Records the fields of every span, and the order spans are entered and exited.
+#[derive(Clone, Default)]
+struct Recorder {
+	spans: Arc<Mutex<Vec<String>>>,
+	log: Arc<Mutex<Vec<String>>>,
+}
+
+struct Fields<'a>(&'a mut String);
+
+impl Visit for Fields<'_> {
+	fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+		self.0.push_str(&format!(" {}={:?}", field.name(), value));
+	}
+	fn record_str(&mut self, field: &Field, value: &str) {
+		self.0.push_str(&format!(" {}={}", field.name(), value));
+	}
+}
+
+impl Subscriber for Recorder {
+	fn enabled(&self, _: &Metadata<'_>) -> bool {
+		true
+	}
+	fn new_span(&self, span: &Attributes<'_>) -> Id {
+		let mut fields = String::new();
+		span.record(&mut Fields(&mut fields));
+		let mut spans = self.spans.lock().unwrap();
+		spans.push(fields);
+		Id::from_u64(spans.len() as u64)
+	}
+	fn record(&self, span: &Id, values: &Record<'_>) {
+		let mut spans = self.spans.lock().unwrap();
+		values.record(&mut Fields(&mut spans[span.into_u64() as usize - 1]));
+	}
+	fn record_follows_from(&self, _: &Id, _: &Id) {}
+	fn event(&self, _: &Event<'_>) {}
+	fn enter(&self, span: &Id) {
+		self.log
+			.lock()
+			.unwrap()
+			.push(format!("enter {}", span.into_u64()));
+	}
+	fn exit(&self, span: &Id) {
+		self.log
+			.lock()
+			.unwrap()
+			.push(format!("exit {}", span.into_u64()));
+	}
+}
+
+fn take(list: &Mutex<Vec<String>>) -> Vec<String> {
+	std::mem::take(&mut *list.lock().unwrap())
+}
+
+#[decurse(debug_args(x))]
+fn count_down(x: u32, _unused: &'static str) -> u32 {
+	if x == 0 {
+		0
+	} else {
+		count_down(x - 1, "") + 1
+	}
+}
+
+// The settings are global, so everything is in one test.
+#[test]
+fn test_spans() {
+	let recorder = Recorder::default();
+	tracing::subscriber::with_default(recorder.clone(), || {
+		assert_eq!(count_down(2, ""), 2);
+		assert_eq!(
+			take(&recorder.spans),
+			[
+				" function=test_trace::count_down depth=1 args=x = 2",
+				" function=test_trace::count_down depth=2 args=x = 1",
+				" function=test_trace::count_down depth=3 args=x = 0",
+			]
+		);
+		// Entered on each poll, exited when it pends or completes.
+		// `tracing` also enters the span while the frame is dropped.
+		assert_eq!(
+			take(&recorder.log),
+			[
+				"enter 1", "exit 1", "enter 2", "exit 2", "enter 3", "exit 3", "enter 3", "exit 3",
+				"enter 2", "exit 2", "enter 2", "exit 2", "enter 1", "exit 1", "enter 1", "exit 1",
+			]
+		);
+
+		trace::set_sample_every(10);
+		trace::set_max_depth(Some(50));
+		assert_eq!(count_down(99999, ""), 99999);
+		let depths: Vec<String> = take(&recorder.spans)
+			.iter()
+			.map(|s| s.split(' ').nth(2).unwrap().to_owned())
+			.collect();
+		assert_eq!(
+			depths,
+			["depth=1", "depth=11", "depth=21", "depth=31", "depth=41"]
+		);
+		trace::set_sample_every(1);
+		trace::set_max_depth(None);
+	});
+}
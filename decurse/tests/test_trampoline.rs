@@ -0,0 +1,71 @@
+use decurse::Trampoline;
+
+#[test]
+fn test_done() {
+	assert_eq!(Trampoline::done(5).run(), 5);
+	assert_eq!(Trampoline::done(5).map(|x| x * 2).run(), 10);
+}
+
+#[test]
+fn test_fibonacci() {
+	fn fibonacci(x: u32) -> Trampoline<u32> {
+		if x == 0 || x == 1 {
+			Trampoline::done(1)
+		} else {
+			Trampoline::suspend(move || fibonacci(x - 1))
+				.and_then(move |a| fibonacci(x - 2).map(move |b| a + b))
+		}
+	}
+	assert_eq!(fibonacci(10).run(), 89);
+}
+
+#[test]
+fn test_long_chain() {
+	let mut t = Trampoline::done(0u64);
+	for i in 0..1000000 {
+		t = t.and_then(move |x| Trampoline::done(x + i));
+	}
+	assert_eq!(t.run(), 499999500000);
+}
+
+#[test]
+fn test_deep_nesting() {
+	fn sum(x: u64) -> Trampoline<u64> {
+		if x == 0 {
+			Trampoline::done(0)
+		} else {
+			Trampoline::suspend(move || sum(x - 1)).map(move |s| s + x)
+		}
+	}
+	assert_eq!(sum(500000).run(), 125000250000);
+}
+
+#[test]
+fn test_mutual() {
+	fn is_even(x: u64) -> Trampoline<bool> {
+		if x == 0 {
+			Trampoline::done(true)
+		} else {
+			Trampoline::suspend(move || is_odd(x - 1))
+		}
+	}
+	fn is_odd(x: u64) -> Trampoline<bool> {
+		if x == 0 {
+			Trampoline::done(false)
+		} else {
+			Trampoline::suspend(move || is_even(x - 1))
+		}
+	}
+	assert!(is_even(1000000).run());
+	assert!(!is_odd(1000000).run());
+}
+
+#[test]
+fn test_drop_long_chain() {
+	let mut t = Trampoline::done(0u64);
+	for i in 0..1000000 {
+		t = t.and_then(move |x| Trampoline::done(x + i));
+	}
+	// Dropped without running.
+	drop(t);
+}
@@ -152,3 +152,19 @@ fn test_borrow_current() {
 //         }
 //     }
 // }
+
+#[test]
+fn test_abortable() {
+	#[decurse_unsound(abortable)]
+	fn sum<'a>(s: &'a [u64]) -> u64 {
+		match s.split_first() {
+			Some((first, rest)) => first + sum(rest),
+			None => 0,
+		}
+	}
+	let arr: Vec<u64> = (0..100000).collect();
+	let handle = decurse::AbortHandle::new();
+	assert_eq!(sum_abortable(&arr, handle.clone()), Ok(4999950000));
+	handle.abort();
+	assert_eq!(sum_abortable(&arr, handle), Err(decurse::Cancelled));
+}
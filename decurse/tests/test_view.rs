@@ -0,0 +1,142 @@
+use std::{
+	collections::HashSet,
+	panic::{catch_unwind, AssertUnwindSafe},
+	rc::Rc,
+	sync::Arc,
+};
+
+use decurse::view::{ArcStr, RcSlice, RcTreeBuilder, RcTreeRef};
+
+const DEEP: usize = 200000;
+
+#[test]
+fn test_rc_slice() {
+	let slice: RcSlice<u32> = (0..10).collect();
+	assert_eq!(&*slice.slice(2..5), &[2, 3, 4]);
+	assert_eq!(&*slice.slice(2..=4), &[2, 3, 4]);
+	// Ranges are relative to the view.
+	let part = slice.slice(3..);
+	assert_eq!(&*part.slice(..2), &[3, 4]);
+	assert_eq!(part.slice(1..3).range(), 4..6);
+	assert!(Rc::ptr_eq(part.whole(), slice.whole()));
+	let (left, right) = part.split_at(3);
+	assert_eq!((&*left, &*right), (&[3, 4, 5][..], &[6, 7, 8, 9][..]));
+	assert_eq!(
+		right.split_last().map(|(last, rest)| (*last, rest.len())),
+		Some((9, 3))
+	);
+	assert!(slice.slice(5..5).split_first().is_none());
+	// Compared and hashed as slices.
+	assert_eq!(slice.slice(1..3), RcSlice::from(vec![1, 2]));
+	assert!(slice.slice(1..3) < slice.slice(2..3));
+	let set: HashSet<_> = vec![slice.slice(0..2), RcSlice::from(vec![0, 1])]
+		.into_iter()
+		.collect();
+	assert_eq!(set.len(), 1);
+	assert_eq!(format!("{:?}", slice.slice(..2)), "[0, 1]");
+
+	let result = catch_unwind(AssertUnwindSafe(|| part.slice(2..8)));
+	assert!(result.is_err());
+}
+
+#[test]
+#[should_panic(expected = "range 0..usize::MAX + 1 out of bounds of a view of length 10.")]
+fn test_rc_slice_range_overflow() {
+	// The end of the range doesn't fit in a `usize`.
+	let slice: RcSlice<u32> = (0..10).collect();
+	slice.slice(..=usize::MAX);
+}
+
+#[decurse::decurse]
+fn count_even(values: RcSlice<u64>) -> usize {
+	match values.split_first() {
+		Some((first, rest)) => count_even(rest) + (first % 2 == 0) as usize,
+		None => 0,
+	}
+}
+
+#[test]
+fn test_rc_slice_recursion() {
+	let values: RcSlice<u64> = (0..DEEP as u64).collect();
+	assert_eq!(count_even(values), DEEP / 2);
+}
+
+#[test]
+fn test_arc_str() {
+	let s = ArcStr::from("héllo wörld");
+	let (hello, world) = s.split_at(6);
+	assert_eq!(hello, "héllo");
+	assert_eq!(world.substr(1..), "wörld");
+	assert_eq!(world.substr(1..).range(), 7..13);
+	assert!(Arc::ptr_eq(world.whole(), s.whole()));
+	assert_eq!(format!("{} {:?}", hello, hello), "héllo \"héllo\"");
+	assert_eq!(s.substr(..3), "hé");
+	let (a, b) = (ArcStr::from("a"), ArcStr::from(String::from("b")));
+	assert!(a < b);
+	let set: HashSet<ArcStr> = vec![hello.clone(), ArcStr::from("héllo")]
+		.into_iter()
+		.collect();
+	assert!(set.contains("héllo"));
+	assert_eq!(set.len(), 1);
+
+	// Not on a character boundary.
+	assert!(catch_unwind(|| s.substr(2..)).is_err());
+	// Can be sent to other threads.
+	let sent = std::thread::spawn(move || world.to_uppercase())
+		.join()
+		.unwrap();
+	assert_eq!(sent, " WÖRLD");
+}
+
+#[decurse::decurse]
+fn count_words(text: ArcStr) -> usize {
+	match text.find(' ') {
+		Some(space) => count_words(text.substr(space + 1..)) + 1,
+		None => 1,
+	}
+}
+
+#[test]
+fn test_arc_str_recursion() {
+	let text = ArcStr::from(vec!["word"; DEEP].join(" "));
+	assert_eq!(count_words(text), DEEP);
+}
+
+#[decurse::decurse]
+fn sum_tree(node: RcTreeRef<u64>) -> u64 {
+	let mut sum = *node;
+	for child in node.children() {
+		sum += sum_tree(child);
+	}
+	sum
+}
+
+#[test]
+fn test_rc_tree_ref() {
+	let mut builder = RcTreeBuilder::new(1);
+	let mut last = 0;
+	for value in 2..=DEEP as u64 {
+		last = builder.push(last, value);
+		builder.push(last, 0);
+	}
+	let root = builder.build();
+	assert_eq!(root.children().len(), 1);
+	assert_eq!(sum_tree(root.clone()), DEEP as u64 * (DEEP as u64 + 1) / 2);
+
+	let first = root.child(0).unwrap();
+	assert_eq!((*first, first.index()), (2, 1));
+	assert!(first.parent().unwrap().ptr_eq(&root));
+	assert!(first.root().ptr_eq(&root));
+	assert!(root.parent().is_none());
+	assert!(root.child(1).is_none());
+	assert_eq!(first.children().len(), 2);
+	assert!(!first.ptr_eq(&first.child(1).unwrap()));
+	assert_eq!(format!("{:?}", first), "RcTreeRef { index: 1, value: 2 }");
+}
+
+#[test]
+#[should_panic(expected = "Decurse: the parent wasn't added to the tree.")]
+fn test_rc_tree_missing_parent() {
+	let mut builder = RcTreeBuilder::new(0);
+	builder.push(1, 1);
+}
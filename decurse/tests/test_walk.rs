@@ -0,0 +1,239 @@
+use std::cell::RefCell;
+
+use decurse::walk::{in_order, post_order, pre_order, Control, Walk};
+
+const DEEP: usize = 200000;
+
+struct Tree {
+	value: u32,
+	children: Vec<Tree>,
+}
+
+fn leaf(value: u32) -> Tree {
+	Tree {
+		value,
+		children: Vec::new(),
+	}
+}
+
+//     1
+//   2   5
+//  3 4   6
+fn sample() -> Tree {
+	Tree {
+		value: 1,
+		children: vec![
+			Tree {
+				value: 2,
+				children: vec![leaf(3), leaf(4)],
+			},
+			Tree {
+				value: 5,
+				children: vec![leaf(6)],
+			},
+		],
+	}
+}
+
+fn children<'a>(tree: &&'a Tree) -> std::slice::Iter<'a, Tree> {
+	tree.children.iter()
+}
+
+fn values(nodes: Vec<&Tree>) -> Vec<u32> {
+	nodes.into_iter().map(|tree| tree.value).collect()
+}
+
+#[test]
+fn test_orders() {
+	let tree = sample();
+	assert_eq!(values(pre_order(&tree, children)), [1, 2, 3, 4, 5, 6]);
+	assert_eq!(values(post_order(&tree, children)), [3, 4, 2, 6, 5, 1]);
+}
+
+#[test]
+fn test_callbacks() {
+	let tree = sample();
+	let events = RefCell::new(Vec::new());
+	let finished = Walk::new(children)
+		.enter(|tree, depth| {
+			events
+				.borrow_mut()
+				.push(format!("enter {} {}", tree.value, depth));
+			Control::Continue
+		})
+		.between(|tree, _| {
+			events.borrow_mut().push(format!("between {}", tree.value));
+			Control::Continue
+		})
+		.exit(|tree, _| {
+			events.borrow_mut().push(format!("exit {}", tree.value));
+			Control::Continue
+		})
+		.run(&tree);
+	assert!(finished);
+	assert_eq!(
+		events.into_inner(),
+		[
+			"enter 1 0",
+			"enter 2 1",
+			"enter 3 2",
+			"between 3",
+			"exit 3",
+			"between 2",
+			"enter 4 2",
+			"between 4",
+			"exit 4",
+			"exit 2",
+			"between 1",
+			"enter 5 1",
+			"enter 6 2",
+			"between 6",
+			"exit 6",
+			"between 5",
+			"exit 5",
+			"exit 1",
+		]
+	);
+}
+
+#[test]
+fn test_prune_and_stop() {
+	let tree = sample();
+	// Pruning in `enter` skips the children, but the node is still exited.
+	let mut exited = Vec::new();
+	Walk::new(children)
+		.enter(|tree, _| match tree.value {
+			2 => Control::Prune,
+			_ => Control::Continue,
+		})
+		.exit(|tree, _| {
+			exited.push(tree.value);
+			Control::Continue
+		})
+		.run(&tree);
+	assert_eq!(exited, [2, 6, 5, 1]);
+
+	// Pruning in `between` skips the other children.
+	let mut entered = Vec::new();
+	Walk::new(children)
+		.enter(|tree, _| {
+			entered.push(tree.value);
+			Control::Continue
+		})
+		.between(|tree, _| match tree.value {
+			2 | 1 => Control::Prune,
+			_ => Control::Continue,
+		})
+		.run(&tree);
+	assert_eq!(entered, [1, 2, 3]);
+
+	// Nothing is called after a stop.
+	let events = RefCell::new(Vec::new());
+	let finished = Walk::new(children)
+		.enter(|tree, _| {
+			events.borrow_mut().push(tree.value);
+			match tree.value {
+				4 => Control::Stop,
+				_ => Control::Continue,
+			}
+		})
+		.exit(|tree, _| {
+			events.borrow_mut().push(tree.value * 10);
+			Control::Continue
+		})
+		.run(&tree);
+	assert!(!finished);
+	assert_eq!(events.into_inner(), [1, 2, 3, 30, 4]);
+}
+
+#[test]
+fn test_deep() {
+	let mut tree = leaf(0);
+	for value in 1..DEEP as u32 {
+		tree = Tree {
+			value,
+			children: vec![tree, leaf(0)],
+		};
+	}
+	assert_eq!(pre_order(&tree, children).len(), 2 * DEEP - 1);
+	let mut max_depth = 0;
+	Walk::new(children)
+		.exit(|_, depth| {
+			max_depth = max_depth.max(depth);
+			Control::Continue
+		})
+		.run(&tree);
+	assert_eq!(max_depth, DEEP - 1);
+	// Stopping at the bottom unwinds every level.
+	let finished = Walk::new(children)
+		.enter(|_, depth| match depth {
+			d if d == DEEP - 1 => Control::Stop,
+			_ => Control::Continue,
+		})
+		.run(&tree);
+	assert!(!finished);
+	// The drop generated for `Tree` would recurse, so take it apart on a list.
+	let mut stack = vec![tree];
+	while let Some(mut tree) = stack.pop() {
+		stack.append(&mut tree.children);
+	}
+}
+
+struct Binary {
+	value: u32,
+	left: Option<Box<Binary>>,
+	right: Option<Box<Binary>>,
+}
+
+fn binary(value: u32, left: Option<Binary>, right: Option<Binary>) -> Option<Binary> {
+	Some(Binary {
+		value,
+		left: left.map(Box::new),
+		right: right.map(Box::new),
+	})
+}
+
+#[test]
+fn test_in_order() {
+	//     4
+	//   2   5
+	//    3   6
+	let tree = binary(
+		4,
+		binary(2, None, binary(3, None, None)),
+		binary(5, None, binary(6, None, None)),
+	)
+	.unwrap();
+	let nodes = in_order(&tree, |tree: &&Binary| {
+		(tree.left.as_deref(), tree.right.as_deref())
+	});
+	let values: Vec<_> = nodes.iter().map(|tree| tree.value).collect();
+	assert_eq!(values, [2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn test_graph() {
+	// A grid where every cell is connected to its neighbours, walked depth first from a corner.
+	const SIZE: usize = 300;
+	let visited = RefCell::new(vec![vec![false; SIZE]; SIZE]);
+	let mut count = 0;
+	Walk::new(|&(x, y): &(usize, usize)| {
+		let neighbours = vec![
+			(x + 1, y),
+			(x, y + 1),
+			(x.wrapping_sub(1), y),
+			(x, y.wrapping_sub(1)),
+		];
+		neighbours
+			.into_iter()
+			.filter(|&(x, y)| x < SIZE && y < SIZE)
+			.filter(|&(x, y)| !visited.borrow()[x][y])
+	})
+	.enter(|&(x, y), _| {
+		visited.borrow_mut()[x][y] = true;
+		count += 1;
+		Control::Continue
+	})
+	.run((0, 0));
+	assert_eq!(count, SIZE * SIZE);
+}
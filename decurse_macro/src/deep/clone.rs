@@ -0,0 +1,101 @@
+use super::{scope, variants, with_bound, Names, Shape};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Error};
+
+// An expression cloning what the reference points to.
+fn clone_expr(shape: &Shape, r: TokenStream, names: &mut Names) -> TokenStream {
+	let scope = scope();
+	match shape {
+		// Cloning an `Rc` doesn't clone its contents.
+		Shape::Other | Shape::Cell(..) | Shape::Rc(..) => quote!(::core::clone::Clone::clone(#r)),
+		Shape::Leaf => quote!(::decurse::deep::DeepClone::deep_clone(#r, #scope).await),
+		Shape::Box(inner) => {
+			let inner = clone_expr(inner, quote!(&**#r), names);
+			quote!(::std::boxed::Box::new(#inner))
+		}
+		Shape::Option(inner) => {
+			let x = names.next();
+			let inner = clone_expr(inner, quote!(#x), names);
+			quote!(match ::core::option::Option::as_ref(#r) {
+				::core::option::Option::Some(#x) => ::core::option::Option::Some(#inner),
+				::core::option::Option::None => ::core::option::Option::None,
+			})
+		}
+		Shape::Seq(inner, _) => {
+			let (items, x) = (names.next(), names.next());
+			let inner = clone_expr(inner, quote!(#x), names);
+			quote!({
+				let mut #items = ::std::vec::Vec::new();
+				for #x in #r {
+					#items.push(#inner);
+				}
+				::core::iter::FromIterator::from_iter(#items)
+			})
+		}
+		Shape::Map(key, value, _) => {
+			let (items, k, v) = (names.next(), names.next(), names.next());
+			let key = clone_expr(key, quote!(#k), names);
+			let value = clone_expr(value, quote!(#v), names);
+			quote!({
+				let mut #items = ::std::vec::Vec::new();
+				for (#k, #v) in #r {
+					#items.push((#key, #value));
+				}
+				::core::iter::FromIterator::from_iter(#items)
+			})
+		}
+		Shape::Tuple(elems) => {
+			let xs: Vec<_> = elems.iter().map(|_| names.next()).collect();
+			let clones = elems
+				.iter()
+				.zip(&xs)
+				.map(|(elem, x)| clone_expr(elem, quote!(#x), names));
+			quote!({
+				let (#(#xs,)*) = #r;
+				(#(#clones,)*)
+			})
+		}
+	}
+}
+
+pub(crate) fn derive_deep_clone(input: DeriveInput) -> Result<TokenStream, Error> {
+	let variants = variants(&input, false)?;
+	let name = &input.ident;
+	let generics = with_bound(&input.generics, quote!(::core::clone::Clone));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let scope = scope();
+	let mut names = Names::default();
+	let arms = variants.iter().map(|variant| {
+		let pattern = variant.pattern(false);
+		let path = &variant.path;
+		let fields = variant.fields.iter().map(|f| {
+			let member = &f.member;
+			let binding = &f.binding;
+			let clone = clone_expr(&f.shape, quote!(#binding), &mut names);
+			quote!(#member: #clone)
+		});
+		quote!(#pattern => #path { #(#fields,)* })
+	});
+	Ok(quote! {
+		impl #impl_generics ::decurse::deep::DeepClone for #name #ty_generics #where_clause {
+			fn deep_clone<'__decurse>(
+				&'__decurse self,
+				#scope: &::decurse::deep::Scope<'__decurse>,
+			) -> ::decurse::deep::ScopedCall<'__decurse, Self> {
+				let __decurse_inner = ::core::clone::Clone::clone(#scope);
+				#scope.call(async move {
+					let #scope = &__decurse_inner;
+					match self {
+						#(#arms,)*
+					}
+				})
+			}
+		}
+		impl #impl_generics ::core::clone::Clone for #name #ty_generics #where_clause {
+			fn clone(&self) -> Self {
+				::decurse::deep::Scope::run(|#scope| ::decurse::deep::DeepClone::deep_clone(self, #scope))
+			}
+		}
+	})
+}
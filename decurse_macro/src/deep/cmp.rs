@@ -0,0 +1,315 @@
+use super::{scope, variants, with_bound, Kind, Names, Shape, Variant};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Error};
+
+// Which comparison is derived.
+#[derive(Clone, Copy)]
+enum Mode {
+	Eq,
+	PartialCmp,
+	Cmp,
+}
+
+impl Mode {
+	fn output(self) -> TokenStream {
+		match self {
+			Mode::Eq => quote!(bool),
+			Mode::PartialCmp => quote!(::core::option::Option<::core::cmp::Ordering>),
+			Mode::Cmp => quote!(::core::cmp::Ordering),
+		}
+	}
+	// The result when the values are equal, also used as a pattern.
+	fn equal(self) -> TokenStream {
+		self.ordering(quote!(Equal), quote!(true))
+	}
+	fn less(self) -> TokenStream {
+		self.ordering(quote!(Less), quote!(false))
+	}
+	fn greater(self) -> TokenStream {
+		self.ordering(quote!(Greater), quote!(false))
+	}
+	fn ordering(self, ordering: TokenStream, eq: TokenStream) -> TokenStream {
+		match self {
+			Mode::Eq => eq,
+			Mode::PartialCmp => {
+				quote!(::core::option::Option::Some(::core::cmp::Ordering::#ordering))
+			}
+			Mode::Cmp => quote!(::core::cmp::Ordering::#ordering),
+		}
+	}
+	// Compare with the std trait.
+	fn std(self, a: TokenStream, b: TokenStream) -> TokenStream {
+		match self {
+			Mode::Eq => quote!(::core::cmp::PartialEq::eq(#a, #b)),
+			Mode::PartialCmp => quote!(::core::cmp::PartialOrd::partial_cmp(#a, #b)),
+			Mode::Cmp => quote!(::core::cmp::Ord::cmp(#a, #b)),
+		}
+	}
+	// Compare in a new frame of the scope.
+	fn deep(self, a: TokenStream, b: TokenStream) -> TokenStream {
+		let scope = scope();
+		match self {
+			Mode::Eq => quote!(::decurse::deep::DeepPartialEq::deep_eq(#a, #b, #scope).await),
+			Mode::PartialCmp => {
+				quote!(::decurse::deep::DeepPartialOrd::deep_partial_cmp(#a, #b, #scope).await)
+			}
+			Mode::Cmp => quote!(::decurse::deep::DeepOrd::deep_cmp(#a, #b, #scope).await),
+		}
+	}
+	// Return the result if it isn't equal.
+	fn check(self, result: TokenStream) -> TokenStream {
+		let equal = self.equal();
+		quote!(match #result {
+			#equal => {}
+			__decurse_result => return __decurse_result,
+		})
+	}
+}
+
+// Code comparing what the references point to, returning from the frame at the first difference.
+fn compare(
+	mode: Mode,
+	shape: &Shape,
+	a: TokenStream,
+	b: TokenStream,
+	names: &mut Names,
+) -> TokenStream {
+	match shape {
+		Shape::Other | Shape::Cell(..) => mode.check(mode.std(a, b)),
+		Shape::Leaf => mode.check(mode.deep(a, b)),
+		// Comparing an `Rc` compares its contents.
+		Shape::Box(inner) | Shape::Rc(inner, _) => {
+			compare(mode, inner, quote!(&**#a), quote!(&**#b), names)
+		}
+		Shape::Option(inner) => {
+			let (x, y) = (names.next(), names.next());
+			let inner = compare(mode, inner, quote!(#x), quote!(#y), names);
+			let (less, greater) = (mode.less(), mode.greater());
+			quote!(match (::core::option::Option::as_ref(#a), ::core::option::Option::as_ref(#b)) {
+				(::core::option::Option::Some(#x), ::core::option::Option::Some(#y)) => { #inner }
+				(::core::option::Option::None, ::core::option::Option::None) => {}
+				(::core::option::Option::None, ::core::option::Option::Some(_)) => return #less,
+				(::core::option::Option::Some(_), ::core::option::Option::None) => return #greater,
+			})
+		}
+		// A `HashSet` looks its items up, leave it to its own `PartialEq`.
+		Shape::Seq(_, Kind::Hash) => mode.check(mode.std(a, b)),
+		Shape::Map(_, value, Kind::Hash) => match mode {
+			// Like the `PartialEq` of `HashMap`, look the keys up and compare the values.
+			Mode::Eq => {
+				let (k, x, y) = (names.next(), names.next(), names.next());
+				let inner = compare(mode, value, quote!(#x), quote!(#y), names);
+				quote! {
+					if (#a).len() != (#b).len() {
+						return false;
+					}
+					for (#k, #x) in #a {
+						match (#b).get(#k) {
+							::core::option::Option::Some(#y) => { #inner }
+							::core::option::Option::None => return false,
+						}
+					}
+				}
+			}
+			_ => mode.check(mode.std(a, b)),
+		},
+		Shape::Seq(..) | Shape::Map(..) => {
+			let (x, y) = (names.next(), names.next());
+			let inner = match shape {
+				Shape::Seq(inner, _) => compare(mode, inner, quote!(#x), quote!(#y), names),
+				Shape::Map(key, value, _) => {
+					// The items are pairs of references.
+					let key = compare(mode, key, quote!(#x.0), quote!(#y.0), names);
+					let value = compare(mode, value, quote!(#x.1), quote!(#y.1), names);
+					quote!(#key #value)
+				}
+				_ => unreachable!(),
+			};
+			match mode {
+				// Like the std collections, compare the lengths first.
+				Mode::Eq => quote! {
+					if (#a).len() != (#b).len() {
+						return false;
+					}
+					for (#x, #y) in ::core::iter::Iterator::zip(::core::iter::IntoIterator::into_iter(#a), #b) {
+						#inner
+					}
+				},
+				// Lexicographically, a prefix is less.
+				_ => {
+					let ys = names.next();
+					let (less, greater) = (mode.less(), mode.greater());
+					quote! {
+						let mut #ys = ::core::iter::IntoIterator::into_iter(#b);
+						for #x in #a {
+							match ::core::iter::Iterator::next(&mut #ys) {
+								::core::option::Option::Some(#y) => { #inner }
+								::core::option::Option::None => return #greater,
+							}
+						}
+						if ::core::iter::Iterator::next(&mut #ys).is_some() {
+							return #less;
+						}
+					}
+				}
+			}
+		}
+		Shape::Tuple(elems) => {
+			let xs: Vec<_> = elems.iter().map(|_| names.next()).collect();
+			let ys: Vec<_> = elems.iter().map(|_| names.next()).collect();
+			let compares: Vec<_> = elems
+				.iter()
+				.zip(xs.iter().zip(&ys))
+				.map(|(elem, (x, y))| compare(mode, elem, quote!(#x), quote!(#y), names))
+				.collect();
+			quote! {
+				let (#(#xs,)*) = #a;
+				let (#(#ys,)*) = #b;
+				#(#compares)*
+			}
+		}
+	}
+}
+
+// The value the variants are ordered by: the discriminant, as with the std derives.
+fn discriminants(variants: &[Variant]) -> Vec<TokenStream> {
+	let mut last = None;
+	variants
+		.iter()
+		.enumerate()
+		.map(|(i, variant)| {
+			if let Some(expr) = &variant.discriminant {
+				last = Some((i, expr));
+			}
+			match last {
+				Some((j, expr)) => {
+					let offset = (i - j) as i128;
+					quote!((#expr) as i128 + #offset)
+				}
+				None => {
+					let i = i as i128;
+					quote!(#i)
+				}
+			}
+		})
+		.collect()
+}
+
+fn derive(input: DeriveInput, mode: Mode) -> Result<TokenStream, Error> {
+	let variants = variants(&input, false)?;
+	let name = &input.ident;
+	let (deep_trait, deep_fn, std_trait, std_fn) = match mode {
+		Mode::Eq => (
+			quote!(DeepPartialEq),
+			quote!(deep_eq),
+			quote!(PartialEq),
+			quote!(eq),
+		),
+		Mode::PartialCmp => (
+			quote!(DeepPartialOrd),
+			quote!(deep_partial_cmp),
+			quote!(PartialOrd),
+			quote!(partial_cmp),
+		),
+		Mode::Cmp => (quote!(DeepOrd), quote!(deep_cmp), quote!(Ord), quote!(cmp)),
+	};
+	let generics = with_bound(&input.generics, quote!(::core::cmp::#std_trait));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let scope = scope();
+	let output = mode.output();
+	let equal = mode.equal();
+	let mut names = Names::default();
+	let arms = variants.iter().map(|variant| {
+		let pattern = variant.pattern(false);
+		let pattern_other = variant.pattern_other();
+		let compares = variant.fields.iter().map(|f| {
+			let (binding, other) = (&f.binding, &f.other);
+			compare(mode, &f.shape, quote!(#binding), quote!(#other), &mut names)
+		});
+		quote!((#pattern, #pattern_other) => { #(#compares)* })
+	});
+	let body = if variants.is_empty() {
+		quote!(match *self {})
+	} else {
+		// Different variants are unequal, or ordered by their discriminants.
+		let (check, different) = match mode {
+			Mode::Eq => (quote!(), quote!(_ => return false,)),
+			_ => {
+				let paths = variants.iter().map(|v| &v.path);
+				let discriminants = discriminants(&variants);
+				let check = mode.check(mode.std(
+					quote!(&__decurse_discriminant(self)),
+					quote!(&__decurse_discriminant(other)),
+				));
+				(
+					quote! {
+						let __decurse_discriminant = |value: &Self| -> i128 {
+							match value {
+								#(#paths { .. } => #discriminants,)*
+							}
+						};
+						#check
+					},
+					quote!(_ => {}),
+				)
+			}
+		};
+		let different = if variants.len() > 1 {
+			different
+		} else {
+			quote!()
+		};
+		quote! {
+			#check
+			match (self, other) {
+				#(#arms)*
+				#different
+			}
+			#equal
+		}
+	};
+	Ok(quote! {
+		impl #impl_generics ::decurse::deep::#deep_trait for #name #ty_generics #where_clause {
+			fn #deep_fn<'__decurse>(
+				&'__decurse self,
+				other: &'__decurse Self,
+				#scope: &::decurse::deep::Scope<'__decurse>,
+			) -> ::decurse::deep::ScopedCall<'__decurse, #output> {
+				let __decurse_inner = ::core::clone::Clone::clone(#scope);
+				#scope.call(async move {
+					let #scope = &__decurse_inner;
+					#body
+				})
+			}
+		}
+		impl #impl_generics ::core::cmp::#std_trait for #name #ty_generics #where_clause {
+			fn #std_fn(&self, other: &Self) -> #output {
+				::decurse::deep::Scope::run(|#scope| ::decurse::deep::#deep_trait::#deep_fn(self, other, #scope))
+			}
+		}
+	})
+}
+
+pub(crate) fn derive_deep_partial_eq(input: DeriveInput) -> Result<TokenStream, Error> {
+	derive(input, Mode::Eq)
+}
+
+pub(crate) fn derive_deep_partial_ord(input: DeriveInput) -> Result<TokenStream, Error> {
+	derive(input, Mode::PartialCmp)
+}
+
+pub(crate) fn derive_deep_ord(input: DeriveInput) -> Result<TokenStream, Error> {
+	derive(input, Mode::Cmp)
+}
+
+// `Eq` has no methods, so this is the std derive.
+pub(crate) fn derive_deep_eq(input: DeriveInput) -> Result<TokenStream, Error> {
+	variants(&input, false)?;
+	let name = &input.ident;
+	let generics = with_bound(&input.generics, quote!(::core::cmp::Eq));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	Ok(quote! {
+		impl #impl_generics ::core::cmp::Eq for #name #ty_generics #where_clause {}
+	})
+}
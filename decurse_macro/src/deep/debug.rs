@@ -0,0 +1,164 @@
+use super::{scope, variants, with_bound, Kind, Names, Shape};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::{ext::IdentExt, DeriveInput, Error, Member};
+
+fn out() -> Ident {
+	Ident::new("__decurse_out", Span::call_site())
+}
+
+// Return the error if there is one.
+fn check(result: TokenStream) -> TokenStream {
+	quote!(if let ::core::result::Result::Err(__decurse_error) = #result {
+		return ::core::result::Result::Err(__decurse_error);
+	})
+}
+
+// Code writing the entries with the code writing each one.
+fn entries(kind: TokenStream, entries: &Ident, each: TokenStream) -> TokenStream {
+	let out = out();
+	let finish = check(quote!(#entries.finish(#out)));
+	quote! {
+		let mut #entries = ::decurse::deep::Entries::#kind();
+		#each
+		#finish
+	}
+}
+
+// Code writing what the reference points to, the way the std `Debug` impls would.
+fn debug(shape: &Shape, r: TokenStream, names: &mut Names) -> TokenStream {
+	let out = out();
+	let scope = scope();
+	match shape {
+		Shape::Other | Shape::Cell(..) => check(quote!(#out.value(#r))),
+		Shape::Leaf => check(quote!(::decurse::deep::DeepDebug::deep_fmt(#r, #out, #scope).await)),
+		Shape::Box(inner) | Shape::Rc(inner, _) => debug(inner, quote!(&**#r), names),
+		Shape::Option(inner) => {
+			let (x, e) = (names.next(), names.next());
+			let inner = debug(inner, quote!(#x), names);
+			let none = check(quote!(#out.write_str("None")));
+			let some = check(quote!(#out.write_str("Some")));
+			let entry = check(quote!(#e.entry(#out)));
+			let entries = entries(quote!(tuple), &e, quote!(#entry #inner));
+			quote!(match ::core::option::Option::as_ref(#r) {
+				::core::option::Option::None => { #none }
+				::core::option::Option::Some(#x) => { #some #entries }
+			})
+		}
+		Shape::Seq(inner, kind) => {
+			let (x, e) = (names.next(), names.next());
+			let inner = debug(inner, quote!(#x), names);
+			let entry = check(quote!(#e.entry(#out)));
+			let kind = match kind {
+				Kind::List => quote!(list),
+				Kind::Tree | Kind::Hash => quote!(set),
+			};
+			entries(kind, &e, quote!(for #x in #r { #entry #inner }))
+		}
+		Shape::Map(key, value, _) => {
+			let (k, v, e) = (names.next(), names.next(), names.next());
+			let key = debug(key, quote!(#k), names);
+			let value = debug(value, quote!(#v), names);
+			let entry = check(quote!(#e.entry(#out)));
+			let colon = check(quote!(#out.write_str(": ")));
+			entries(
+				quote!(set),
+				&e,
+				quote!(for (#k, #v) in #r { #entry #key #colon #value }),
+			)
+		}
+		Shape::Tuple(elems) => {
+			let e = names.next();
+			let xs: Vec<_> = elems.iter().map(|_| names.next()).collect();
+			let entry = check(quote!(#e.entry(#out)));
+			let debugs: TokenStream = elems
+				.iter()
+				.zip(&xs)
+				.map(|(elem, x)| {
+					let inner = debug(elem, quote!(#x), names);
+					quote!(#entry #inner)
+				})
+				.collect();
+			let entries = entries(quote!(anonymous_tuple), &e, debugs);
+			quote! {
+				let (#(#xs,)*) = #r;
+				#entries
+			}
+		}
+	}
+}
+
+pub(crate) fn derive_deep_debug(input: DeriveInput) -> Result<TokenStream, Error> {
+	let variants = variants(&input, false)?;
+	let name = &input.ident;
+	let generics = with_bound(&input.generics, quote!(::core::fmt::Debug));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let scope = scope();
+	let out = out();
+	let mut names = Names::default();
+	let arms = variants.iter().map(|variant| {
+		let pattern = variant.pattern(false);
+		let write_name = check({
+			let name = &variant.name;
+			quote!(#out.write_str(#name))
+		});
+		// Like the std derive, a variant without fields is just its name, whatever brackets it has.
+		if variant.fields.is_empty() {
+			return quote!(#pattern => { #write_name });
+		}
+		let e = names.next();
+		let entry = check(quote!(#e.entry(#out)));
+		let mut named = false;
+		let fields: TokenStream = variant
+			.fields
+			.iter()
+			.map(|f| {
+				let binding = &f.binding;
+				let inner = debug(&f.shape, quote!(#binding), &mut names);
+				match &f.member {
+					Member::Named(ident) => {
+						named = true;
+						let field = format!("{}: ", ident.unraw());
+						let write_field = check(quote!(#out.write_str(#field)));
+						quote!(#entry #write_field #inner)
+					}
+					Member::Unnamed(_) => quote!(#entry #inner),
+				}
+			})
+			.collect();
+		let kind = if named { quote!(fields) } else { quote!(tuple) };
+		let entries = entries(kind, &e, fields);
+		quote!(#pattern => { #write_name #entries })
+	});
+	let body = if variants.is_empty() {
+		quote!(match *self {})
+	} else {
+		quote! {
+			match self {
+				#(#arms,)*
+			}
+			::core::result::Result::Ok(())
+		}
+	};
+	Ok(quote! {
+		impl #impl_generics ::decurse::deep::DeepDebug for #name #ty_generics #where_clause {
+			fn deep_fmt<'__decurse>(
+				&'__decurse self,
+				#out: &'__decurse ::decurse::deep::DebugOutput<'__decurse>,
+				#scope: &::decurse::deep::Scope<'__decurse>,
+			) -> ::decurse::deep::ScopedCall<'__decurse, ::core::fmt::Result> {
+				let __decurse_inner = ::core::clone::Clone::clone(#scope);
+				#scope.call(async move {
+					let #scope = &__decurse_inner;
+					#body
+				})
+			}
+		}
+		impl #impl_generics ::core::fmt::Debug for #name #ty_generics #where_clause {
+			fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+				let #out = ::decurse::deep::DebugOutput::new(f);
+				::decurse::deep::Scope::run(|#scope| ::decurse::deep::DeepDebug::deep_fmt(self, &#out, #scope))
+			}
+		}
+	})
+}
@@ -0,0 +1,260 @@
+use super::{variants, Field, Names, Shape};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use syn::{DeriveInput, Error, Type};
+
+fn children() -> Ident {
+	Ident::new("__decurse_children", Span::call_site())
+}
+
+// Code taking the children out of the place (without moving the place).
+// Children held directly are left in place, see `rotation` for those of the type itself.
+fn take_in_place(shape: &Shape, place: TokenStream, names: &mut Names) -> TokenStream {
+	match shape {
+		Shape::Other | Shape::Leaf => quote!(),
+		Shape::Box(inner) => take_in_place(inner, quote!(*#place), names),
+		Shape::Rc(inner, rc) => {
+			let x = names.next();
+			let take = take_in_place(inner, quote!(*#x), names);
+			quote!(if let ::core::option::Option::Some(#x) = #rc::get_mut(&mut #place) { #take })
+		}
+		Shape::Cell(inner, cell) => {
+			let x = names.next();
+			let take = take_in_place(inner, quote!(*#x), names);
+			quote!(let #x = #cell::get_mut(&mut #place); #take)
+		}
+		Shape::Option(inner) => {
+			let x = names.next();
+			let take = take_owned(inner, &x, names);
+			quote!(if let ::core::option::Option::Some(#x) = ::core::option::Option::take(&mut #place) { #take })
+		}
+		Shape::Seq(..) | Shape::Map(..) => {
+			let x = names.next();
+			let take = take_owned(shape, &x, names);
+			quote!(let #x = ::core::mem::take(&mut #place); #take)
+		}
+		Shape::Tuple(elems) => elems
+			.iter()
+			.enumerate()
+			.map(|(i, elem)| {
+				let i = syn::Index::from(i);
+				take_in_place(elem, quote!((#place).#i), names)
+			})
+			.collect(),
+	}
+}
+
+// Code taking the children out of the owned value in the variable.
+fn take_owned(shape: &Shape, x: &Ident, names: &mut Names) -> TokenStream {
+	let children = children();
+	match shape {
+		Shape::Other => quote!(let _ = #x;),
+		Shape::Leaf => quote!(#children.push(#x);),
+		Shape::Box(inner) if matches!(**inner, Shape::Leaf) => quote!(#children.push_box(#x);),
+		Shape::Box(inner) => {
+			let y = names.next();
+			let take = take_owned(inner, &y, names);
+			quote!(let #y = *#x; #take)
+		}
+		Shape::Rc(inner, rc) => {
+			let y = names.next();
+			let take = take_owned(inner, &y, names);
+			quote!(if let ::core::result::Result::Ok(#y) = #rc::try_unwrap(#x) { #take })
+		}
+		Shape::Cell(inner, cell) => {
+			let y = names.next();
+			let take = take_owned(inner, &y, names);
+			quote!(let #y = #cell::into_inner(#x); #take)
+		}
+		Shape::Option(inner) => {
+			let y = names.next();
+			let take = take_owned(inner, &y, names);
+			quote!(if let ::core::option::Option::Some(#y) = #x { #take })
+		}
+		Shape::Seq(inner, _) => {
+			let y = names.next();
+			let take = take_owned(inner, &y, names);
+			quote!(for #y in #x { #take })
+		}
+		Shape::Map(key, value, _) => {
+			let (k, v) = (names.next(), names.next());
+			let take_key = take_owned(key, &k, names);
+			let take_value = take_owned(value, &v, names);
+			quote!(for (#k, #v) in #x { #take_key #take_value })
+		}
+		Shape::Tuple(elems) => {
+			let ys: Vec<Ident> = elems.iter().map(|_| names.next()).collect();
+			let takes = elems
+				.iter()
+				.zip(&ys)
+				.map(|(elem, y)| take_owned(elem, y, names));
+			quote!(let (#(#ys,)*) = #x; #(#takes)*)
+		}
+	}
+}
+
+// Whether the field is a `Box`, `Rc` or `Arc` directly holding the type itself.
+fn holds_self(field: &Field, this: &str) -> bool {
+	if !matches!(&field.shape, Shape::Box(inner) | Shape::Rc(inner, _) if matches!(**inner, Shape::Leaf))
+	{
+		return false;
+	}
+	let mut ty = &field.ty;
+	while let Type::Paren(syn::TypeParen { elem, .. }) | Type::Group(syn::TypeGroup { elem, .. }) =
+		ty
+	{
+		ty = elem;
+	}
+	let inner = match ty {
+		Type::Path(path) => match path.path.segments.last().map(|s| &s.arguments) {
+			Some(syn::PathArguments::AngleBracketed(args)) => {
+				args.args.iter().find_map(|arg| match arg {
+					syn::GenericArgument::Type(ty) => Some(ty),
+					_ => None,
+				})
+			}
+			_ => None,
+		},
+		_ => None,
+	};
+	match inner {
+		Some(inner) => {
+			let inner = quote!(#inner).to_string();
+			inner == "Self" || inner == this
+		}
+		None => false,
+	}
+}
+
+// Code replacing the value with the child in the field, taking the other fields apart.
+// For a shared `Rc` or `Arc`, this does nothing and goes on.
+fn rotation(fields: &[Field], k: usize, names: &mut Names) -> TokenStream {
+	let field = &fields[k];
+	let binding = &field.binding;
+	let others: Vec<&Field> = fields
+		.iter()
+		.enumerate()
+		.filter(|&(i, _)| i != k)
+		.map(|(_, f)| f)
+		.collect();
+	let reads = others.iter().map(|f| {
+		let binding = &f.binding;
+		quote!(let #binding = unsafe { ::core::ptr::read(#binding) };)
+	});
+	let takes: Vec<TokenStream> = others
+		.iter()
+		.map(|f| take_owned(&f.shape, &f.binding, names))
+		.collect();
+	let next = names.next();
+	// Safety: Every field is read exactly once, and `self` is then overwritten without being dropped.
+	let replace = quote! {
+		#(#reads)*
+		unsafe { ::core::ptr::write(self, #next) };
+		#(#takes)*
+		continue;
+	};
+	match &field.shape {
+		Shape::Rc(_, rc) => quote! {
+			match #rc::try_unwrap(unsafe { ::core::ptr::read(#binding) }) {
+				::core::result::Result::Ok(#next) => { #replace }
+				// Shared, so dropping it won't drop the child.
+				::core::result::Result::Err(__decurse_rc) => ::core::mem::forget(__decurse_rc),
+			}
+		},
+		_ => quote! {
+			let #next = *unsafe { ::core::ptr::read(#binding) };
+			#replace
+		},
+	}
+}
+
+pub(crate) fn derive_deep_drop(input: DeriveInput) -> Result<TokenStream, Error> {
+	let variants = variants(&input, true)?;
+	let name = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let this = quote!(#name #ty_generics).to_string();
+	let children = children();
+	let mut names = Names::default();
+	let mut rotates = false;
+	let arms: Vec<TokenStream> = variants
+		.iter()
+		.map(|variant| {
+			let mut body = TokenStream::new();
+			let mut replaced = false;
+			for (k, field) in variant.fields.iter().enumerate() {
+				if holds_self(field, &this) {
+					body.extend(rotation(&variant.fields, k, &mut names));
+					// Replacing with a boxed child always happens, so the rest is unreachable.
+					if let Shape::Box(_) = field.shape {
+						replaced = true;
+						break;
+					}
+				}
+			}
+			let pattern = if body.is_empty() {
+				variant.pattern(true)
+			} else {
+				rotates = true;
+				variant.pattern(false)
+			};
+			if !replaced {
+				for f in &variant.fields {
+					let binding = &f.binding;
+					body.extend(take_in_place(&f.shape, quote!(*#binding), &mut names));
+				}
+			}
+			quote!(#pattern => { #body })
+		})
+		.collect();
+	let take_children = if rotates {
+		quote!(loop {
+			match self {
+				#(#arms)*
+			}
+			break;
+		})
+	} else {
+		quote!(match self {
+			#(#arms)*
+		})
+	};
+	let owned_arms = variants.iter().map(|variant| {
+		let pattern = variant.pattern(false);
+		let bindings = variant.fields.iter().map(|f| &f.binding);
+		let takes: Vec<TokenStream> = variant
+			.fields
+			.iter()
+			.map(|f| take_owned(&f.shape, &f.binding, &mut names))
+			.collect();
+		quote!(#pattern => {
+			#(let #bindings = unsafe { ::core::ptr::read(#bindings) };)*
+			#(#takes)*
+		})
+	});
+	Ok(quote! {
+		impl #impl_generics ::decurse::deep::DeepDrop for #name #ty_generics #where_clause {
+			fn take_children<'__decurse>(&mut self, #children: &mut ::decurse::deep::Children<'__decurse>)
+			where
+				Self: '__decurse,
+			{
+				#take_children
+			}
+			fn take_children_owned<'__decurse>(
+				this: ::core::mem::ManuallyDrop<Self>,
+				#children: &mut ::decurse::deep::Children<'__decurse>,
+			) where
+				Self: '__decurse,
+			{
+				// Safety: Every field is read exactly once, and `this` is never dropped.
+				match &*this {
+					#(#owned_arms)*
+				}
+			}
+		}
+		impl #impl_generics ::core::ops::Drop for #name #ty_generics #where_clause {
+			fn drop(&mut self) {
+				::decurse::deep::drop_deep(self);
+			}
+		}
+	})
+}
@@ -0,0 +1,174 @@
+use super::{scope, variants, with_bound, Kind, Names, Shape};
+use proc_macro2::{Ident, Literal, Span, TokenStream, TokenTree};
+use quote::quote;
+use syn::{DeriveInput, Error};
+
+fn state() -> Ident {
+	Ident::new("__decurse_state", Span::call_site())
+}
+
+// Hash with the std trait.
+fn hash_std(r: TokenStream) -> TokenStream {
+	let state = state();
+	quote!(::core::hash::Hash::hash(#r, &mut *#state.borrow_mut());)
+}
+
+// Code hashing what the reference points to, feeding the hasher what the std `Hash` impls would.
+fn hash(shape: &Shape, r: TokenStream, names: &mut Names) -> TokenStream {
+	let state = state();
+	let scope = scope();
+	match shape {
+		Shape::Other | Shape::Cell(..) => hash_std(r),
+		Shape::Leaf => quote!(::decurse::deep::DeepHash::deep_hash(#r, #state, #scope).await;),
+		Shape::Box(inner) | Shape::Rc(inner, _) => hash(inner, quote!(&**#r), names),
+		// `Option` derives `Hash`, so this hashes the discriminant first.
+		Shape::Option(inner) => {
+			let x = names.next();
+			let inner = hash(inner, quote!(#x), names);
+			let (none, some) = (hash_std(quote!(&0isize)), hash_std(quote!(&1isize)));
+			quote!(match ::core::option::Option::as_ref(#r) {
+				::core::option::Option::None => { #none }
+				::core::option::Option::Some(#x) => { #some #inner }
+			})
+		}
+		// Hash collections don't implement `Hash`, leave the error to the std trait.
+		Shape::Seq(_, Kind::Hash) | Shape::Map(_, _, Kind::Hash) => hash_std(r),
+		Shape::Seq(..) | Shape::Map(..) => {
+			let x = names.next();
+			let inner = match shape {
+				Shape::Seq(inner, _) => hash(inner, quote!(#x), names),
+				Shape::Map(key, value, _) => {
+					let key = hash(key, quote!(#x.0), names);
+					let value = hash(value, quote!(#x.1), names);
+					quote!(#key #value)
+				}
+				_ => unreachable!(),
+			};
+			// The length prefix of the std collections.
+			quote! {
+				::core::hash::Hasher::write_usize(&mut *#state.borrow_mut(), (#r).len());
+				for #x in #r {
+					#inner
+				}
+			}
+		}
+		Shape::Tuple(elems) => {
+			let xs: Vec<_> = elems.iter().map(|_| names.next()).collect();
+			let hashes: Vec<_> = elems
+				.iter()
+				.zip(&xs)
+				.map(|(elem, x)| hash(elem, quote!(#x), names))
+				.collect();
+			quote! {
+				let (#(#xs,)*) = #r;
+				#(#hashes)*
+			}
+		}
+	}
+}
+
+// The type of the discriminant: the integer in `#[repr(...)]`, or `isize`.
+fn repr(input: &DeriveInput) -> TokenStream {
+	const INTEGERS: &[&str] = &[
+		"u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+	];
+	for attr in input.attrs.iter().filter(|a| a.path.is_ident("repr")) {
+		for token in attr.tokens.clone() {
+			if let TokenTree::Group(group) = token {
+				for token in group.stream() {
+					match token {
+						TokenTree::Ident(ident) if INTEGERS.iter().any(|i| ident == i) => {
+							return quote!(#ident)
+						}
+						_ => {}
+					}
+				}
+			}
+		}
+	}
+	quote!(isize)
+}
+
+pub(crate) fn derive_deep_hash(input: DeriveInput) -> Result<TokenStream, Error> {
+	let variants = variants(&input, false)?;
+	let name = &input.ident;
+	let generics = with_bound(&input.generics, quote!(::core::hash::Hash));
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	let scope = scope();
+	let state = state();
+	let mut names = Names::default();
+	let arms = variants.iter().map(|variant| {
+		let pattern = variant.pattern(false);
+		let hashes = variant
+			.fields
+			.iter()
+			.map(|f| {
+				let binding = &f.binding;
+				hash(&f.shape, quote!(#binding), &mut names)
+			})
+			.collect::<TokenStream>();
+		quote!(#pattern => { #hashes })
+	});
+	// Like the std derive, hash the discriminant if there's more than one variant.
+	let discriminant = if variants.len() > 1 {
+		let repr = repr(&input);
+		let mut last = None;
+		let discriminants = variants.iter().enumerate().map(|(i, variant)| {
+			let path = &variant.path;
+			if let Some(expr) = &variant.discriminant {
+				last = Some((i, expr));
+			}
+			let value = match last {
+				Some((j, expr)) => {
+					let offset = Literal::usize_unsuffixed(i - j);
+					quote!((#expr) + #offset)
+				}
+				None => {
+					let i = Literal::usize_unsuffixed(i);
+					quote!(#i)
+				}
+			};
+			quote!(#path { .. } => #value)
+		});
+		let hash = hash_std(quote!(&__decurse_discriminant));
+		quote! {
+			let __decurse_discriminant: #repr = match self {
+				#(#discriminants,)*
+			};
+			#hash
+		}
+	} else {
+		quote!()
+	};
+	let body = if variants.is_empty() {
+		quote!(match *self {})
+	} else {
+		quote! {
+			#discriminant
+			match self {
+				#(#arms,)*
+			}
+		}
+	};
+	Ok(quote! {
+		impl #impl_generics ::decurse::deep::DeepHash for #name #ty_generics #where_clause {
+			fn deep_hash<'__decurse>(
+				&'__decurse self,
+				#state: &'__decurse ::core::cell::RefCell<&'__decurse mut dyn ::core::hash::Hasher>,
+				#scope: &::decurse::deep::Scope<'__decurse>,
+			) -> ::decurse::deep::ScopedCall<'__decurse, ()> {
+				let __decurse_inner = ::core::clone::Clone::clone(#scope);
+				#scope.call(async move {
+					let #scope = &__decurse_inner;
+					#body
+				})
+			}
+		}
+		impl #impl_generics ::core::hash::Hash for #name #ty_generics #where_clause {
+			fn hash<__H: ::core::hash::Hasher>(&self, #state: &mut __H) {
+				let #state = ::core::cell::RefCell::new(#state as &mut dyn ::core::hash::Hasher);
+				::decurse::deep::Scope::run(|#scope| ::decurse::deep::DeepHash::deep_hash(self, &#state, #scope))
+			}
+		}
+	})
+}
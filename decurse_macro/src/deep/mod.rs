@@ -0,0 +1,308 @@
+// The derives of the `decurse::deep` module.
+//
+// They find the children of a type from the types of its fields (see `Shape`),
+// and generate code that handles the children on the heap instead of recursing into them.
+
+mod clone;
+mod cmp;
+mod debug;
+mod drop;
+mod hash;
+mod recursive;
+
+pub(crate) use clone::derive_deep_clone;
+pub(crate) use cmp::{
+	derive_deep_eq, derive_deep_ord, derive_deep_partial_eq, derive_deep_partial_ord,
+};
+pub(crate) use debug::derive_deep_debug;
+pub(crate) use drop::derive_deep_drop;
+pub(crate) use hash::derive_deep_hash;
+pub(crate) use recursive::derive_recursive;
+
+use proc_macro2::{Ident, Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+	ext::IdentExt, parse_quote, Data, DeriveInput, Error, Expr, Fields, Generics, Member, Type,
+};
+
+// How a field holds children.
+pub(crate) enum Shape {
+	// No children.
+	Other,
+	// A child.
+	Leaf,
+	Box(Box<Shape>),
+	// `Rc` or `Arc`, with the path to it.
+	Rc(Box<Shape>, TokenStream),
+	// `Cell` or `RefCell`, with the path to it.
+	Cell(Box<Shape>, TokenStream),
+	Option(Box<Shape>),
+	// A collection, with the shape of its items.
+	Seq(Box<Shape>, Kind),
+	// A map, with the shapes of its keys and values.
+	Map(Box<Shape>, Box<Shape>, Kind),
+	Tuple(Vec<Shape>),
+}
+
+// The kind of a collection, which decides how the std traits compare and print it.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Kind {
+	// `Vec`, `VecDeque`, `LinkedList` or `BinaryHeap`.
+	List,
+	// `BTreeSet` or `BTreeMap`.
+	Tree,
+	// `HashSet` or `HashMap`, whose order doesn't matter.
+	Hash,
+}
+
+impl Shape {
+	fn of(ty: &Type, is_child: &dyn Fn(&Type) -> bool) -> Self {
+		let shape = Self::of_child(ty, is_child);
+		// E.g. `Option<Weak<Self>>`, which mentions the type but holds no child.
+		if shape.has_children() {
+			shape
+		} else {
+			Shape::Other
+		}
+	}
+	fn of_child(ty: &Type, is_child: &dyn Fn(&Type) -> bool) -> Self {
+		if !is_child(ty) {
+			return Shape::Other;
+		}
+		match ty {
+			Type::Paren(paren) => Self::of(&paren.elem, is_child),
+			Type::Group(group) => Self::of(&group.elem, is_child),
+			Type::Tuple(tuple) => {
+				Shape::Tuple(tuple.elems.iter().map(|t| Self::of(t, is_child)).collect())
+			}
+			Type::Path(path) if path.qself.is_none() => {
+				// UNWRAP Safety: A path always has at least one segment.
+				let last = path.path.segments.last().unwrap();
+				let args: Vec<&Type> = match &last.arguments {
+					syn::PathArguments::AngleBracketed(args) => args
+						.args
+						.iter()
+						.filter_map(|arg| match arg {
+							syn::GenericArgument::Type(ty) => Some(ty),
+							_ => None,
+						})
+						.collect(),
+					_ => Vec::new(),
+				};
+				let inner = |i: usize| Box::new(Self::of(args[i], is_child));
+				match (last.ident.to_string().as_str(), args.len()) {
+					("Box", 1) => Shape::Box(inner(0)),
+					("Rc", 1) => Shape::Rc(inner(0), quote!(::std::rc::Rc)),
+					("Arc", 1) => Shape::Rc(inner(0), quote!(::std::sync::Arc)),
+					// A weak reference doesn't own what it points to.
+					("Weak", 1) => Shape::Other,
+					("Cell", 1) => Shape::Cell(inner(0), quote!(::core::cell::Cell)),
+					("RefCell", 1) => Shape::Cell(inner(0), quote!(::core::cell::RefCell)),
+					("Option", 1) => Shape::Option(inner(0)),
+					("Vec", 1) | ("VecDeque", 1) | ("LinkedList", 1) | ("BinaryHeap", 1) => {
+						Shape::Seq(inner(0), Kind::List)
+					}
+					("BTreeSet", 1) => Shape::Seq(inner(0), Kind::Tree),
+					("HashSet", 1) => Shape::Seq(inner(0), Kind::Hash),
+					("BTreeMap", 2) => Shape::Map(inner(0), inner(1), Kind::Tree),
+					("HashMap", 2) => Shape::Map(inner(0), inner(1), Kind::Hash),
+					_ => Shape::Leaf,
+				}
+			}
+			_ => Shape::Leaf,
+		}
+	}
+	pub(crate) fn is_other(&self) -> bool {
+		matches!(self, Shape::Other)
+	}
+	fn has_children(&self) -> bool {
+		match self {
+			Shape::Other => false,
+			Shape::Leaf => true,
+			Shape::Box(inner)
+			| Shape::Rc(inner, _)
+			| Shape::Cell(inner, _)
+			| Shape::Option(inner)
+			| Shape::Seq(inner, _) => inner.has_children(),
+			Shape::Map(key, value, _) => key.has_children() || value.has_children(),
+			Shape::Tuple(elems) => elems.iter().any(Shape::has_children),
+		}
+	}
+	// The shape without `Cell` and `RefCell`, which only `DeepDrop` looks into:
+	// the other derives borrow the children for longer than a `RefCell` could be borrowed.
+	pub(crate) fn without_cells(self) -> Self {
+		let shape = match self {
+			Shape::Cell(..) => Shape::Other,
+			Shape::Box(inner) => Shape::Box(Box::new(inner.without_cells())),
+			Shape::Rc(inner, rc) => Shape::Rc(Box::new(inner.without_cells()), rc),
+			Shape::Option(inner) => Shape::Option(Box::new(inner.without_cells())),
+			Shape::Seq(inner, kind) => Shape::Seq(Box::new(inner.without_cells()), kind),
+			Shape::Map(key, value, kind) => Shape::Map(
+				Box::new(key.without_cells()),
+				Box::new(value.without_cells()),
+				kind,
+			),
+			Shape::Tuple(elems) => {
+				Shape::Tuple(elems.into_iter().map(Shape::without_cells).collect())
+			}
+			shape => shape,
+		};
+		if shape.has_children() {
+			shape
+		} else {
+			Shape::Other
+		}
+	}
+}
+
+// Whether the tokens mention the type.
+fn mentions(tokens: TokenStream, name: &Ident) -> bool {
+	tokens.into_iter().any(|token| match token {
+		TokenTree::Ident(ident) => ident == *name || ident == "Self",
+		TokenTree::Group(group) => mentions(group.stream(), name),
+		_ => false,
+	})
+}
+
+pub(crate) struct Field {
+	pub(crate) member: Member,
+	pub(crate) ty: Type,
+	// The name the field is bound to in patterns.
+	pub(crate) binding: Ident,
+	// The name the field of the other value is bound to, for the derives comparing two values.
+	pub(crate) other: Ident,
+	pub(crate) shape: Shape,
+}
+
+pub(crate) struct Variant {
+	// `Self` for structs, `Self::Variant` for enums.
+	pub(crate) path: TokenStream,
+	// The name of the struct or variant.
+	pub(crate) name: String,
+	pub(crate) fields: Vec<Field>,
+	// The explicit discriminant, if any.
+	pub(crate) discriminant: Option<Expr>,
+}
+
+impl Variant {
+	// A pattern binding the fields, e.g. `Self::Variant { 0: __decurse_0, .. }`.
+	pub(crate) fn pattern(&self, only_children: bool) -> TokenStream {
+		self.bind(only_children, |f| &f.binding)
+	}
+	// A pattern binding the fields to their `other` names.
+	pub(crate) fn pattern_other(&self) -> TokenStream {
+		self.bind(false, |f| &f.other)
+	}
+	fn bind(&self, only_children: bool, binding: impl Fn(&Field) -> &Ident) -> TokenStream {
+		let path = &self.path;
+		let fields = self
+			.fields
+			.iter()
+			.filter(|f| !only_children || !f.shape.is_other())
+			.map(|f| {
+				let member = &f.member;
+				let binding = binding(f);
+				quote!(#member: #binding)
+			});
+		quote!(#path { #(#fields,)* .. })
+	}
+}
+
+// How the field holds children: marked with `#[deep]`, not at all with `#[deep(skip)]`,
+// and otherwise wherever its type mentions the type itself.
+pub(crate) fn field_shape(field: &syn::Field, name: &Ident) -> Result<Shape, Error> {
+	let mut marked = false;
+	for attr in field.attrs.iter().filter(|a| a.path.is_ident("deep")) {
+		if attr.tokens.is_empty() {
+			marked = true;
+			continue;
+		}
+		match attr.parse_args::<Ident>() {
+			Ok(arg) if arg == "skip" => return Ok(Shape::Other),
+			_ => {
+				return Err(Error::new_spanned(
+					attr,
+					"Decurse: expected `#[deep]` or `#[deep(skip)]`.",
+				))
+			}
+		}
+	}
+	let is_child = |ty: &Type| marked || mentions(ty.to_token_stream(), name);
+	Ok(Shape::of(&field.ty, &is_child))
+}
+
+// The variants of the type (just one for a struct), with the shape of each field.
+// Children in a `Cell` or `RefCell` are only looked for with `cells`, see `Shape::without_cells`.
+pub(crate) fn variants(input: &DeriveInput, cells: bool) -> Result<Vec<Variant>, Error> {
+	let name = &input.ident;
+	let fields = |fields: &Fields| -> Result<Vec<Field>, Error> {
+		fields
+			.iter()
+			.enumerate()
+			.map(|(i, field)| {
+				Ok(Field {
+					member: match &field.ident {
+						Some(ident) => Member::Named(ident.clone()),
+						None => Member::Unnamed(i.into()),
+					},
+					ty: field.ty.clone(),
+					binding: format_ident!("__decurse_{}", i),
+					other: format_ident!("__decurse_other_{}", i),
+					shape: match field_shape(field, name)? {
+						shape if cells => shape,
+						shape => shape.without_cells(),
+					},
+				})
+			})
+			.collect()
+	};
+	match &input.data {
+		Data::Struct(data) => Ok(vec![Variant {
+			path: quote!(Self),
+			name: name.unraw().to_string(),
+			fields: fields(&data.fields)?,
+			discriminant: None,
+		}]),
+		Data::Enum(data) => data
+			.variants
+			.iter()
+			.map(|variant| {
+				let ident = &variant.ident;
+				Ok(Variant {
+					path: quote!(Self::#ident),
+					name: ident.unraw().to_string(),
+					fields: fields(&variant.fields)?,
+					discriminant: variant.discriminant.as_ref().map(|(_, expr)| expr.clone()),
+				})
+			})
+			.collect(),
+		Data::Union(data) => Err(Error::new(
+			data.union_token.span,
+			"Decurse: union not supported.",
+		)),
+	}
+}
+
+// Generates fresh variable names.
+#[derive(Default)]
+pub(crate) struct Names(usize);
+
+impl Names {
+	pub(crate) fn next(&mut self) -> Ident {
+		self.0 += 1;
+		Ident::new(&format!("__decurse_x{}", self.0), Span::call_site())
+	}
+}
+
+// The generics with the bound added to every type parameter, as the std derives do.
+pub(crate) fn with_bound(generics: &Generics, bound: TokenStream) -> Generics {
+	let mut generics = generics.clone();
+	for param in generics.type_params_mut() {
+		param.bounds.push(parse_quote!(#bound));
+	}
+	generics
+}
+
+fn scope() -> Ident {
+	Ident::new("__decurse_scope", Span::call_site())
+}
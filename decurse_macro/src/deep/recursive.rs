@@ -0,0 +1,324 @@
+use super::{field_shape, variants, Kind, Names, Shape};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+	spanned::Spanned, Data, DeriveInput, Error, Fields, GenericArgument, GenericParam,
+	PathArguments, Type,
+};
+
+// The type arguments of the last segment of a path type.
+fn type_args(ty: &mut Type) -> Vec<&mut Type> {
+	match ty {
+		Type::Path(path) => match path.path.segments.last_mut().map(|s| &mut s.arguments) {
+			Some(PathArguments::AngleBracketed(args)) => args
+				.args
+				.iter_mut()
+				.filter_map(|arg| match arg {
+					GenericArgument::Type(ty) => Some(ty),
+					_ => None,
+				})
+				.collect(),
+			_ => Vec::new(),
+		},
+		_ => Vec::new(),
+	}
+}
+
+// The type of the field in the layer: the children replaced with `r`.
+fn layer_type(ty: &Type, shape: &Shape, name: &Ident, r: &Ident) -> Result<Type, Error> {
+	let unsupported = |message: &str| Err(Error::new(ty.span(), message));
+	let mut ty = ty.clone();
+	while let Type::Paren(syn::TypeParen { elem, .. }) | Type::Group(syn::TypeGroup { elem, .. }) =
+		ty
+	{
+		ty = *elem;
+	}
+	match shape {
+		Shape::Other => Ok(ty),
+		Shape::Leaf => match &ty {
+			Type::Path(path)
+				if path.qself.is_none()
+					&& matches!(path.path.segments.last(), Some(s) if s.ident == *name || s.ident == "Self") =>
+			{
+				Ok(syn::parse_quote!(#r))
+			}
+			_ => unsupported("Decurse: Recursive only supports children of the type itself."),
+		},
+		Shape::Box(inner) => layer_type(type_args(&mut ty)[0], inner, name, r),
+		Shape::Rc(..) => unsupported("Decurse: Recursive doesn't support children in Rc or Arc."),
+		Shape::Cell(..) => {
+			unsupported("Decurse: Recursive doesn't support children in Cell or RefCell.")
+		}
+		Shape::Seq(_, Kind::Tree) | Shape::Seq(_, Kind::Hash) => {
+			unsupported("Decurse: Recursive doesn't support sets of children.")
+		}
+		Shape::Map(key, ..) if !key.is_other() => {
+			unsupported("Decurse: Recursive doesn't support children as keys.")
+		}
+		Shape::Option(inner) | Shape::Seq(inner, _) | Shape::Map(_, inner, _) => {
+			let mut layer = ty.clone();
+			// UNWRAP Safety: The shape was found from the type arguments.
+			let arg = type_args(&mut layer).pop().unwrap();
+			*arg = layer_type(arg, inner, name, r)?;
+			Ok(layer)
+		}
+		Shape::Tuple(elems) => match &ty {
+			Type::Tuple(tuple) => {
+				let mut layer = tuple.clone();
+				for (elem, shape) in layer.elems.iter_mut().zip(elems) {
+					*elem = layer_type(elem, shape, name, r)?;
+				}
+				Ok(Type::Tuple(layer))
+			}
+			_ => unreachable!(),
+		},
+	}
+}
+
+// Which conversion is generated.
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+	// From a field of the type to a field of the layer.
+	Into,
+	// Back.
+	From,
+	// Between fields of layers, applying `f` to the children.
+	Map,
+}
+
+// Whether the conversion does anything.
+fn changes(shape: &Shape, direction: Direction) -> bool {
+	match shape {
+		Shape::Other => false,
+		Shape::Leaf => direction == Direction::Map,
+		Shape::Box(inner) => direction != Direction::Map || changes(inner, direction),
+		Shape::Rc(inner, _)
+		| Shape::Cell(inner, _)
+		| Shape::Option(inner)
+		| Shape::Seq(inner, _)
+		| Shape::Map(_, inner, _) => changes(inner, direction),
+		Shape::Tuple(elems) => elems.iter().any(|elem| changes(elem, direction)),
+	}
+}
+
+// An expression converting the owned value of the field.
+fn convert(shape: &Shape, x: TokenStream, direction: Direction, names: &mut Names) -> TokenStream {
+	if !changes(shape, direction) {
+		return x;
+	}
+	match shape {
+		Shape::Other | Shape::Rc(..) | Shape::Cell(..) => x,
+		Shape::Leaf => quote!(__decurse_f(#x)),
+		Shape::Box(inner) => match direction {
+			Direction::Into => convert(inner, quote!(*#x), direction, names),
+			Direction::From => {
+				let inner = convert(inner, x, direction, names);
+				quote!(::std::boxed::Box::new(#inner))
+			}
+			Direction::Map => convert(inner, x, direction, names),
+		},
+		Shape::Option(inner) => {
+			let y = names.next();
+			let inner = convert(inner, quote!(#y), direction, names);
+			quote!(::core::option::Option::map(#x, |#y| #inner))
+		}
+		Shape::Seq(inner, _) => {
+			let y = names.next();
+			let inner = convert(inner, quote!(#y), direction, names);
+			quote!(::core::iter::Iterator::collect(::core::iter::Iterator::map(
+				::core::iter::IntoIterator::into_iter(#x),
+				|#y| #inner
+			)))
+		}
+		Shape::Map(_, value, _) => {
+			let (k, v) = (names.next(), names.next());
+			let value = convert(value, quote!(#v), direction, names);
+			quote!(::core::iter::Iterator::collect(::core::iter::Iterator::map(
+				::core::iter::IntoIterator::into_iter(#x),
+				|(#k, #v)| (#k, #value)
+			)))
+		}
+		Shape::Tuple(elems) => {
+			let ys: Vec<_> = elems.iter().map(|_| names.next()).collect();
+			let converted: Vec<_> = elems
+				.iter()
+				.zip(&ys)
+				.map(|(elem, y)| convert(elem, quote!(#y), direction, names))
+				.collect();
+			quote!({
+				let (#(#ys,)*) = #x;
+				(#(#converted,)*)
+			})
+		}
+	}
+}
+
+pub(crate) fn derive_recursive(input: DeriveInput) -> Result<TokenStream, Error> {
+	let variants = variants(&input, true)?;
+	let name = &input.ident;
+	let vis = &input.vis;
+	let layer = format_ident!("{}Layer", name);
+	let r = if input.generics.type_params().any(|p| p.ident == "R") {
+		Ident::new("__DecurseR", Span::call_site())
+	} else {
+		Ident::new("R", Span::call_site())
+	};
+	if !variants
+		.iter()
+		.flat_map(|v| &v.fields)
+		.any(|f| !f.shape.is_other())
+	{
+		return Err(Error::new(
+			name.span(),
+			"Decurse: Recursive needs a field holding children.",
+		));
+	}
+
+	// The layer type.
+	let layer_fields = |fields: &Fields| -> Result<TokenStream, Error> {
+		let mut layer_fields = Vec::new();
+		for field in fields {
+			let shape = field_shape(field, name)?;
+			let ty = layer_type(&field.ty, &shape, name, &r)?;
+			let field_vis = &field.vis;
+			layer_fields.push(match &field.ident {
+				Some(ident) => quote!(#field_vis #ident: #ty),
+				None => quote!(#field_vis #ty),
+			});
+		}
+		Ok(match fields {
+			Fields::Named(_) => quote!({ #(#layer_fields,)* }),
+			Fields::Unnamed(_) => quote!((#(#layer_fields,)*)),
+			Fields::Unit => quote!(),
+		})
+	};
+	let mut params = input.generics.params.clone();
+	params.push(syn::parse_quote!(#r));
+	let where_clause = &input.generics.where_clause;
+	let layer_def = match &input.data {
+		Data::Struct(data) => {
+			let fields = layer_fields(&data.fields)?;
+			let semi = match data.fields {
+				Fields::Named(_) => quote!(),
+				_ => quote!(;),
+			};
+			quote!(#vis struct #layer<#params> #where_clause #fields #semi)
+		}
+		Data::Enum(data) => {
+			let layer_variants = data
+				.variants
+				.iter()
+				.map(|variant| {
+					let ident = &variant.ident;
+					let fields = layer_fields(&variant.fields)?;
+					Ok(quote!(#ident #fields))
+				})
+				.collect::<Result<Vec<_>, Error>>()?;
+			quote!(#vis enum #layer<#params> #where_clause { #(#layer_variants,)* })
+		}
+		// `variants` already failed.
+		Data::Union(_) => unreachable!(),
+	};
+	let args: Vec<TokenStream> = input
+		.generics
+		.params
+		.iter()
+		.map(|param| match param {
+			GenericParam::Type(param) => {
+				let ident = &param.ident;
+				quote!(#ident)
+			}
+			GenericParam::Lifetime(param) => {
+				let lifetime = &param.lifetime;
+				quote!(#lifetime)
+			}
+			GenericParam::Const(param) => {
+				let ident = &param.ident;
+				quote!(#ident)
+			}
+		})
+		.collect();
+
+	// The conversions.
+	let mut names = Names::default();
+	let layer_paths: Vec<TokenStream> = match &input.data {
+		Data::Enum(data) => data
+			.variants
+			.iter()
+			.map(|variant| {
+				let ident = &variant.ident;
+				quote!(#layer::#ident)
+			})
+			.collect(),
+		_ => vec![quote!(#layer)],
+	};
+	let mut arms = |direction: Direction| -> Vec<TokenStream> {
+		variants
+			.iter()
+			.zip(&layer_paths)
+			.map(|(variant, layer_path)| {
+				let (from_path, to_path) = match direction {
+					Direction::Into => (&variant.path, layer_path),
+					Direction::From => (layer_path, &variant.path),
+					Direction::Map => (layer_path, layer_path),
+				};
+				let bindings: Vec<_> = variant.fields.iter().map(|f| &f.binding).collect();
+				let members: Vec<_> = variant.fields.iter().map(|f| &f.member).collect();
+				let converted: Vec<_> = variant
+					.fields
+					.iter()
+					.map(|f| {
+						let binding = &f.binding;
+						convert(&f.shape, quote!(#binding), direction, &mut names)
+					})
+					.collect();
+				// Into reads the fields out of a reference, see `into_layer`.
+				let read = if direction == Direction::Into {
+					quote!(#(let #bindings = unsafe { ::core::ptr::read(#bindings) };)*)
+				} else {
+					quote!()
+				};
+				quote!(#from_path { #(#members: #bindings,)* } => {
+					#read
+					#(let #bindings = #converted;)*
+					#to_path { #(#members: #bindings,)* }
+				})
+			})
+			.collect()
+	};
+	let into_arms = arms(Direction::Into);
+	let from_arms = arms(Direction::From);
+	let map_arms = arms(Direction::Map);
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let doc = format!(
+		"[`{}`] with its children replaced by `{}`, for [`Recursive`](::decurse::deep::Recursive).",
+		name, r
+	);
+	Ok(quote! {
+		#[doc = #doc]
+		#layer_def
+		impl #impl_generics ::decurse::deep::Recursive for #name #ty_generics #where_clause {
+			type Layer<#r> = #layer<#(#args,)* #r>;
+			fn into_layer(self) -> Self::Layer<Self> {
+				let this = ::core::mem::ManuallyDrop::new(self);
+				// Safety: Every field is read exactly once, and `this` is never dropped.
+				match &*this {
+					#(#into_arms)*
+				}
+			}
+			fn from_layer(layer: Self::Layer<Self>) -> Self {
+				match layer {
+					#(#from_arms)*
+				}
+			}
+			fn map_layer<#r, __DecurseS>(
+				layer: Self::Layer<#r>,
+				mut __decurse_f: impl FnMut(#r) -> __DecurseS,
+			) -> Self::Layer<__DecurseS> {
+				match layer {
+					#(#map_arms)*
+				}
+			}
+		}
+	})
+}
@@ -1,15 +1,19 @@
 //! This crate provide macros for the [`decurse` crate](https://crates.io/crates/decurse).
 //! Please see there for more details.
 
+mod deep;
+
 use proc_macro2::{Ident, Span, TokenStream};
 use quote::quote;
 use syn::{
 	fold::{fold_expr, fold_fn_arg, fold_item_fn, Fold},
+	parenthesized,
 	parse::Parse,
 	parse_macro_input, parse_quote,
 	punctuated::Punctuated,
-	token::Comma,
-	Error, Expr, FnArg, Generics, ItemFn, Pat, PatIdent, Signature, Stmt, Token, Visibility,
+	token::{Comma, Paren},
+	Attribute, DeriveInput, Error, Expr, ExprPath, FnArg, Generics, Index, ItemFn, Pat, PatIdent,
+	Path, ReturnType, Signature, Stmt, Token, Type, Visibility,
 };
 struct Parsed(ItemFn);
 
@@ -28,6 +32,134 @@ impl Parse for Parsed {
 	}
 }
 
+#[derive(Default)]
+struct Options {
+	abortable: bool,
+	// `Some` even if empty, since it also makes the function callable by others.
+	calls: Option<Vec<Path>>,
+	memoize: Option<Memoize>,
+	detect_cycles: bool,
+	// Empty for all the arguments.
+	debug_args: Option<Vec<Ident>>,
+	debug_result: bool,
+	state: Option<Type>,
+	// Set by `#[decurse::generator]` rather than parsed.
+	generator: bool,
+}
+
+impl Options {
+	// Without options that need a `Recursion`, the function runs directly on the runtime.
+	fn is_plain(&self) -> bool {
+		self.calls.is_none()
+			&& self.memoize.is_none()
+			&& !self.detect_cycles
+			&& self.debug_args.is_none()
+			&& !self.debug_result
+	}
+}
+
+enum Memoize {
+	// A thread-local cache generated by the macro.
+	ThreadLocal { limit: Option<Expr> },
+	// A cache supplied by the user.
+	Cache(Expr),
+}
+
+// One `key = value` inside an option's parentheses.
+struct KeyValue {
+	key: Ident,
+	value: Expr,
+}
+
+impl Parse for KeyValue {
+	fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+		let key = input.parse()?;
+		input.parse::<Token![=]>()?;
+		let value = input.parse()?;
+		Ok(Self { key, value })
+	}
+}
+
+impl Parse for Options {
+	fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+		let mut options = Options::default();
+		while !input.is_empty() {
+			let name: Ident = input.parse()?;
+			let content = if input.peek(Paren) {
+				let content;
+				parenthesized!(content in input);
+				Some(content)
+			} else {
+				None
+			};
+			match (name.to_string().as_str(), content) {
+				("abortable", None) => options.abortable = true,
+				("detect_cycles", None) => options.detect_cycles = true,
+				("debug_result", None) => options.debug_result = true,
+				("state", Some(content)) => options.state = Some(content.parse()?),
+				("debug_args", content) => {
+					let mut names = Vec::new();
+					if let Some(content) = content {
+						let selected: Punctuated<Ident, Comma> =
+							Punctuated::parse_terminated(&content)?;
+						names.extend(selected);
+					}
+					options.debug_args = Some(names);
+				}
+				("calls", Some(content)) => {
+					let paths: Punctuated<Path, Comma> = Punctuated::parse_terminated(&content)?;
+					options.calls.get_or_insert_with(Vec::new).extend(paths);
+				}
+				("memoize", content) => {
+					let mut memoize = Memoize::ThreadLocal { limit: None };
+					if let Some(content) = content {
+						let kvs: Punctuated<KeyValue, Comma> =
+							Punctuated::parse_terminated(&content)?;
+						for kv in kvs {
+							match (kv.key.to_string().as_str(), &mut memoize) {
+								("limit", Memoize::ThreadLocal { limit }) => {
+									*limit = Some(kv.value)
+								}
+								("cache", Memoize::ThreadLocal { limit: None }) => {
+									memoize = Memoize::Cache(kv.value)
+								}
+								_ => {
+									return Err(Error::new(
+										kv.key.span(),
+										"Decurse: expected `limit = ...` or `cache = ...`.",
+									))
+								}
+							}
+						}
+					}
+					options.memoize = Some(memoize);
+				}
+				_ => return Err(Error::new(name.span(), "Decurse: unknown option.")),
+			}
+			if !input.is_empty() {
+				input.parse::<Comma>()?;
+			}
+		}
+		Ok(options)
+	}
+}
+
+// The name of the hidden function that other functions call through the `calls` option.
+fn inner_ident(name: &Ident) -> Ident {
+	Ident::new(&format!("__decurse_{}", name), name.span())
+}
+
+// The name of the frames of the function, as shown by `decurse::stack`.
+fn frame_name(name: &Ident) -> TokenStream {
+	let suffix = format!("::{}", name);
+	quote!(::core::concat!(::core::module_path!(), #suffix))
+}
+
+// The name of the `Recurse` handle in the body.
+fn rec_ident() -> Ident {
+	Ident::new("__decurse_rec", Span::call_site())
+}
+
 fn remove_lifetimes(sig: &Signature) -> Generics {
 	let mut generics = sig.generics.clone();
 	generics.params = generics
@@ -80,6 +212,9 @@ impl Fold for ArgChecker {
 
 struct Folder {
 	use_unsound_impl: bool,
+	plain: bool,
+	name: Ident,
+	calls: Vec<Path>,
 	sig: Signature,
 	closure_nested: usize,
 	async_nested: usize,
@@ -88,9 +223,18 @@ struct Folder {
 }
 
 impl Folder {
-	fn new(sig: Signature, use_unsound_impl: bool) -> Self {
+	fn new(
+		name: Ident,
+		calls: Vec<Path>,
+		sig: Signature,
+		use_unsound_impl: bool,
+		plain: bool,
+	) -> Self {
 		Self {
 			use_unsound_impl,
+			plain,
+			name,
+			calls,
 			sig,
 			closure_nested: 0,
 			async_nested: 0,
@@ -100,13 +244,56 @@ impl Folder {
 	}
 	fn generate_call(&self, args: &Punctuated<Expr, Comma>) -> Expr {
 		let func = &self.sig.ident;
+		#[allow(non_snake_case)]
+		let REC = rec_ident();
 		let generics_wo_lt = remove_lifetimes(&self.sig);
 		let spi = generics_wo_lt.split_for_impl();
 		let tbfs = &spi.1.as_turbofish();
 		if self.use_unsound_impl {
 			parse_quote!(::decurse::for_macro_only_recurse_unsound!(#func#tbfs, (#args)))
+		} else if self.plain {
+			let frame_name = frame_name(func);
+			parse_quote!(::decurse::for_macro_only_recurse_sound!(#frame_name, #func#tbfs, (#args)))
 		} else {
-			parse_quote!(::decurse::for_macro_only_recurse_sound!(#func#tbfs, (#args)))
+			let args = args.iter();
+			parse_quote!(#REC.call((#(#args,)*)).await)
+		}
+	}
+	// Call another decursed function's recursion, so it runs on our heap stack.
+	fn generate_other_call(&self, func: &ExprPath, args: &Punctuated<Expr, Comma>) -> Expr {
+		#[allow(non_snake_case)]
+		let REC = rec_ident();
+		let mut func = func.clone();
+		// UNWRAP Safety: A path always has at least one segment.
+		let last = func.path.segments.last_mut().unwrap();
+		last.ident = inner_ident(&last.ident);
+		let args = args.iter();
+		parse_quote!(#REC.call_with(&#func(), (#(#args,)*)).await)
+	}
+	fn fold_args(&mut self, args: &Punctuated<Expr, Comma>) -> Punctuated<Expr, Comma> {
+		args.clone()
+			.into_iter()
+			.map(|arg| self.fold_expr(arg))
+			.collect()
+	}
+	fn check_nesting(&mut self, ident: &Ident) {
+		if self.closure_nested > 0 {
+			self.errors.push(Error::new(
+				ident.span(),
+				"Decurse: recursive call inside closure not supported.",
+			));
+		}
+		if self.async_nested > 0 {
+			self.errors.push(Error::new(
+				ident.span(),
+				"Decurse: recursive call inside async block not supported.",
+			));
+		}
+		if self.fn_nested > 0 {
+			self.errors.push(Error::new(
+				ident.span(),
+				"Decurse: recursive call in sub-function not supported.",
+			))
 		}
 	}
 }
@@ -118,26 +305,18 @@ impl Fold for Folder {
 				if let Expr::Path(p) = &*c.func {
 					let ident = &p.path.segments.first().unwrap().ident;
 					let l = p.path.segments.len();
-					if l == 1 && ident == &self.sig.ident {
-						if self.closure_nested > 0 {
-							self.errors.push(Error::new(
-								ident.span(),
-								"Decurse: recursive call inside closure not supported.",
-							));
-						}
-						if self.async_nested > 0 {
-							self.errors.push(Error::new(
-								ident.span(),
-								"Decurse: recursive call inside async block not supported.",
-							));
-						}
-						if self.fn_nested > 0 {
-							self.errors.push(Error::new(
-								ident.span(),
-								"Decurse: recursive call in sub-function not supported.",
-							))
-						}
-						return self.generate_call(&c.args);
+					if l == 1 && ident == &self.name {
+						self.check_nesting(ident);
+						let args = self.fold_args(&c.args);
+						return self.generate_call(&args);
+					}
+					// The whole path must match, so that `calls(new)` doesn't catch `Vec::new()`.
+					if p.qself.is_none() && self.calls.contains(&p.path) {
+						// UNWRAP Safety: A path always has at least one segment.
+						let last_ident = &p.path.segments.last().unwrap().ident;
+						self.check_nesting(last_ident);
+						let args = self.fold_args(&c.args);
+						return self.generate_other_call(p, &args);
 					}
 				}
 				fold_expr(self, node)
@@ -165,19 +344,23 @@ impl Fold for Folder {
 	}
 }
 
-fn generate(mut new: ItemFn, use_unsound_impl: bool) -> Result<TokenStream, Error> {
+fn generate(
+	mut new: ItemFn,
+	options: Options,
+	use_unsound_impl: bool,
+) -> Result<TokenStream, Error> {
 	// Extracting infos
+	// The attributes go on the wrapper, not on the async function nested in it.
+	let attrs = std::mem::take(&mut new.attrs);
+	let vis = new.vis.clone();
 	let name = new.sig.ident.clone();
-	let generics_wo_lt = remove_lifetimes(&new.sig);
-	let spi = generics_wo_lt.split_for_impl();
-	let tbfs = &spi.1.as_turbofish();
 	let mut wrapping_sig = new.sig.clone();
 	wrapping_sig
 		.inputs
 		.iter_mut()
 		.enumerate()
-		.for_each(|(i, a)| match a {
-			FnArg::Typed(t) => {
+		.for_each(|(i, a)| {
+			if let FnArg::Typed(t) = a {
 				let ident = Ident::new(&format!("arg_{}", i), Span::call_site());
 				let id = PatIdent {
 					attrs: Vec::new(),
@@ -186,19 +369,88 @@ fn generate(mut new: ItemFn, use_unsound_impl: bool) -> Result<TokenStream, Erro
 					ident,
 					subpat: None,
 				};
-				t.pat = Box::new(Pat::Ident(id));
+				*t.pat = Pat::Ident(id);
 			}
-			_ => {}
 		});
-	let arg_names =
-		(0..new.sig.inputs.len()).map(|i| Ident::new(&format!("arg_{}", i), Span::call_site()));
+	let arg_names: Vec<Ident> = (0..new.sig.inputs.len())
+		.map(|i| Ident::new(&format!("arg_{}", i), Span::call_site()))
+		.collect();
+
+	// A generator yields its items, so the recursion itself has no output.
+	if options.generator {
+		if let ReturnType::Default = new.sig.output {
+			return Err(Error::new_spanned(
+				&new.sig,
+				"Decurse: generator must return decurse::Generator<Item>.",
+			));
+		}
+		if options.abortable || options.memoize.is_some() || options.state.is_some() {
+			return Err(Error::new(
+				Span::call_site(),
+				"Decurse: abortable, memoize and state options not supported by generators.",
+			));
+		}
+		new.sig.output = ReturnType::Default;
+	}
 
 	// Modifying signature
 	new.vis = Visibility::Inherited;
 	new.sig.asyncness = Some(Token!(async)(Span::call_site()));
 
 	// Modifying body
-	let mut folder = Folder::new(new.sig.clone(), use_unsound_impl);
+	if use_unsound_impl {
+		if options.calls.is_some() {
+			return Err(Error::new(
+				Span::call_site(),
+				"Decurse: calls option not supported by decurse_unsound.",
+			));
+		}
+		if options.memoize.is_some() {
+			return Err(Error::new(
+				Span::call_site(),
+				"Decurse: memoize option not supported by decurse_unsound.",
+			));
+		}
+		if options.detect_cycles {
+			return Err(Error::new(
+				Span::call_site(),
+				"Decurse: detect_cycles option not supported by decurse_unsound.",
+			));
+		}
+		if options.debug_args.is_some() || options.debug_result {
+			return Err(Error::new(
+				Span::call_site(),
+				"Decurse: debug_args and debug_result options not supported by decurse_unsound.",
+			));
+		}
+		if let Some(state) = &options.state {
+			return Err(Error::new_spanned(
+				state,
+				"Decurse: state option not supported by decurse_unsound.",
+			));
+		}
+	}
+	if let Some(Memoize::ThreadLocal { .. }) = &options.memoize {
+		if let Some(param) = new
+			.sig
+			.generics
+			.params
+			.iter()
+			.find(|p| !matches!(p, syn::GenericParam::Lifetime(_)))
+		{
+			return Err(Error::new_spanned(
+				param,
+				"Decurse: memoize on generic function needs a cache (`memoize(cache = ...)`).",
+			));
+		}
+	}
+	let mut folder = Folder::new(
+		name.clone(),
+		options.calls.clone().unwrap_or_default(),
+		new.sig.clone(),
+		use_unsound_impl,
+		options.is_plain(),
+	);
 	let stmts: Vec<Stmt> = new
 		.block
 		.stmts
@@ -210,40 +462,410 @@ fn generate(mut new: ItemFn, use_unsound_impl: bool) -> Result<TokenStream, Erro
 		return Err(e);
 	}
 
-	// Create wrapper
-	if use_unsound_impl {
-		Ok(quote! {
-			#wrapping_sig {
+	let output = if use_unsound_impl {
+		generate_unsound(new, &options, &attrs, &vis, &wrapping_sig, &arg_names)
+	} else {
+		generate_sound(new, &options, &attrs, &vis, &wrapping_sig, &arg_names)?
+	};
+	Ok(output)
+}
+
+// The attributes of the function that the items generated next to its wrapper get too.
+fn sibling_attrs(attrs: &[Attribute]) -> Vec<Attribute> {
+	attrs
+		.iter()
+		.filter(|attr| attr.path.is_ident("doc") || attr.path.is_ident("cfg"))
+		.cloned()
+		.collect()
+}
+
+fn abortable_signature(wrapping_sig: &Signature) -> Signature {
+	let mut abortable_sig = wrapping_sig.clone();
+	let name = &wrapping_sig.ident;
+	abortable_sig.ident = Ident::new(&format!("{}_abortable", name), name.span());
+	abortable_sig
+		.inputs
+		.push(parse_quote!(abort_handle: ::decurse::AbortHandle));
+	let ret = return_type(wrapping_sig);
+	abortable_sig.output = parse_quote!(-> ::core::result::Result<#ret, ::decurse::Cancelled>);
+	abortable_sig
+}
+
+fn return_type(sig: &Signature) -> Type {
+	match &sig.output {
+		ReturnType::Default => parse_quote!(()),
+		ReturnType::Type(_, ty) => (**ty).clone(),
+	}
+}
+
+// The format string and tuple indices for printing the selected arguments as `name = value`.
+fn debug_args_selected(selected: &[Ident], pats: &[Pat]) -> Result<(String, Vec<Index>), Error> {
+	let mut formats = Vec::new();
+	let mut indices = Vec::new();
+	for name in selected {
+		let index = pats.iter().position(|pat| match pat {
+			Pat::Ident(PatIdent { ident, .. }) => ident == name,
+			_ => false,
+		});
+		match index {
+			Some(index) => {
+				formats.push(format!("{} = {{:?}}", name));
+				indices.push(Index::from(index));
+			}
+			None => {
+				return Err(Error::new(
+					name.span(),
+					"Decurse: debug_args can only select arguments of the function.",
+				))
+			}
+		}
+	}
+	Ok((formats.join(", "), indices))
+}
+
+// The sound version turns the function into an async function, executed on the runtime.
+// A plain function is nested in each wrapper.
+// With options, it is instead turned into a `Recursion` whose argument is the tuple of all the arguments.
+fn generate_sound(
+	mut new: ItemFn,
+	options: &Options,
+	attrs: &[Attribute],
+	vis: &Visibility,
+	wrapping_sig: &Signature,
+	arg_names: &[Ident],
+) -> Result<TokenStream, Error> {
+	let mut output = quote!();
+	let siblings = sibling_attrs(attrs);
+	let (run, run_abortable, call) = if options.is_plain() {
+		let name = new.sig.ident.clone();
+		let generics_wo_lt = remove_lifetimes(&new.sig);
+		let spi = generics_wo_lt.split_for_impl();
+		let tbfs = spi.1.as_turbofish();
+		let frame_name = frame_name(&name);
+		new.block.stmts.insert(
+			0,
+			parse_quote!(let __decurse_frame = ::decurse::for_macro_only::sound::enter(#frame_name);),
+		);
+		let fut = quote!(#name #tbfs (#(#arg_names),*));
+		(
+			quote!({
+				#new
+				::decurse::for_macro_only::sound::execute(#frame_name, #fut)
+			}),
+			quote!({
 				#new
-				::decurse::for_macro_only::unsound::execute(#name#tbfs(#(#arg_names),*))
+				::decurse::for_macro_only::sound::execute_abortable(#frame_name, #fut, abort_handle)
+			}),
+			quote!({
+				#new
+				::decurse::for_macro_only::sound::recurse(#frame_name, #fut)
+			}),
+		)
+	} else {
+		let recursion =
+			generate_recursion(new, options, &siblings, vis, wrapping_sig, &mut output)?;
+		(
+			quote!(#recursion.run((#(#arg_names,)*))),
+			quote!(#recursion.run_abortable((#(#arg_names,)*), abort_handle)),
+			quote!(#recursion.call((#(#arg_names,)*))),
+		)
+	};
+
+	// With state, the wrappers take it as their first argument and put it in scope while running.
+	let mut wrapping_sig = wrapping_sig.clone();
+	let (run, run_abortable) = match &options.state {
+		None if options.generator => (
+			quote!(::decurse::Generator::new(async move { #call.await })),
+			run_abortable,
+		),
+		None => (run, run_abortable),
+		Some(state) => {
+			wrapping_sig
+				.inputs
+				.insert(0, parse_quote!(state: &mut #state));
+			(
+				quote!(::decurse::State::<#state>::scope(state, || #run)),
+				quote!(::decurse::State::<#state>::scope(state, || #run_abortable)),
+			)
+		}
+	};
+
+	output.extend(quote! {
+		#(#attrs)*
+		#vis #wrapping_sig {
+			#run
+		}
+	});
+
+	if options.abortable {
+		let abortable_sig = abortable_signature(&wrapping_sig);
+		output.extend(quote! {
+			#(#siblings)*
+			#vis #abortable_sig {
+				#run_abortable
 			}
+		});
+	}
+	Ok(output)
+}
+
+// The expression making the `Recursion`, with the items it needs put in `output`.
+fn generate_recursion(
+	mut new: ItemFn,
+	options: &Options,
+	siblings: &[Attribute],
+	vis: &Visibility,
+	wrapping_sig: &Signature,
+	output: &mut TokenStream,
+) -> Result<TokenStream, Error> {
+	let name = new.sig.ident.clone();
+	let generics_wo_lt = remove_lifetimes(&new.sig);
+	let spi = generics_wo_lt.split_for_impl();
+	let tbfs = spi.1.as_turbofish();
+	let (pats, tys): (Vec<Pat>, Vec<Type>) = new
+		.sig
+		.inputs
+		.iter()
+		.filter_map(|a| match a {
+			FnArg::Typed(t) => Some(((*t.pat).clone(), (*t.ty).clone())),
+			FnArg::Receiver(_) => None,
 		})
+		.unzip();
+	let args_ty: Type = parse_quote!((#(#tys,)*));
+	let ret = return_type(&new.sig);
+	#[allow(non_snake_case)]
+	let REC = rec_ident();
+	new.sig.inputs = parse_quote!(
+		#REC: ::decurse::Recurse<#args_ty, #ret>,
+		(#(#pats,)*): #args_ty
+	);
+	let generics = new.sig.generics.clone();
+
+	let memoize = match &options.memoize {
+		None => quote!(),
+		Some(Memoize::Cache(cache)) => quote!(.memoize(&#cache)),
+		Some(Memoize::ThreadLocal { limit }) => {
+			let cache = match limit {
+				Some(limit) => quote!(::decurse::memo::Cache::with_limit(#limit)),
+				None => quote!(::decurse::memo::Cache::new()),
+			};
+			quote!(.memoize({
+				::std::thread_local! {
+					static CACHE: ::decurse::memo::Cache<#args_ty, #ret> = #cache;
+				}
+				&CACHE
+			}))
+		}
+	};
+
+	let detect_cycles = if options.detect_cycles {
+		quote!(.detect_cycles_by_body())
+	} else {
+		quote!()
+	};
+
+	let frame_name = frame_name(&wrapping_sig.ident);
+	let named = quote!(.named(#frame_name));
+
+	// Print the arguments separated by commas, rather than as a tuple.
+	let debug_args = match &options.debug_args {
+		None => quote!(),
+		Some(selected) => {
+			let (format, indices) = if selected.is_empty() {
+				let format = vec!["{:?}"; tys.len()].join(", ");
+				(format, (0..tys.len()).map(Index::from).collect())
+			} else {
+				debug_args_selected(selected, &pats)?
+			};
+			quote!(.debug_args_with(|args: &#args_ty| ::std::format!(#format, #(args.#indices),*)))
+		}
+	};
+
+	let debug_result = if options.debug_result {
+		quote!(.debug_result())
 	} else {
-		Ok(quote! {
-			#wrapping_sig {
+		quote!()
+	};
+
+	let options_chain = quote!(#named #memoize #detect_cycles #debug_args #debug_result);
+	let recursion = match &options.calls {
+		// The body is nested in the recursion constructor, as it is in the wrapper for a plain function,
+		// so that it works the same in an `impl` block.
+		None => quote!({
+			#new
+			::decurse::Recursion::new(#name #tbfs) #options_chain
+		}),
+		// With `calls`, other functions call the recursion through a hidden function next to the wrapper.
+		Some(calls) => {
+			let inner_name = inner_ident(&name);
+			let where_clause = &generics.where_clause;
+			// Calls to `Self::...` mean we are in an `impl` block.
+			// The body is then a hidden associated function, since a nested one couldn't use `Self`.
+			let in_impl = calls
+				.iter()
+				.any(|path| matches!(path.segments.first(), Some(s) if s.ident == "Self"));
+			let body = if in_impl {
+				let body_name = Ident::new(&format!("__decurse_body_{}", name), name.span());
+				new.sig.ident = body_name.clone();
+				output.extend(quote! {
+					#(#siblings)*
+					#[doc(hidden)]
+					#new
+				});
+				quote!({
+					::decurse::Recursion::new(Self::#body_name #tbfs) #options_chain
+				})
+			} else {
+				quote!({
+					#new
+					::decurse::Recursion::new(#name #tbfs) #options_chain
+				})
+			};
+			output.extend(quote! {
+				#(#siblings)*
+				#[doc(hidden)]
+				#vis fn #inner_name #generics () -> ::decurse::Recursion<#args_ty, #ret> #where_clause #body
+			});
+			if in_impl {
+				quote!(Self::#inner_name #tbfs ())
+			} else {
+				quote!(#inner_name #tbfs ())
+			}
+		}
+	};
+
+	Ok(recursion)
+}
+
+// The unsound version turns the function into an async function, executed on a `PinnedVec` of its own futures.
+// The async function is nested in each wrapper.
+fn generate_unsound(
+	new: ItemFn,
+	options: &Options,
+	attrs: &[Attribute],
+	vis: &Visibility,
+	wrapping_sig: &Signature,
+	arg_names: &[Ident],
+) -> TokenStream {
+	let name = new.sig.ident.clone();
+	let generics_wo_lt = remove_lifetimes(&new.sig);
+	let spi = generics_wo_lt.split_for_impl();
+	let tbfs = spi.1.as_turbofish();
+	let mut output = quote! {
+		#(#attrs)*
+		#vis #wrapping_sig {
+			#new
+			::decurse::for_macro_only::unsound::execute(#name #tbfs (#(#arg_names),*))
+		}
+	};
+
+	if options.abortable {
+		let abortable_sig = abortable_signature(wrapping_sig);
+		let siblings = sibling_attrs(attrs);
+		output.extend(quote! {
+			#(#siblings)*
+			#vis #abortable_sig {
 				#new
-				::decurse::for_macro_only::sound::execute(#name#tbfs(#(#arg_names),*))
+				::decurse::for_macro_only::unsound::execute_abortable(#name #tbfs (#(#arg_names),*), abort_handle)
 			}
-		})
+		});
 	}
+	output
 }
 
 #[proc_macro_attribute]
 pub fn decurse_sound(
-	_attr: proc_macro::TokenStream,
+	attr: proc_macro::TokenStream,
 	item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+	let options = parse_macro_input!(attr as Options);
 	let parsed = parse_macro_input!(item as Parsed);
-	let generated = generate(parsed.0, false).unwrap_or_else(Error::into_compile_error);
+	let generated = generate(parsed.0, options, false).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_attribute]
+pub fn decurse_generator(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let mut options = parse_macro_input!(attr as Options);
+	options.generator = true;
+	let parsed = parse_macro_input!(item as Parsed);
+	let generated = generate(parsed.0, options, false).unwrap_or_else(Error::into_compile_error);
 	generated.into()
 }
 
 #[proc_macro_attribute]
 pub fn decurse_unsound(
-	_attr: proc_macro::TokenStream,
+	attr: proc_macro::TokenStream,
 	item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
+	let options = parse_macro_input!(attr as Options);
 	let parsed = parse_macro_input!(item as Parsed);
-	let generated = generate(parsed.0, true).unwrap_or_else(Error::into_compile_error);
+	let generated = generate(parsed.0, options, true).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepDrop, attributes(deep))]
+pub fn derive_deep_drop(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_drop(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepClone, attributes(deep))]
+pub fn derive_deep_clone(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_clone(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepPartialEq, attributes(deep))]
+pub fn derive_deep_partial_eq(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_partial_eq(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepEq, attributes(deep))]
+pub fn derive_deep_eq(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_eq(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepPartialOrd, attributes(deep))]
+pub fn derive_deep_partial_ord(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_partial_ord(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepOrd, attributes(deep))]
+pub fn derive_deep_ord(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_ord(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepHash, attributes(deep))]
+pub fn derive_deep_hash(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_hash(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(DeepDebug, attributes(deep))]
+pub fn derive_deep_debug(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_deep_debug(input).unwrap_or_else(Error::into_compile_error);
+	generated.into()
+}
+
+#[proc_macro_derive(Recursive, attributes(deep))]
+pub fn derive_recursive(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let input = parse_macro_input!(item as DeriveInput);
+	let generated = deep::derive_recursive(input).unwrap_or_else(Error::into_compile_error);
 	generated.into()
 }
@@ -93,6 +93,10 @@ impl<T> PinnedVec<T> {
 	pub fn len(&self) -> usize {
 		self.len
 	}
+	/// Whether the PinnedVec contains no element.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
 	/// Get the current capacity of the PinnedVec
 	/// Pushing within capacity means no extra allocation.
 	/// Pushing over capacity will cause allocation, increasing capacity.